@@ -0,0 +1,143 @@
+-- 多租户与 RBAC 扩展的表结构变更 (MySQL), 在已有 user / role / access / user_role / role_access 表上执行一次
+-- 执行后重启服务, 启动时 check_adm 会补齐 ADMIN 和接口权限
+
+-- 已有表增加租户, 现有数据归入默认租户 0
+alter table user add column tenant_id int not null default 0, add index idx_user_tenant (tenant_id);
+alter table role add column tenant_id int not null default 0, add index idx_role_tenant (tenant_id);
+alter table access add column tenant_id int not null default 0, add index idx_access_tenant (tenant_id);
+alter table user_role add column tenant_id int not null default 0, add index idx_user_role_user (user_id), add index idx_user_role_role (role_id);
+alter table role_access add column tenant_id int not null default 0, add index idx_role_access_role (role_id), add index idx_role_access_access (access_id);
+
+-- 角色数据范围 1: SELF 2: DEPT 3: TENANT 4: ALL
+alter table role add column data_scope tinyint not null default 3;
+update role set data_scope = 4 where name = 'ADMIN' and tenant_id = 0;
+
+-- 部门, path 为从顶级部门到自身的id路径, 如 `/1/3/`
+create table if not exists org_unit (
+    id int not null auto_increment,
+    create_time datetime not null,
+    update_time datetime not null,
+    name varchar(64) not null,
+    parent_id int not null default 0,
+    path varchar(512) not null,
+    head_user_id int null,
+    create_by int not null,
+    status tinyint not null default 1,
+    tenant_id int not null default 0,
+    primary key (id),
+    index idx_org_unit_path (tenant_id, path)
+);
+
+create table if not exists user_org (
+    id int not null auto_increment,
+    user_id int not null,
+    org_id int not null,
+    tenant_id int not null default 0,
+    primary key (id),
+    index idx_user_org_user (user_id),
+    index idx_user_org_org (org_id)
+);
+
+create table if not exists org_role (
+    id int not null auto_increment,
+    org_id int not null,
+    role_id int not null,
+    tenant_id int not null default 0,
+    primary key (id),
+    index idx_org_role_org (org_id),
+    index idx_org_role_role (role_id)
+);
+
+-- 用户组
+create table if not exists user_group (
+    id int not null auto_increment,
+    create_time datetime not null,
+    update_time datetime not null,
+    name varchar(64) not null,
+    create_by int not null,
+    status tinyint not null default 1,
+    tenant_id int not null default 0,
+    primary key (id),
+    index idx_user_group_tenant (tenant_id)
+);
+
+create table if not exists user_group_member (
+    id int not null auto_increment,
+    group_id int not null,
+    user_id int not null,
+    tenant_id int not null default 0,
+    primary key (id),
+    index idx_user_group_member_group (group_id),
+    index idx_user_group_member_user (user_id)
+);
+
+create table if not exists group_role (
+    id int not null auto_increment,
+    group_id int not null,
+    role_id int not null,
+    tenant_id int not null default 0,
+    primary key (id),
+    index idx_group_role_group (group_id),
+    index idx_group_role_role (role_id)
+);
+
+-- 角色约束 1: 互斥 2: 数量限制, role_ids 为 json 数组
+create table if not exists role_constraint (
+    id int not null auto_increment,
+    create_time datetime not null,
+    update_time datetime not null,
+    name varchar(64) not null,
+    constraint_type tinyint not null,
+    role_ids text not null,
+    max_count int not null default 0,
+    create_by int not null,
+    status tinyint not null default 1,
+    tenant_id int not null default 0,
+    primary key (id),
+    index idx_role_constraint_tenant (tenant_id)
+);
+
+-- 策略 effect 1: 允许 0: 拒绝, conditions 为 json 条件列表
+create table if not exists policy (
+    id int not null auto_increment,
+    create_time datetime not null,
+    update_time datetime not null,
+    name varchar(64) not null,
+    access_id int not null,
+    effect tinyint not null,
+    conditions text not null,
+    create_by int not null,
+    status tinyint not null default 1,
+    tenant_id int not null default 0,
+    primary key (id),
+    index idx_policy_access (access_id)
+);
+
+-- 菜单 menu_type 1: 页面 2: 按钮, access_id 为空时所有用户可见
+create table if not exists menu (
+    id int not null auto_increment,
+    create_time datetime not null,
+    update_time datetime not null,
+    name varchar(64) not null,
+    parent_id int not null default 0,
+    menu_type tinyint not null,
+    path varchar(255) null,
+    icon varchar(255) null,
+    sort int not null default 0,
+    access_id int null,
+    create_by int not null,
+    status tinyint not null default 1,
+    tenant_id int not null default 0,
+    primary key (id),
+    index idx_menu_tenant (tenant_id)
+);
+
+-- 缓存变更事件, 与业务数据在同一事务中写入, 后台按 id 顺序处理后删除
+create table if not exists cache_outbox (
+    id bigint not null auto_increment,
+    create_time datetime not null,
+    event_type tinyint not null,
+    target_id int not null,
+    tenant_id int not null default 0,
+    primary key (id)
+);
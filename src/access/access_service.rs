@@ -1,22 +1,29 @@
 use super::{AccessListQuery, AccessMapItem, AccessUpdateData, CreateAccessData};
 use crate::{
-    access::{check_access_by_id, AccessListListData},
-    entity::access_entity::AccessEntity,
+    access::{check_access_by_id, check_access_deletable, AccessListListData},
+    cache::smembers_ids,
+    entity::{access_entity::AccessEntity, role_access_entity::RoleAccessEntity},
+    operation::spawn_operation,
     response::{MyError, ResponseBody},
-    user::check_user_by_user_id,
+    role::role_access_service::unbind_access_from_cache,
+    user::{
+        check_user_by_user_id,
+        user_role_service::{get_role_users, sync_user_auth},
+        OptionData,
+    },
     util::{
-        common::{get_transaction_tx, rds_str_to_list, RedisKeys},
-        structs::{CreateByData, Status},
+        common::{get_jwt_from_req, get_tenant_id, get_transaction_tx, rds_str_to_list, RedisKeys},
+        data_scope::{append_scope_filter, get_user_data_scope, scope_table},
+        outbox::add_events,
+        structs::{CreateByData, OutboxEvent, Status},
         sync_opt::{self, DelOptData, SyncOptData},
     },
     RB,
 };
-use actix_web::{delete, get, post, web, Responder};
+use actix_web::{delete, get, post, web, HttpRequest, Responder};
 use rbs::to_value;
-use redis::AsyncCommands;
 use rs_service_util::{
     auth::gen_access_value,
-    redis_conn,
     sql_tool::{SqlTool, SqlToolPageData},
     time::get_current_time_fmt,
 };
@@ -26,8 +33,15 @@ use rs_service_util::{
     responses( (status = 200))
 )]
 #[post("/create_access")]
-async fn create_access(req_data: web::Json<CreateAccessData>) -> Result<impl Responder, MyError> {
-    if check_user_by_user_id(req_data.create_by).await.is_none() {
+async fn create_access(
+    req_data: web::Json<CreateAccessData>,
+    req: HttpRequest,
+) -> Result<impl Responder, MyError> {
+    let tenant_id = get_tenant_id(&req);
+    if check_user_by_user_id(req_data.create_by, tenant_id)
+        .await
+        .is_none()
+    {
         return Err(MyError::UserNotExist);
     }
 
@@ -39,6 +53,7 @@ async fn create_access(req_data: web::Json<CreateAccessData>) -> Result<impl Res
         create_by: req_data.create_by,
         status: Status::ACTIVE as i8,
         value: 0,
+        tenant_id,
     };
 
     let tx = get_transaction_tx().await.unwrap();
@@ -72,6 +87,7 @@ async fn create_access(req_data: web::Json<CreateAccessData>) -> Result<impl Res
             sync_opt::sync(SyncOptData::default(
                 RedisKeys::AccessMapIds,
                 RedisKeys::AccessMap,
+                tenant_id,
                 item.id,
                 item,
             ))
@@ -87,9 +103,16 @@ async fn create_access(req_data: web::Json<CreateAccessData>) -> Result<impl Res
     responses( (status = 200))
 )]
 #[post("/get_access_list")]
-async fn get_access_list(req_data: web::Json<AccessListQuery>) -> impl Responder {
+async fn get_access_list(req_data: web::Json<AccessListQuery>, req: HttpRequest) -> impl Responder {
+    let tenant_id = get_tenant_id(&req);
+    let jwt_user = get_jwt_from_req(&req);
+    let scope = get_user_data_scope(&jwt_user).await;
     let ex_db = RB.acquire().await.expect("msg");
-    let mut tool = SqlTool::init("select * from access", "order by create_time desc");
+    let table = scope_table("access", &scope, "create_by", &jwt_user);
+    let mut tool = SqlTool::init(
+        &format!("select * from {table}"),
+        "order by create_time desc",
+    );
 
     if let Some(name) = req_data.name.clone() {
         tool.append_sql_filed("name", to_value!(name));
@@ -100,6 +123,7 @@ async fn get_access_list(req_data: web::Json<AccessListQuery>) -> impl Responder
     if let Some(create_by) = req_data.create_by {
         tool.append_sql_filed("create_by", to_value!(create_by));
     }
+    append_scope_filter(&mut tool, &scope, "create_by", &jwt_user, tenant_id);
     tool.append_sql_filed("status", to_value!(1));
 
     let page_sql = tool.gen_page_sql(req_data.page_no, req_data.take);
@@ -130,7 +154,7 @@ async fn get_access_list(req_data: web::Json<AccessListQuery>) -> impl Responder
     }
     let conf = SqlToolPageData {
         ex_db,
-        table: "access".to_string(),
+        table,
         records,
         page_no: req_data.page_no as u64,
         page_size: req_data.take as u64,
@@ -147,8 +171,10 @@ async fn get_access_list(req_data: web::Json<AccessListQuery>) -> impl Responder
 #[post("/update_access")]
 pub async fn update_access_by_id(
     req_data: web::Json<AccessUpdateData>,
+    req: HttpRequest,
 ) -> Result<impl Responder, MyError> {
-    match check_access_by_id(req_data.id).await {
+    let tenant_id = get_tenant_id(&req);
+    match check_access_by_id(req_data.id, tenant_id).await {
         None => {
             return Err(MyError::AccessNotExist);
         }
@@ -175,6 +201,7 @@ pub async fn update_access_by_id(
             sync_opt::sync(SyncOptData::default(
                 RedisKeys::AccessMapIds,
                 RedisKeys::AccessMap,
+                tenant_id,
                 item.id,
                 item,
             ))
@@ -190,36 +217,76 @@ pub async fn update_access_by_id(
     responses( (status = 200))
 )]
 #[delete("/{id}")]
-pub async fn delete_access(id: web::Path<i32>) -> Result<impl Responder, MyError> {
+pub async fn delete_access(
+    id: web::Path<i32>,
+    req: HttpRequest,
+) -> Result<impl Responder, MyError> {
+    let tenant_id = get_tenant_id(&req);
     let id = id.into_inner();
+    let mut access = check_access_by_id(id, tenant_id)
+        .await
+        .ok_or(MyError::AccessNotExist)?;
+    check_access_deletable(&access)?;
 
-    match check_access_by_id(id).await {
-        None => {
-            return Err(MyError::AccessNotExist);
-        }
-        Some(mut access) => {
-            access.status = Status::DEACTIVE as i8;
-            access.update_time = get_current_time_fmt();
-            let tx = get_transaction_tx().await.expect("get tx err");
-            let update_res = AccessEntity::update_by_column(&tx, &access, "id").await;
-            tx.commit().await.expect("msg");
+    let ex = RB.acquire().await.expect("msg");
+    let bind_list: Vec<RoleAccessEntity> = RoleAccessEntity::select_by_column(&ex, "access_id", id)
+        .await
+        .expect("查询角色权限失败");
+    drop(ex);
+    let role_ids: Vec<i32> = bind_list.into_iter().map(|val| val.role_id).collect();
 
-            if let Err(rbs::Error::E(error)) = update_res {
-                log::error!(" {} {error}", MyError::UpdateAccessError);
-                tx.rollback().await.expect("rollback error");
-                return Err(MyError::UpdateAccessError);
-            }
+    // 停用权限并解除所有角色绑定
+    access.status = Status::DEACTIVE as i8;
+    access.update_time = get_current_time_fmt();
+    let tx = get_transaction_tx().await.expect("get tx err");
+    let update_res = AccessEntity::update_by_column(&tx, &access, "id").await;
+    if let Err(rbs::Error::E(error)) = update_res {
+        log::error!(" {} {error}", MyError::UpdateAccessError);
+        tx.rollback().await.expect("rollback error");
+        return Err(MyError::UpdateAccessError);
+    }
+    let del_res = RoleAccessEntity::delete_by_column(&tx, "access_id", id).await;
+    if let Err(rbs::Error::E(error)) = del_res {
+        log::error!(" {} {error}", MyError::UpdateAccessError);
+        tx.rollback().await.expect("rollback error");
+        return Err(MyError::UpdateAccessError);
+    }
+    let event_res = add_events(&tx, OutboxEvent::RoleAccess, &role_ids, tenant_id).await;
+    if let Err(rbs::Error::E(error)) = event_res {
+        log::error!(" {} {error}", MyError::UpdateAccessError);
+        tx.rollback().await.expect("rollback error");
+        return Err(MyError::UpdateAccessError);
+    }
+    tx.commit().await.expect("commit error");
 
-            sync_opt::del(DelOptData::default(
-                RedisKeys::AccessMapIds,
-                RedisKeys::AccessMap,
-                vec![id],
-            ))
-            .await;
+    sync_opt::del(DelOptData::default(
+        RedisKeys::AccessMapIds,
+        RedisKeys::AccessMap,
+        tenant_id,
+        vec![id],
+    ))
+    .await;
+
+    let mut users: Vec<OptionData> = vec![];
+    for role_id in role_ids.iter() {
+        unbind_access_from_cache(role_id, &vec![id], tenant_id).await;
+        for user in get_role_users(*role_id).await {
+            if !users.iter().any(|val| val.id == user.id) {
+                users.push(user);
+            }
         }
     }
 
-    Ok(ResponseBody::success("权限更新成功"))
+    // 受影响的用户可能很多, 权限值在后台重新计算
+    let op = spawn_operation(
+        "delete_access",
+        tenant_id,
+        users,
+        move |user: OptionData| async move { sync_user_auth(user.id, tenant_id).await.map(|_| ()) },
+    )
+    .await;
+
+    Ok(ResponseBody::default(Some(op)))
 }
 
 #[utoipa::path(
@@ -227,39 +294,40 @@ pub async fn delete_access(id: web::Path<i32>) -> Result<impl Responder, MyError
     responses( (status = 200))
 )]
 #[get("/access_map")]
-pub async fn get_access_map() -> Result<impl Responder, MyError> {
-    let mut conn = redis_conn!().await;
-    let cache_ids: Vec<i32> = conn
-        .smembers(RedisKeys::AccessMapIds.to_string())
-        .await
-        .expect("msg");
+pub async fn get_access_map(req: HttpRequest) -> Result<impl Responder, MyError> {
+    let tenant_id = get_tenant_id(&req);
+    let cache_ids: Vec<i32> = smembers_ids(&RedisKeys::AccessMapIds.key(tenant_id)).await;
+    let cache_list: Option<Vec<AccessMapItem>> =
+        rds_str_to_list(cache_ids, RedisKeys::AccessMap, tenant_id).await;
+    if let Some(list) = cache_list {
+        return Ok(ResponseBody::default(Some(list)));
+    }
 
-    if cache_ids.is_empty() {
-        let list: Vec<AccessMapItem> = get_access().await;
-        for ele in &list {
-            sync_opt::sync(SyncOptData::default(
-                RedisKeys::UserIds,
-                RedisKeys::UserInfo,
+    // 缓存为空或无法解析时从数据库重建
+    let list: Vec<AccessMapItem> = get_access(tenant_id).await;
+    let sync_list = list
+        .iter()
+        .map(|ele| {
+            SyncOptData::default(
+                RedisKeys::AccessMapIds,
+                RedisKeys::AccessMap,
+                tenant_id,
                 ele.id,
                 ele.clone(),
-            ))
-            .await;
-        }
-        Ok(ResponseBody::default(Some(list)))
-    } else {
-        let list: Vec<AccessMapItem> = rds_str_to_list(cache_ids, RedisKeys::AccessMap, |val| {
-            let item: AccessMapItem = serde_json::from_str(&val).expect("msg");
-            item
+            )
         })
-        .await;
-        Ok(ResponseBody::default(Some(list)))
-    }
+        .collect();
+    sync_opt::sync_list(sync_list).await;
+    Ok(ResponseBody::default(Some(list)))
 }
 
-async fn get_access() -> Vec<AccessMapItem> {
+async fn get_access(tenant_id: i32) -> Vec<AccessMapItem> {
     let ex = RB.acquire().await.expect("asdf");
     let list: Vec<AccessMapItem> = ex
-        .query_decode("select id,name,value from access where status=1", vec![])
+        .query_decode(
+            "select id,name,value from access where status=1 and tenant_id = ?",
+            vec![to_value!(tenant_id)],
+        )
         .await
         .expect("msg");
     list
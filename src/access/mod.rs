@@ -1,12 +1,16 @@
-use redis::AsyncCommands;
-use rs_service_util::redis_conn;
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 use utoipa_actix_web::service_config::ServiceConfig;
 
 use crate::{
     entity::access_entity::AccessEntity,
-    util::{common::RedisKeys, structs::CreateByData},
+    rbac::rbac_plan::BUILTIN_NAME,
+    response::MyError,
+    util::{
+        access_guard::RouteAccess,
+        common::{cache_ids_exist, RedisKeys, DEFAULT_TENANT_ID},
+        structs::CreateByData,
+    },
     RB,
 };
 
@@ -23,6 +27,15 @@ pub fn configure() -> impl FnOnce(&mut ServiceConfig) {
     }
 }
 
+/// 接口所需权限, 由 `access_guard` 中间件校验
+pub const ROUTE_ACCESS: &[RouteAccess] = &[
+    RouteAccess::new("POST", "/api/access/create_access", "ACCESS_CREATE"),
+    RouteAccess::new("POST", "/api/access/get_access_list", "ACCESS_LIST"),
+    RouteAccess::new("GET", "/api/access/access_map", "ACCESS_LIST"),
+    RouteAccess::new("POST", "/api/access/update_access", "ACCESS_UPDATE"),
+    RouteAccess::new("DELETE", "/api/access/{id}", "ACCESS_DELETE"),
+];
+
 #[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
 pub struct CreateAccessData {
     pub name: String,
@@ -66,26 +79,32 @@ pub struct AccessValueData {
     pub value: u64,
 }
 
-pub async fn check_access_by_id(id: i32) -> Option<AccessEntity> {
+pub async fn check_access_by_id(id: i32, tenant_id: i32) -> Option<AccessEntity> {
     let ex_db = RB.acquire().await.expect("get db ex error");
-    AccessEntity::select_by_id(&ex_db, id.clone())
+    AccessEntity::select_by_id(&ex_db, id, tenant_id)
         .await
         .expect("权限查询失败")
 }
 
-pub async fn check_access_by_ids(list: &Vec<i32>) -> Option<bool> {
-    let mut conn = redis_conn!().await;
-    for id in list {
-        let in_ids_cache: bool = conn
-            .sismember(RedisKeys::AccessMapIds.to_string(), id)
-            .await
-            .expect("msg");
-        let in_info_cache: bool = conn
-            .hexists(RedisKeys::AccessMap.to_string(), id)
-            .await
-            .expect("msg");
-        if !in_ids_cache && !in_info_cache {
-            let db_role = check_access_by_id(id.clone()).await;
+/// 内置的 ADMIN 权限决定超级管理员, 不能删除
+pub fn check_access_deletable(access: &AccessEntity) -> Result<(), MyError> {
+    if access.name == BUILTIN_NAME {
+        return Err(MyError::BuiltinAccessDelete);
+    }
+    Ok(())
+}
+
+pub async fn check_access_by_ids(list: &Vec<i32>, tenant_id: i32) -> Option<bool> {
+    let in_cache = cache_ids_exist(
+        RedisKeys::AccessMapIds,
+        RedisKeys::AccessMap,
+        tenant_id,
+        list,
+    )
+    .await;
+    for (id, in_cache) in list.iter().zip(in_cache) {
+        if !in_cache {
+            let db_role = check_access_by_id(id.clone(), tenant_id).await;
             if db_role.is_none() {
                 return None;
             }
@@ -93,3 +112,38 @@ pub async fn check_access_by_ids(list: &Vec<i32>) -> Option<bool> {
     }
     Some(true)
 }
+
+/// 获取 ADMIN 权限值, 持有该权限的用户拥有全部权限
+pub async fn get_adm_access_value() -> u64 {
+    let ex_db = RB.acquire().await.expect("get db ex error");
+    let adm_access = AccessEntity::select_by_name(&ex_db, "ADMIN", DEFAULT_TENANT_ID)
+        .await
+        .expect("权限查询失败");
+    adm_access.map(|val| val.value).unwrap_or(0)
+}
+
+/// 获取 ADMIN 权限 id, 只存在于默认租户
+pub async fn get_adm_access_id() -> Option<i32> {
+    let ex_db = RB.acquire().await.expect("get db ex error");
+    let adm_access = AccessEntity::select_by_name(&ex_db, BUILTIN_NAME, DEFAULT_TENANT_ID)
+        .await
+        .expect("权限查询失败");
+    adm_access.and_then(|val| val.id)
+}
+
+#[cfg(test)]
+mod test {
+    use super::check_access_deletable;
+    use crate::{entity::access_entity::AccessEntity, response::MyError};
+
+    #[test]
+    fn test_check_access_deletable() {
+        let mut access = AccessEntity::default_adm_access(1);
+        assert!(matches!(
+            check_access_deletable(&access),
+            Err(MyError::BuiltinAccessDelete)
+        ));
+        access.name = "ROLE_CREATE".to_string();
+        assert!(check_access_deletable(&access).is_ok());
+    }
+}
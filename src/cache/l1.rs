@@ -0,0 +1,227 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// Redis 前的进程内一级缓存
+///
+/// 按 key 保存集合成员或 hash 中读过的字段, key 数量超过容量时淘汰最久未使用的 key;
+/// 失效消息可能丢失, 每个 key 另有过期时间兜底
+pub struct L1Cache {
+    inner: Mutex<Inner>,
+    capacity: usize,
+    ttl: Duration,
+}
+
+#[derive(Default)]
+struct Inner {
+    entries: HashMap<String, Entry>,
+    /// 最近使用的序号
+    tick: u64,
+    /// 每次失效加一, 读 Redis 期间发生过失效的结果不写入
+    generation: u64,
+}
+
+struct Entry {
+    value: Value,
+    expire_at: Instant,
+    used: u64,
+}
+
+enum Value {
+    Set(Vec<String>),
+    /// 字段不存在也缓存, 值为 None
+    Hash(HashMap<String, Option<String>>),
+}
+
+impl L1Cache {
+    pub fn new(capacity: usize, ttl: Duration) -> Self {
+        Self {
+            inner: Mutex::new(Inner::default()),
+            capacity,
+            ttl,
+        }
+    }
+
+    /// `L1_CACHE_SIZE` 最多缓存的 key 数, 默认 2000, 为 0 时关闭;
+    /// `L1_CACHE_TTL` 每个 key 的缓存秒数, 默认 60
+    pub fn from_env() -> Self {
+        let capacity = std::env::var("L1_CACHE_SIZE")
+            .ok()
+            .and_then(|val| val.parse().ok())
+            .unwrap_or(2000);
+        let ttl = std::env::var("L1_CACHE_TTL")
+            .ok()
+            .and_then(|val| val.parse().ok())
+            .unwrap_or(60);
+        Self::new(capacity, Duration::from_secs(ttl))
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.capacity > 0
+    }
+
+    /// 读 Redis 前取当前的失效代数, 写入时传回
+    pub fn generation(&self) -> u64 {
+        self.lock().generation
+    }
+
+    pub fn get_set(&self, key: &str) -> Option<Vec<String>> {
+        let mut inner = self.lock();
+        match inner.touch(key).map(|entry| &entry.value) {
+            Some(Value::Set(members)) => Some(members.clone()),
+            _ => None,
+        }
+    }
+
+    pub fn put_set(&self, key: &str, members: Vec<String>, generation: u64) {
+        let mut inner = self.lock();
+        if !self.enabled() || inner.generation != generation {
+            return;
+        }
+        let entry = self.entry_mut(&mut inner, key);
+        entry.value = Value::Set(members);
+    }
+
+    /// 按 fields 的顺序返回, 未缓存的字段为 None
+    pub fn get_fields(&self, key: &str, fields: &[String]) -> Vec<Option<Option<String>>> {
+        let mut inner = self.lock();
+        match inner.touch(key).map(|entry| &entry.value) {
+            Some(Value::Hash(hash)) => fields.iter().map(|val| hash.get(val).cloned()).collect(),
+            _ => vec![None; fields.len()],
+        }
+    }
+
+    pub fn put_fields(&self, key: &str, values: Vec<(String, Option<String>)>, generation: u64) {
+        let mut inner = self.lock();
+        if !self.enabled() || inner.generation != generation {
+            return;
+        }
+        let entry = self.entry_mut(&mut inner, key);
+        if !matches!(entry.value, Value::Hash(_)) {
+            entry.value = Value::Hash(HashMap::new());
+        }
+        if let Value::Hash(hash) = &mut entry.value {
+            hash.extend(values);
+        }
+    }
+
+    pub fn invalidate(&self, keys: &[String]) {
+        let mut inner = self.lock();
+        inner.generation += 1;
+        for key in keys {
+            inner.entries.remove(key);
+        }
+    }
+
+    pub fn clear(&self) {
+        let mut inner = self.lock();
+        inner.generation += 1;
+        inner.entries.clear();
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Inner> {
+        self.inner.lock().expect("l1 cache lock")
+    }
+
+    /// 取出或新建 key, 新建前容量已满时先删除过期的 key, 仍然满则淘汰最久未使用的
+    fn entry_mut<'a>(&self, inner: &'a mut Inner, key: &str) -> &'a mut Entry {
+        if !inner.entries.contains_key(key) && inner.entries.len() >= self.capacity {
+            let now = Instant::now();
+            inner.entries.retain(|_, entry| entry.expire_at > now);
+            if inner.entries.len() >= self.capacity {
+                let lru = inner
+                    .entries
+                    .iter()
+                    .min_by_key(|(_, entry)| entry.used)
+                    .map(|(key, _)| key.clone());
+                if let Some(lru) = lru {
+                    inner.entries.remove(&lru);
+                }
+            }
+        }
+        inner.tick += 1;
+        let used = inner.tick;
+        let expire_at = Instant::now() + self.ttl;
+        let entry = inner.entries.entry(key.to_string()).or_insert(Entry {
+            value: Value::Set(vec![]),
+            expire_at,
+            used,
+        });
+        entry.used = used;
+        entry
+    }
+}
+
+impl Inner {
+    /// 取出未过期的 key 并记录使用
+    fn touch(&mut self, key: &str) -> Option<&Entry> {
+        let expired = self
+            .entries
+            .get(key)
+            .is_some_and(|entry| entry.expire_at <= Instant::now());
+        if expired {
+            self.entries.remove(key);
+        }
+        self.tick += 1;
+        let tick = self.tick;
+        let entry = self.entries.get_mut(key)?;
+        entry.used = tick;
+        Some(entry)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::L1Cache;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|val| val.to_string()).collect()
+    }
+
+    #[test]
+    fn test_l1_cache() {
+        let l1 = L1Cache::new(2, Duration::from_secs(60));
+        let generation = l1.generation();
+        l1.put_set("1:role_access_1", args(&["1", "2"]), generation);
+        l1.put_fields(
+            "1:role_info",
+            vec![
+                ("1".to_string(), Some("a".to_string())),
+                ("2".to_string(), None),
+            ],
+            generation,
+        );
+        assert_eq!(l1.get_set("1:role_access_1"), Some(args(&["1", "2"])));
+        assert_eq!(
+            l1.get_fields("1:role_info", &args(&["1", "2", "3"])),
+            vec![Some(Some("a".to_string())), Some(None), None]
+        );
+
+        // 容量满时淘汰最久未使用的 key
+        l1.get_set("1:role_access_1");
+        l1.put_set("1:role_access_2", args(&["3"]), generation);
+        assert_eq!(l1.get_fields("1:role_info", &args(&["1"])), vec![None]);
+        assert!(l1.get_set("1:role_access_1").is_some());
+
+        // 失效后删除 key, 失效前读取的结果不再写入
+        l1.invalidate(&args(&["1:role_access_1"]));
+        assert_eq!(l1.get_set("1:role_access_1"), None);
+        l1.put_set("1:role_access_1", args(&["1"]), generation);
+        assert_eq!(l1.get_set("1:role_access_1"), None);
+        assert_eq!(l1.get_set("1:role_access_2"), Some(args(&["3"])));
+
+        l1.clear();
+        assert_eq!(l1.get_set("1:role_access_2"), None);
+    }
+
+    #[test]
+    fn test_l1_cache_disabled() {
+        let l1 = L1Cache::new(0, Duration::from_secs(60));
+        l1.put_set("1:role_access_1", vec![], l1.generation());
+        assert_eq!(l1.get_set("1:role_access_1"), None);
+    }
+}
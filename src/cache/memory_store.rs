@@ -0,0 +1,314 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use super::{CacheOp, CacheStore};
+
+/// 进程内缓存, 用于本地开发和测试, 多个实例之间不共享
+#[derive(Default)]
+pub struct MemoryStore {
+    data: Mutex<HashMap<String, Entry>>,
+}
+
+struct Entry {
+    value: Value,
+    expire_at: Option<Instant>,
+}
+
+enum Value {
+    Str(String),
+    Set(BTreeSet<String>),
+    Hash(BTreeMap<String, String>),
+}
+
+impl MemoryStore {
+    /// 持有锁读写数据, 过期的 key 先被删除
+    fn with<T>(&self, key: &str, f: impl FnOnce(&mut HashMap<String, Entry>) -> T) -> T {
+        let mut data = self.data.lock().expect("memory cache lock");
+        let expired = data
+            .get(key)
+            .and_then(|entry| entry.expire_at)
+            .is_some_and(|at| at <= Instant::now());
+        if expired {
+            data.remove(key);
+        }
+        f(&mut data)
+    }
+
+    fn set_mut<'a>(data: &'a mut HashMap<String, Entry>, key: &str) -> &'a mut BTreeSet<String> {
+        let entry = data.entry(key.to_string()).or_insert(Entry {
+            value: Value::Set(BTreeSet::new()),
+            expire_at: None,
+        });
+        if !matches!(entry.value, Value::Set(_)) {
+            entry.value = Value::Set(BTreeSet::new());
+        }
+        match &mut entry.value {
+            Value::Set(set) => set,
+            _ => unreachable!(),
+        }
+    }
+
+    fn hash_mut<'a>(
+        data: &'a mut HashMap<String, Entry>,
+        key: &str,
+    ) -> &'a mut BTreeMap<String, String> {
+        let entry = data.entry(key.to_string()).or_insert(Entry {
+            value: Value::Hash(BTreeMap::new()),
+            expire_at: None,
+        });
+        if !matches!(entry.value, Value::Hash(_)) {
+            entry.value = Value::Hash(BTreeMap::new());
+        }
+        match &mut entry.value {
+            Value::Hash(hash) => hash,
+            _ => unreachable!(),
+        }
+    }
+
+    /// 与 Redis 一致, 空集合和空 hash 不保留 key
+    fn remove_empty(data: &mut HashMap<String, Entry>, key: &str) {
+        let empty = match data.get(key).map(|entry| &entry.value) {
+            Some(Value::Set(set)) => set.is_empty(),
+            Some(Value::Hash(hash)) => hash.is_empty(),
+            _ => false,
+        };
+        if empty {
+            data.remove(key);
+        }
+    }
+
+    fn apply(data: &mut HashMap<String, Entry>, op: CacheOp) {
+        match op {
+            CacheOp::Del(key) => {
+                data.remove(&key);
+            }
+            CacheOp::SAdd(key, members) => {
+                if !members.is_empty() {
+                    Self::set_mut(data, &key).extend(members);
+                }
+            }
+            CacheOp::SRem(key, members) => {
+                if let Some(Value::Set(set)) = data.get_mut(&key).map(|entry| &mut entry.value) {
+                    members.iter().for_each(|member| {
+                        set.remove(member);
+                    });
+                }
+                Self::remove_empty(data, &key);
+            }
+            CacheOp::HSet(key, field, value) => {
+                Self::hash_mut(data, &key).insert(field, value);
+            }
+            CacheOp::HDel(key, fields) => {
+                if let Some(Value::Hash(hash)) = data.get_mut(&key).map(|entry| &mut entry.value) {
+                    fields.iter().for_each(|field| {
+                        hash.remove(field);
+                    });
+                }
+                Self::remove_empty(data, &key);
+            }
+            CacheOp::Rename(from, to) => {
+                if let Some(entry) = data.remove(&from) {
+                    data.insert(to, entry);
+                }
+            }
+        }
+    }
+}
+
+impl CacheStore for MemoryStore {
+    async fn get(&self, key: &str) -> Option<String> {
+        self.with(key, |data| match data.get(key).map(|entry| &entry.value) {
+            Some(Value::Str(val)) => Some(val.clone()),
+            _ => None,
+        })
+    }
+
+    async fn set_ex(&self, key: &str, value: String, seconds: u64) {
+        self.with(key, |data| {
+            data.insert(
+                key.to_string(),
+                Entry {
+                    value: Value::Str(value),
+                    expire_at: Some(Instant::now() + Duration::from_secs(seconds)),
+                },
+            );
+        })
+    }
+
+    async fn set_nx_ex(&self, key: &str, value: String, seconds: u64) -> bool {
+        self.with(key, |data| {
+            if data.contains_key(key) {
+                return false;
+            }
+            data.insert(
+                key.to_string(),
+                Entry {
+                    value: Value::Str(value),
+                    expire_at: Some(Instant::now() + Duration::from_secs(seconds)),
+                },
+            );
+            true
+        })
+    }
+
+    async fn expire_if_eq(&self, key: &str, value: &str, seconds: u64) -> bool {
+        self.with(key, |data| match data.get_mut(key) {
+            Some(entry) if matches!(&entry.value, Value::Str(val) if val == value) => {
+                entry.expire_at = Some(Instant::now() + Duration::from_secs(seconds));
+                true
+            }
+            _ => false,
+        })
+    }
+
+    async fn ttl(&self, key: &str) -> i64 {
+        self.with(key, |data| match data.get(key) {
+            None => -2,
+            Some(Entry {
+                expire_at: None, ..
+            }) => -1,
+            Some(Entry {
+                expire_at: Some(at),
+                ..
+            }) => at.saturating_duration_since(Instant::now()).as_secs() as i64,
+        })
+    }
+
+    async fn exists(&self, key: &str) -> bool {
+        self.with(key, |data| data.contains_key(key))
+    }
+
+    async fn del(&self, key: &str) {
+        self.with(key, |data| {
+            data.remove(key);
+        })
+    }
+
+    async fn incr(&self, key: &str, delta: i64) -> i64 {
+        self.with(key, |data| {
+            let entry = data.entry(key.to_string()).or_insert(Entry {
+                value: Value::Str("0".to_string()),
+                expire_at: None,
+            });
+            let current = match &entry.value {
+                Value::Str(val) => val.parse::<i64>().unwrap_or(0),
+                _ => 0,
+            };
+            entry.value = Value::Str((current + delta).to_string());
+            current + delta
+        })
+    }
+
+    async fn smembers(&self, key: &str) -> Vec<String> {
+        self.with(key, |data| match data.get(key).map(|entry| &entry.value) {
+            Some(Value::Set(set)) => set.iter().cloned().collect(),
+            _ => vec![],
+        })
+    }
+
+    async fn smembers_many(&self, keys: &[String]) -> Vec<Vec<String>> {
+        let mut list: Vec<Vec<String>> = vec![];
+        for key in keys {
+            list.push(self.smembers(key).await);
+        }
+        list
+    }
+
+    async fn smismember(&self, key: &str, members: &[String]) -> Vec<bool> {
+        self.with(key, |data| match data.get(key).map(|entry| &entry.value) {
+            Some(Value::Set(set)) => members.iter().map(|val| set.contains(val)).collect(),
+            _ => vec![false; members.len()],
+        })
+    }
+
+    async fn hmget(&self, key: &str, fields: &[String]) -> Vec<Option<String>> {
+        self.with(key, |data| match data.get(key).map(|entry| &entry.value) {
+            Some(Value::Hash(hash)) => fields.iter().map(|val| hash.get(val).cloned()).collect(),
+            _ => vec![None; fields.len()],
+        })
+    }
+
+    async fn hgetall(&self, key: &str) -> BTreeMap<String, String> {
+        self.with(key, |data| match data.get(key).map(|entry| &entry.value) {
+            Some(Value::Hash(hash)) => hash.clone(),
+            _ => BTreeMap::new(),
+        })
+    }
+
+    async fn scan_prefix(&self, prefix: &str) -> Vec<String> {
+        let now = Instant::now();
+        let data = self.data.lock().expect("memory cache lock");
+        data.iter()
+            .filter(|(key, entry)| {
+                key.starts_with(prefix) && entry.expire_at.is_none_or(|at| at > now)
+            })
+            .map(|(key, _)| key.clone())
+            .collect()
+    }
+
+    async fn exec(&self, ops: Vec<CacheOp>) {
+        let mut data = self.data.lock().expect("memory cache lock");
+        for op in ops {
+            Self::apply(&mut data, op);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::MemoryStore;
+    use crate::cache::{CacheOp, CacheStore};
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|val| val.to_string()).collect()
+    }
+
+    #[actix_rt::test]
+    async fn test_memory_store() {
+        let store = MemoryStore::default();
+        store
+            .exec(vec![
+                CacheOp::SAdd("1:role_ids".to_string(), args(&["1", "2"])),
+                CacheOp::HSet("1:role_info".to_string(), "1".to_string(), "a".to_string()),
+            ])
+            .await;
+        assert_eq!(store.smembers("1:role_ids").await, args(&["1", "2"]));
+        assert_eq!(
+            store.smismember("1:role_ids", &args(&["2", "3"])).await,
+            vec![true, false]
+        );
+        assert_eq!(
+            store.hmget("1:role_info", &args(&["1", "2"])).await,
+            vec![Some("a".to_string()), None]
+        );
+
+        // 删除最后一个成员后 key 不存在
+        store
+            .exec(vec![CacheOp::SRem(
+                "1:role_ids".to_string(),
+                args(&["1", "2"]),
+            )])
+            .await;
+        assert!(!store.exists("1:role_ids").await);
+        assert_eq!(store.scan_prefix("1:role").await, args(&["1:role_info"]));
+
+        assert_eq!(store.incr("1:operation_seq", 1).await, 1);
+        assert_eq!(store.incr("1:operation_seq", 1).await, 2);
+
+        store.set_ex("login", "data".to_string(), 60).await;
+        assert_eq!(store.get("login").await, Some("data".to_string()));
+        assert!(store.ttl("login").await > 0);
+        store.set_ex("login", "data".to_string(), 0).await;
+        assert_eq!(store.get("login").await, None);
+        assert_eq!(store.ttl("login").await, -2);
+
+        // 租约只有持有者可以续期
+        assert!(store.set_nx_ex("leader", "a".to_string(), 10).await);
+        assert!(!store.set_nx_ex("leader", "b".to_string(), 10).await);
+        assert!(store.expire_if_eq("leader", "a", 10).await);
+        assert!(!store.expire_if_eq("leader", "b", 10).await);
+    }
+}
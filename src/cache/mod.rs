@@ -0,0 +1,179 @@
+use std::collections::BTreeMap;
+
+use once_cell::sync::OnceCell;
+
+use memory_store::MemoryStore;
+use redis_store::RedisStore;
+
+pub mod l1;
+pub mod memory_store;
+pub mod redis_store;
+
+static CACHE: OnceCell<Cache> = OnceCell::new();
+
+/// 在一个事务 (`MULTI`) 中执行的写操作
+#[derive(Clone, Debug, PartialEq)]
+pub enum CacheOp {
+    Del(String),
+    SAdd(String, Vec<String>),
+    SRem(String, Vec<String>),
+    HSet(String, String, String),
+    HDel(String, Vec<String>),
+    /// 用前一个 key 覆盖后一个 key, 前一个 key 必须存在
+    Rename(String, String),
+}
+
+impl CacheOp {
+    /// 被修改的 key, `Rename` 为目标 key
+    pub fn key(&self) -> &str {
+        match self {
+            CacheOp::Del(key)
+            | CacheOp::SAdd(key, _)
+            | CacheOp::SRem(key, _)
+            | CacheOp::HSet(key, _, _)
+            | CacheOp::HDel(key, _)
+            | CacheOp::Rename(_, key) => key,
+        }
+    }
+}
+
+/// 服务用到的缓存操作, 成员、字段和值都以字符串存储
+pub trait CacheStore {
+    async fn get(&self, key: &str) -> Option<String>;
+    async fn set_ex(&self, key: &str, value: String, seconds: u64);
+    /// key 不存在时写入, 返回是否写入
+    async fn set_nx_ex(&self, key: &str, value: String, seconds: u64) -> bool;
+    /// key 的值等于 value 时重设过期时间, 返回是否重设
+    async fn expire_if_eq(&self, key: &str, value: &str, seconds: u64) -> bool;
+    /// 剩余秒数, key 不存在或没有过期时间时小于 0
+    async fn ttl(&self, key: &str) -> i64;
+    async fn exists(&self, key: &str) -> bool;
+    async fn del(&self, key: &str);
+    async fn incr(&self, key: &str, delta: i64) -> i64;
+    async fn smembers(&self, key: &str) -> Vec<String>;
+    /// 一次往返读取多个集合, 按 keys 的顺序返回; 不经过一级缓存, 用于对比和校验
+    async fn smembers_many(&self, keys: &[String]) -> Vec<Vec<String>>;
+    /// 按 members 的顺序返回是否在集合中
+    async fn smismember(&self, key: &str, members: &[String]) -> Vec<bool>;
+    /// 按 fields 的顺序返回
+    async fn hmget(&self, key: &str, fields: &[String]) -> Vec<Option<String>>;
+    /// 不经过一级缓存
+    async fn hgetall(&self, key: &str) -> BTreeMap<String, String>;
+    /// 以 prefix 开头的所有 key
+    async fn scan_prefix(&self, prefix: &str) -> Vec<String>;
+    /// 原子执行一组写操作
+    async fn exec(&self, ops: Vec<CacheOp>);
+}
+
+/// 由 `CACHE_BACKEND` 选择的缓存实现
+pub enum Cache {
+    Redis(RedisStore),
+    Memory(MemoryStore),
+}
+
+macro_rules! dispatch {
+    ($self:ident, $store:ident => $call:expr) => {
+        match $self {
+            Cache::Redis($store) => $call,
+            Cache::Memory($store) => $call,
+        }
+    };
+}
+
+impl CacheStore for Cache {
+    async fn get(&self, key: &str) -> Option<String> {
+        dispatch!(self, store => store.get(key).await)
+    }
+    async fn set_ex(&self, key: &str, value: String, seconds: u64) {
+        dispatch!(self, store => store.set_ex(key, value, seconds).await)
+    }
+    async fn set_nx_ex(&self, key: &str, value: String, seconds: u64) -> bool {
+        dispatch!(self, store => store.set_nx_ex(key, value, seconds).await)
+    }
+    async fn expire_if_eq(&self, key: &str, value: &str, seconds: u64) -> bool {
+        dispatch!(self, store => store.expire_if_eq(key, value, seconds).await)
+    }
+    async fn ttl(&self, key: &str) -> i64 {
+        dispatch!(self, store => store.ttl(key).await)
+    }
+    async fn exists(&self, key: &str) -> bool {
+        dispatch!(self, store => store.exists(key).await)
+    }
+    async fn del(&self, key: &str) {
+        dispatch!(self, store => store.del(key).await)
+    }
+    async fn incr(&self, key: &str, delta: i64) -> i64 {
+        dispatch!(self, store => store.incr(key, delta).await)
+    }
+    async fn smembers(&self, key: &str) -> Vec<String> {
+        dispatch!(self, store => store.smembers(key).await)
+    }
+    async fn smembers_many(&self, keys: &[String]) -> Vec<Vec<String>> {
+        dispatch!(self, store => store.smembers_many(keys).await)
+    }
+    async fn smismember(&self, key: &str, members: &[String]) -> Vec<bool> {
+        dispatch!(self, store => store.smismember(key, members).await)
+    }
+    async fn hmget(&self, key: &str, fields: &[String]) -> Vec<Option<String>> {
+        dispatch!(self, store => store.hmget(key, fields).await)
+    }
+    async fn hgetall(&self, key: &str) -> BTreeMap<String, String> {
+        dispatch!(self, store => store.hgetall(key).await)
+    }
+    async fn scan_prefix(&self, prefix: &str) -> Vec<String> {
+        dispatch!(self, store => store.scan_prefix(prefix).await)
+    }
+    async fn exec(&self, ops: Vec<CacheOp>) {
+        dispatch!(self, store => store.exec(ops).await)
+    }
+}
+
+/// `CACHE_BACKEND=memory` 时使用进程内缓存, 其他情况连接 `REDIS_URL`
+pub async fn init_cache() {
+    let backend = std::env::var("CACHE_BACKEND").unwrap_or_default();
+    let cache = if backend == "memory" {
+        log::info!("cache backend memory");
+        Cache::Memory(MemoryStore::default())
+    } else {
+        Cache::Redis(RedisStore::connect().await)
+    };
+    let _ = CACHE.set(cache);
+}
+
+/// 测试中使用进程内缓存, 各测试共用, 需使用不同的租户id避免互相影响
+#[cfg(test)]
+pub fn init_memory_cache() {
+    let _ = CACHE.set(Cache::Memory(MemoryStore::default()));
+}
+
+pub fn cache() -> &'static Cache {
+    CACHE.get().expect("cache is not initialized")
+}
+
+pub fn is_memory() -> bool {
+    matches!(cache(), Cache::Memory(_))
+}
+
+/// id 转为缓存中的成员/字段
+pub fn id_args(ids: &[i32]) -> Vec<String> {
+    ids.iter().map(|id| id.to_string()).collect()
+}
+
+/// 缓存中的成员转为 id, 忽略无法解析的成员
+pub fn parse_ids(list: Vec<String>) -> Vec<i32> {
+    list.into_iter()
+        .filter_map(|val| val.parse().ok())
+        .collect()
+}
+
+pub async fn smembers_ids(key: &str) -> Vec<i32> {
+    parse_ids(cache().smembers(key).await)
+}
+
+pub async fn sadd_ids(key: String, ids: &[i32]) {
+    cache().exec(vec![CacheOp::SAdd(key, id_args(ids))]).await;
+}
+
+pub async fn srem_ids(key: String, ids: &[i32]) {
+    cache().exec(vec![CacheOp::SRem(key, id_args(ids))]).await;
+}
@@ -0,0 +1,289 @@
+use std::{collections::BTreeMap, sync::Arc, time::Duration};
+
+use futures_util::StreamExt;
+use redis::AsyncCommands;
+use rs_service_util::{redis::RedisTool, redis_conn};
+
+use super::{l1::L1Cache, CacheOp, CacheStore};
+use crate::{util::common::RedisKeys, REDIS, REDIS_KEY};
+
+/// 基于全局 `REDIS` 连接的实现, jwt 中间件也使用该连接
+///
+/// 权限表、角色信息和角色权限集合先读进程内的一级缓存, 写入后通过 pub/sub 通知所有实例失效
+pub struct RedisStore {
+    l1: Arc<L1Cache>,
+}
+
+impl RedisStore {
+    pub async fn connect() -> Self {
+        let redis_url = std::env::var("REDIS_URL").expect("REDIS_URL must be set");
+        let _ = REDIS.set(RedisTool::new(redis_url.clone()).await);
+
+        let l1 = Arc::new(L1Cache::from_env());
+        if l1.enabled() {
+            let client = redis::Client::open(redis_url).expect("REDIS_URL 格式错误");
+            actix_rt::spawn(subscribe_invalidation(client, l1.clone()));
+        } else {
+            log::info!("l1 cache is close");
+        }
+        RedisStore { l1 }
+    }
+
+    /// 删除本地的一级缓存并通知其他实例
+    async fn invalidate(&self, keys: Vec<String>) {
+        if keys.is_empty() || !self.l1.enabled() {
+            return;
+        }
+        self.l1.invalidate(&keys);
+        let payload = serde_json::to_string(&keys).expect("msg");
+        let mut conn = redis_conn!().await;
+        let res: Result<i64, redis::RedisError> = conn.publish(invalidate_channel(), payload).await;
+        if let Err(error) = res {
+            log::error!("发送缓存失效消息失败 {error}");
+        }
+    }
+}
+
+fn invalidate_channel() -> String {
+    format!("{}_l1_invalidate", REDIS_KEY.to_string())
+}
+
+/// 是否使用一级缓存: `access_map`、`access_map_ids`、`role_info`、`role_access_{id}`
+fn is_l1_key(key: &str) -> bool {
+    let Some((_, name)) = key.rsplit_once(':') else {
+        return false;
+    };
+    [
+        RedisKeys::AccessMap,
+        RedisKeys::AccessMapIds,
+        RedisKeys::RoleInfo,
+    ]
+    .iter()
+    .any(|val| name == val.to_string())
+        || name.starts_with(&format!("{}_", RedisKeys::RoleAccess))
+}
+
+/// 订阅失效消息, 断开后重新订阅
+///
+/// 断开期间的消息会丢失, 每次订阅成功后先清空一级缓存
+async fn subscribe_invalidation(client: redis::Client, l1: Arc<L1Cache>) {
+    loop {
+        match client.get_async_pubsub().await {
+            Err(error) => log::error!("连接缓存失效订阅失败 {error}"),
+            Ok(mut pubsub) => match pubsub.subscribe(invalidate_channel()).await {
+                Err(error) => log::error!("订阅缓存失效消息失败 {error}"),
+                Ok(()) => {
+                    l1.clear();
+                    let mut stream = pubsub.on_message();
+                    while let Some(msg) = stream.next().await {
+                        let keys: Option<Vec<String>> = msg
+                            .get_payload::<String>()
+                            .ok()
+                            .and_then(|payload| serde_json::from_str(&payload).ok());
+                        match keys {
+                            Some(keys) => l1.invalidate(&keys),
+                            None => l1.clear(),
+                        }
+                    }
+                    log::warn!("缓存失效订阅断开");
+                }
+            },
+        }
+        l1.clear();
+        actix_rt::time::sleep(Duration::from_secs(1)).await;
+    }
+}
+
+impl CacheStore for RedisStore {
+    async fn get(&self, key: &str) -> Option<String> {
+        let mut conn = redis_conn!().await;
+        conn.get(key).await.expect("msg")
+    }
+
+    async fn set_ex(&self, key: &str, value: String, seconds: u64) {
+        let mut conn = redis_conn!().await;
+        let _: () = conn.set_ex(key, value, seconds).await.expect("msg");
+    }
+
+    async fn set_nx_ex(&self, key: &str, value: String, seconds: u64) -> bool {
+        let mut conn = redis_conn!().await;
+        let res: Option<String> = redis::cmd("SET")
+            .arg(key)
+            .arg(value)
+            .arg("NX")
+            .arg("EX")
+            .arg(seconds)
+            .query_async(&mut conn)
+            .await
+            .expect("msg");
+        res.is_some()
+    }
+
+    async fn expire_if_eq(&self, key: &str, value: &str, seconds: u64) -> bool {
+        let script = redis::Script::new(
+            "if redis.call('GET', KEYS[1]) == ARGV[1] then return redis.call('EXPIRE', KEYS[1], ARGV[2]) else return 0 end",
+        );
+        let mut conn = redis_conn!().await;
+        let res: i64 = script
+            .key(key)
+            .arg(value)
+            .arg(seconds)
+            .invoke_async(&mut conn)
+            .await
+            .expect("msg");
+        res == 1
+    }
+
+    async fn ttl(&self, key: &str) -> i64 {
+        let mut conn = redis_conn!().await;
+        conn.ttl(key).await.expect("msg")
+    }
+
+    async fn exists(&self, key: &str) -> bool {
+        let mut conn = redis_conn!().await;
+        conn.exists(key).await.expect("msg")
+    }
+
+    async fn del(&self, key: &str) {
+        let mut conn = redis_conn!().await;
+        let _: () = conn.del(key).await.expect("msg");
+        if is_l1_key(key) {
+            self.invalidate(vec![key.to_string()]).await;
+        }
+    }
+
+    async fn incr(&self, key: &str, delta: i64) -> i64 {
+        let mut conn = redis_conn!().await;
+        conn.incr(key, delta).await.expect("msg")
+    }
+
+    async fn smembers(&self, key: &str) -> Vec<String> {
+        let l1 = is_l1_key(key);
+        if l1 {
+            if let Some(members) = self.l1.get_set(key) {
+                return members;
+            }
+        }
+        let generation = self.l1.generation();
+        let mut conn = redis_conn!().await;
+        let members: Vec<String> = conn.smembers(key).await.expect("msg");
+        if l1 {
+            self.l1.put_set(key, members.clone(), generation);
+        }
+        members
+    }
+
+    /// 不经过一级缓存, 用于全量同步时对比
+    async fn smembers_many(&self, keys: &[String]) -> Vec<Vec<String>> {
+        if keys.is_empty() {
+            return vec![];
+        }
+        let mut pipe = redis::pipe();
+        for key in keys {
+            pipe.smembers(key);
+        }
+        let mut conn = redis_conn!().await;
+        pipe.query_async(&mut conn).await.expect("msg")
+    }
+
+    async fn smismember(&self, key: &str, members: &[String]) -> Vec<bool> {
+        if members.is_empty() {
+            return vec![];
+        }
+        let mut conn = redis_conn!().await;
+        redis::cmd("SMISMEMBER")
+            .arg(key)
+            .arg(members)
+            .query_async(&mut conn)
+            .await
+            .expect("msg")
+    }
+
+    async fn hmget(&self, key: &str, fields: &[String]) -> Vec<Option<String>> {
+        if fields.is_empty() {
+            return vec![];
+        }
+        let l1 = is_l1_key(key);
+        let cached = if l1 {
+            self.l1.get_fields(key, fields)
+        } else {
+            vec![None; fields.len()]
+        };
+        let missing: Vec<String> = fields
+            .iter()
+            .zip(&cached)
+            .filter(|(_, val)| val.is_none())
+            .map(|(field, _)| field.clone())
+            .collect();
+        if missing.is_empty() {
+            return cached.into_iter().flatten().collect();
+        }
+
+        let generation = self.l1.generation();
+        let mut conn = redis_conn!().await;
+        let values: Vec<Option<String>> = redis::cmd("HMGET")
+            .arg(key)
+            .arg(&missing)
+            .query_async(&mut conn)
+            .await
+            .expect("msg");
+        if l1 {
+            let pairs = missing.into_iter().zip(values.iter().cloned()).collect();
+            self.l1.put_fields(key, pairs, generation);
+        }
+        let mut values = values.into_iter();
+        cached
+            .into_iter()
+            .map(|val| val.unwrap_or_else(|| values.next().flatten()))
+            .collect()
+    }
+
+    async fn hgetall(&self, key: &str) -> BTreeMap<String, String> {
+        let mut conn = redis_conn!().await;
+        conn.hgetall(key).await.expect("msg")
+    }
+
+    async fn scan_prefix(&self, prefix: &str) -> Vec<String> {
+        let mut conn = redis_conn!().await;
+        let mut keys: Vec<String> = vec![];
+        let mut iter: redis::AsyncIter<String> =
+            conn.scan_match(format!("{prefix}*")).await.expect("msg");
+        while let Some(key) = iter.next_item().await {
+            keys.push(key);
+        }
+        keys
+    }
+
+    async fn exec(&self, ops: Vec<CacheOp>) {
+        if ops.is_empty() {
+            return;
+        }
+        let mut keys: Vec<String> = ops
+            .iter()
+            .map(|op| op.key().to_string())
+            .filter(|key| is_l1_key(key))
+            .collect();
+        keys.sort();
+        keys.dedup();
+        let mut pipe = redis::pipe();
+        pipe.atomic();
+        for op in ops {
+            match op {
+                CacheOp::Del(key) => pipe.del(key).ignore(),
+                CacheOp::SAdd(key, members) if !members.is_empty() => {
+                    pipe.sadd(key, members).ignore()
+                }
+                CacheOp::SRem(key, members) if !members.is_empty() => {
+                    pipe.srem(key, members).ignore()
+                }
+                CacheOp::HSet(key, field, value) => pipe.hset(key, field, value).ignore(),
+                CacheOp::HDel(key, fields) if !fields.is_empty() => pipe.hdel(key, fields).ignore(),
+                CacheOp::Rename(from, to) => pipe.rename(from, to).ignore(),
+                _ => &mut pipe,
+            };
+        }
+        let mut conn = redis_conn!().await;
+        let _: () = pipe.query_async(&mut conn).await.expect("msg");
+        self.invalidate(keys).await;
+    }
+}
@@ -0,0 +1,246 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use actix_web::{post, web, HttpRequest, Responder};
+use rbs::to_value;
+use serde::Serialize;
+use serde_json::Value;
+
+use super::{diff_hash, diff_set, CacheCheckData, CacheCheckRes, CacheDiff};
+use crate::{
+    access::AccessMapItem,
+    cache::{cache, CacheOp, CacheStore},
+    entity::{role_access_entity::RoleAccessEntity, user_role_entity::UserRoleEntity},
+    response::ResponseBody,
+    user::{
+        user_role_service::{get_role_users, sync_user_auth, ALL_USER_ROLE_SQL},
+        OptionData,
+    },
+    util::common::{get_tenant_id, get_tenant_ids, RedisKeys},
+    RB,
+};
+
+#[utoipa::path(
+    tag = "cache",
+    responses( (status = 200))
+)]
+#[post("/check")]
+pub async fn check_cache(req_data: web::Json<CacheCheckData>, req: HttpRequest) -> impl Responder {
+    let tenant_id = get_tenant_id(&req);
+    let res = check_tenant(tenant_id, req_data.repair.unwrap_or(false)).await;
+    ResponseBody::default(Some(res))
+}
+
+/// 检查所有租户, 供命令行 `cache-check [--repair]` 使用
+pub async fn check_all_tenants(repair: bool) -> Vec<CacheCheckRes> {
+    let mut list: Vec<CacheCheckRes> = vec![];
+    for tenant_id in get_tenant_ids().await {
+        list.push(check_tenant(tenant_id, repair).await);
+    }
+    list
+}
+
+/// 对比租户下所有 `RedisKeys` 缓存与数据库, repair 为 true 时按数据库修复
+///
+/// 操作记录 (`operation`) 是临时数据, 不参与检查
+async fn check_tenant(tenant_id: i32, repair: bool) -> CacheCheckRes {
+    log::info!("check_tenant {tenant_id} repair {repair}");
+    let ex = RB.acquire().await.expect("msg");
+    let users: Vec<OptionData> = ex
+        .query_decode(
+            "select id, name from user where status = 1 and tenant_id = ?",
+            vec![to_value!(tenant_id)],
+        )
+        .await
+        .expect("查询用户失败");
+    let roles: Vec<OptionData> = ex
+        .query_decode(
+            "select id, name from role where status = 1 and tenant_id = ?",
+            vec![to_value!(tenant_id)],
+        )
+        .await
+        .expect("查询角色失败");
+    let access: Vec<AccessMapItem> = ex
+        .query_decode(
+            "select id, name, value from access where status = 1 and tenant_id = ?",
+            vec![to_value!(tenant_id)],
+        )
+        .await
+        .expect("查询权限失败");
+    // 与全量同步使用相同的数据
+    let user_roles: Vec<UserRoleEntity> = ex
+        .query_decode(
+            &format!("select * from ({ALL_USER_ROLE_SQL}) as t where t.tenant_id = ?"),
+            vec![to_value!(tenant_id)],
+        )
+        .await
+        .expect("查询用户角色失败");
+    let role_access: Vec<RoleAccessEntity> =
+        RoleAccessEntity::select_by_column(&ex, "tenant_id", tenant_id)
+            .await
+            .expect("查询角色权限失败");
+    drop(ex);
+
+    let mut diffs: Vec<CacheDiff> = vec![];
+    let pairs = [
+        (
+            RedisKeys::UserIds,
+            RedisKeys::UserInfo,
+            to_hash(&users, |val| val.id),
+        ),
+        (
+            RedisKeys::RoleIds,
+            RedisKeys::RoleInfo,
+            to_hash(&roles, |val| val.id),
+        ),
+        (
+            RedisKeys::AccessMapIds,
+            RedisKeys::AccessMap,
+            to_hash(&access, |val| val.id),
+        ),
+    ];
+    for (set_key, hash_key, expected) in pairs {
+        diffs.extend(check_hash_pair(set_key, hash_key, tenant_id, &expected, repair).await);
+    }
+
+    let mut user_role_map: BTreeMap<i32, BTreeSet<String>> = BTreeMap::new();
+    for val in user_roles {
+        user_role_map
+            .entry(val.user_id)
+            .or_default()
+            .insert(val.role_id.to_string());
+    }
+    let user_diffs = check_id_sets(RedisKeys::UserRoles, tenant_id, &user_role_map, repair).await;
+
+    let mut role_access_map: BTreeMap<i32, BTreeSet<String>> = BTreeMap::new();
+    for val in role_access {
+        role_access_map
+            .entry(val.role_id)
+            .or_default()
+            .insert(val.access_id.to_string());
+    }
+    let role_diffs =
+        check_id_sets(RedisKeys::RoleAccess, tenant_id, &role_access_map, repair).await;
+
+    if repair {
+        // 角色缓存修复后, 刷新受影响用户的登录权限值
+        let mut user_ids: BTreeSet<i32> = user_diffs.iter().map(|(user_id, _)| *user_id).collect();
+        for (role_id, _) in role_diffs.iter() {
+            for user in get_role_users(*role_id).await {
+                user_ids.insert(user.id);
+            }
+        }
+        for user_id in user_ids {
+            if let Err(error) = sync_user_auth(user_id, tenant_id).await {
+                log::error!("刷新用户 {user_id} 权限失败, {error}");
+            }
+        }
+    }
+
+    diffs.extend(user_diffs.into_iter().map(|(_, diff)| diff));
+    diffs.extend(role_diffs.into_iter().map(|(_, diff)| diff));
+    log::info!("check_tenant {tenant_id} diffs {}", diffs.len());
+    CacheCheckRes {
+        tenant_id,
+        repaired: repair,
+        diffs,
+    }
+}
+
+fn to_hash<T: Serialize>(list: &[T], id: impl Fn(&T) -> i32) -> BTreeMap<String, Value> {
+    list.iter()
+        .map(|val| {
+            let value = serde_json::to_value(val).expect("msg");
+            (id(val).to_string(), value)
+        })
+        .collect()
+}
+
+/// 检查 id 集合和对应的信息 hash, 例如 `user_ids` / `user_info`
+async fn check_hash_pair(
+    set_key: RedisKeys,
+    hash_key: RedisKeys,
+    tenant_id: i32,
+    expected: &BTreeMap<String, Value>,
+    repair: bool,
+) -> Vec<CacheDiff> {
+    let set_key = set_key.key(tenant_id);
+    let hash_key = hash_key.key(tenant_id);
+    // 对比的是 redis 中的数据, 不经过一级缓存
+    let members: Vec<String> = cache()
+        .smembers_many(std::slice::from_ref(&set_key))
+        .await
+        .into_iter()
+        .flatten()
+        .collect();
+    let fields: BTreeMap<String, String> = cache().hgetall(&hash_key).await;
+
+    let expected_ids: BTreeSet<String> = expected.keys().cloned().collect();
+    let set_diff = diff_set(&set_key, &expected_ids, &members.into_iter().collect());
+    let hash_diff = diff_hash(&hash_key, expected, &fields);
+
+    if repair {
+        repair_set(&set_diff).await;
+        let mut ops = vec![CacheOp::HDel(hash_key.clone(), hash_diff.extra.clone())];
+        for field in hash_diff.missing.iter().chain(hash_diff.mismatch.iter()) {
+            ops.push(CacheOp::HSet(
+                hash_key.clone(),
+                field.clone(),
+                expected[field].to_string(),
+            ));
+        }
+        cache().exec(ops).await;
+    }
+
+    [set_diff, hash_diff]
+        .into_iter()
+        .filter(|diff| !diff.is_empty())
+        .collect()
+}
+
+/// 检查按 id 拆分的集合, 例如 `user_roles_{id}`, 包括数据库中已没有记录的 key
+async fn check_id_sets(
+    key: RedisKeys,
+    tenant_id: i32,
+    expected: &BTreeMap<i32, BTreeSet<String>>,
+    repair: bool,
+) -> Vec<(i32, CacheDiff)> {
+    let prefix = format!("{}_", key.key(tenant_id));
+    let cache_keys: Vec<String> = cache().scan_prefix(&prefix).await;
+
+    let mut ids: BTreeSet<i32> = expected.keys().copied().collect();
+    for val in cache_keys {
+        if let Some(id) = val.strip_prefix(&prefix).and_then(|id| id.parse().ok()) {
+            ids.insert(id);
+        }
+    }
+
+    let id_keys: Vec<String> = ids.iter().map(|id| key.id_key(tenant_id, *id)).collect();
+    let members_list: Vec<Vec<String>> = cache().smembers_many(&id_keys).await;
+
+    let empty: BTreeSet<String> = BTreeSet::new();
+    let mut diffs: Vec<(i32, CacheDiff)> = vec![];
+    for ((id, id_key), members) in ids.into_iter().zip(id_keys).zip(members_list) {
+        let diff = diff_set(
+            &id_key,
+            expected.get(&id).unwrap_or(&empty),
+            &members.into_iter().collect(),
+        );
+        if diff.is_empty() {
+            continue;
+        }
+        if repair {
+            repair_set(&diff).await;
+        }
+        diffs.push((id, diff));
+    }
+    diffs
+}
+
+async fn repair_set(diff: &CacheDiff) {
+    cache()
+        .exec(vec![
+            CacheOp::SRem(diff.key.clone(), diff.extra.clone()),
+            CacheOp::SAdd(diff.key.clone(), diff.missing.clone()),
+        ])
+        .await;
+}
@@ -0,0 +1,125 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use utoipa_actix_web::service_config::ServiceConfig;
+
+use crate::util::access_guard::RouteAccess;
+
+mod cache_check_service;
+
+pub use cache_check_service::check_all_tenants;
+
+pub fn configure() -> impl FnOnce(&mut ServiceConfig) {
+    |config: &mut ServiceConfig| {
+        config.service(cache_check_service::check_cache);
+    }
+}
+
+/// 接口所需权限, 由 `access_guard` 中间件校验
+pub const ROUTE_ACCESS: &[RouteAccess] = &[RouteAccess::new("POST", "/api/cache/check", "ADMIN")];
+
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct CacheCheckData {
+    pub repair: Option<bool>, // 是否按数据库修复缓存
+}
+
+/// 一个缓存 key 与数据库的差异, set 为成员, hash 为字段
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct CacheDiff {
+    pub key: String,
+    pub missing: Vec<String>,  // 数据库中有, 缓存中没有
+    pub extra: Vec<String>,    // 缓存中有, 数据库中没有
+    pub mismatch: Vec<String>, // hash 中内容与数据库不一致
+}
+
+impl CacheDiff {
+    pub fn is_empty(&self) -> bool {
+        self.missing.is_empty() && self.extra.is_empty() && self.mismatch.is_empty()
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CacheCheckRes {
+    pub tenant_id: i32,
+    pub repaired: bool,
+    pub diffs: Vec<CacheDiff>,
+}
+
+pub fn diff_set(key: &str, expected: &BTreeSet<String>, actual: &BTreeSet<String>) -> CacheDiff {
+    CacheDiff {
+        key: key.to_string(),
+        missing: expected.difference(actual).cloned().collect(),
+        extra: actual.difference(expected).cloned().collect(),
+        mismatch: vec![],
+    }
+}
+
+/// 缓存中的值按 json 比较, 不受字段顺序影响
+pub fn diff_hash(
+    key: &str,
+    expected: &BTreeMap<String, serde_json::Value>,
+    actual: &BTreeMap<String, String>,
+) -> CacheDiff {
+    let mut diff = CacheDiff {
+        key: key.to_string(),
+        ..Default::default()
+    };
+    for (field, value) in expected.iter() {
+        match actual.get(field) {
+            None => diff.missing.push(field.clone()),
+            Some(cache) => {
+                let same =
+                    serde_json::from_str::<serde_json::Value>(cache).is_ok_and(|val| &val == value);
+                if !same {
+                    diff.mismatch.push(field.clone());
+                }
+            }
+        }
+    }
+    diff.extra = actual
+        .keys()
+        .filter(|field| !expected.contains_key(*field))
+        .cloned()
+        .collect();
+    diff
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::{BTreeMap, BTreeSet};
+
+    use serde_json::json;
+
+    use super::{diff_hash, diff_set};
+
+    fn set(list: &[&str]) -> BTreeSet<String> {
+        list.iter().map(|val| val.to_string()).collect()
+    }
+
+    #[test]
+    fn test_diff_set() {
+        let diff = diff_set("1:role_ids", &set(&["1", "2", "3"]), &set(&["2", "3", "4"]));
+        assert_eq!(diff.missing, vec!["1"]);
+        assert_eq!(diff.extra, vec!["4"]);
+        assert!(diff_set("1:role_ids", &set(&["1"]), &set(&["1"])).is_empty());
+    }
+
+    #[test]
+    fn test_diff_hash() {
+        let expected = BTreeMap::from([
+            ("1".to_string(), json!({"id": 1, "name": "a"})),
+            ("2".to_string(), json!({"id": 2, "name": "b"})),
+            ("3".to_string(), json!({"id": 3, "name": "c"})),
+        ]);
+        let actual = BTreeMap::from([
+            ("1".to_string(), r#"{"name":"a","id":1}"#.to_string()),
+            ("2".to_string(), r#"{"id":2,"name":"old"}"#.to_string()),
+            (r#"{"id":4,"name":"d"}"#.to_string(), "4".to_string()),
+        ]);
+        let diff = diff_hash("1:user_info", &expected, &actual);
+        assert_eq!(diff.missing, vec!["3"]);
+        assert_eq!(diff.mismatch, vec!["2"]);
+        assert_eq!(diff.extra, vec![r#"{"id":4,"name":"d"}"#]);
+    }
+}
@@ -0,0 +1,194 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::{entity::role_constraint_entity::RoleConstraintEntity, util::structs::ConstraintType};
+
+/// 解析后的角色约束
+#[derive(Clone, Debug)]
+pub struct RoleConstraint {
+    pub id: i32,
+    pub name: String,
+    pub constraint_type: ConstraintType,
+    pub role_ids: Vec<i32>,
+    pub max_count: i32,
+}
+
+impl RoleConstraint {
+    pub fn parse(entity: &RoleConstraintEntity) -> Option<Self> {
+        let constraint_type = ConstraintType::from(entity.constraint_type)?;
+        let role_ids: Vec<i32> = match serde_json::from_str(&entity.role_ids) {
+            Ok(list) => list,
+            Err(e) => {
+                log::error!("角色约束 {:?} 角色列表解析失败 {e}", entity.id);
+                return None;
+            }
+        };
+        Some(Self {
+            id: entity.id?,
+            name: entity.name.clone(),
+            constraint_type,
+            role_ids,
+            max_count: entity.max_count,
+        })
+    }
+}
+
+/// 违反约束的情况
+///
+/// 互斥: 每个用户一条, role_ids 为该用户同时持有的角色;
+/// 数量限制: 每个约束一条, user_ids 为所有持有者
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema, PartialEq)]
+pub struct ConstraintViolation {
+    pub constraint_id: i32,
+    pub name: String,
+    pub constraint_type: i8,
+    pub user_ids: Vec<i32>,
+    pub role_ids: Vec<i32>,
+}
+
+/// 按用户的有效角色检查所有约束
+pub fn check_constraints(
+    constraints: &[RoleConstraint],
+    user_roles: &HashMap<i32, Vec<i32>>,
+) -> Vec<ConstraintViolation> {
+    let mut user_ids: Vec<i32> = user_roles.keys().copied().collect();
+    user_ids.sort();
+
+    let mut violations: Vec<ConstraintViolation> = vec![];
+    for constraint in constraints {
+        let held = |user_id: &i32| -> Vec<i32> {
+            constraint
+                .role_ids
+                .iter()
+                .filter(|id| user_roles[user_id].contains(id))
+                .copied()
+                .collect()
+        };
+        match constraint.constraint_type {
+            ConstraintType::MUTEX => {
+                for user_id in user_ids.iter() {
+                    let role_ids = held(user_id);
+                    if role_ids.len() > 1 {
+                        violations.push(ConstraintViolation {
+                            constraint_id: constraint.id,
+                            name: constraint.name.clone(),
+                            constraint_type: ConstraintType::MUTEX as i8,
+                            user_ids: vec![*user_id],
+                            role_ids,
+                        });
+                    }
+                }
+            }
+            ConstraintType::CARDINALITY => {
+                let holders: Vec<i32> = user_ids
+                    .iter()
+                    .filter(|user_id| !held(user_id).is_empty())
+                    .copied()
+                    .collect();
+                if holders.len() > constraint.max_count as usize {
+                    violations.push(ConstraintViolation {
+                        constraint_id: constraint.id,
+                        name: constraint.name.clone(),
+                        constraint_type: ConstraintType::CARDINALITY as i8,
+                        user_ids: holders,
+                        role_ids: constraint.role_ids.clone(),
+                    });
+                }
+            }
+        }
+    }
+    violations
+}
+
+/// 变更后新产生的违规, 已存在的违规不拦截, 但不能变得更严重
+pub fn new_violations(
+    before: &[ConstraintViolation],
+    after: Vec<ConstraintViolation>,
+) -> Vec<ConstraintViolation> {
+    let is_cardinality =
+        |val: &ConstraintViolation| val.constraint_type == ConstraintType::CARDINALITY as i8;
+    after
+        .into_iter()
+        .filter(|val| {
+            let prev = before.iter().find(|prev| {
+                prev.constraint_id == val.constraint_id
+                    && (is_cardinality(val) || prev.user_ids == val.user_ids)
+            });
+            match prev {
+                None => true,
+                Some(prev) if is_cardinality(val) => val.user_ids.len() > prev.user_ids.len(),
+                Some(prev) => val.role_ids.iter().any(|id| !prev.role_ids.contains(id)),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use super::{check_constraints, new_violations, RoleConstraint};
+    use crate::util::structs::ConstraintType;
+
+    fn constraints() -> Vec<RoleConstraint> {
+        vec![
+            RoleConstraint {
+                id: 1,
+                name: "payment".to_string(),
+                constraint_type: ConstraintType::MUTEX,
+                role_ids: vec![10, 11],
+                max_count: 1,
+            },
+            RoleConstraint {
+                id: 2,
+                name: "admin".to_string(),
+                constraint_type: ConstraintType::CARDINALITY,
+                role_ids: vec![1],
+                max_count: 2,
+            },
+        ]
+    }
+
+    #[test]
+    fn test_check_constraints() {
+        let user_roles = HashMap::from([
+            (1, vec![1, 10]),
+            (2, vec![1, 10, 11]),
+            (3, vec![1]),
+            (4, vec![11]),
+        ]);
+        let res = check_constraints(&constraints(), &user_roles);
+        assert_eq!(res.len(), 2);
+        assert_eq!(res[0].user_ids, vec![2]);
+        assert_eq!(res[0].role_ids, vec![10, 11]);
+        assert_eq!(res[1].constraint_id, 2);
+        assert_eq!(res[1].user_ids, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_new_violations() {
+        let before = HashMap::from([(1, vec![1]), (2, vec![1]), (3, vec![1]), (4, vec![10])]);
+        let before = check_constraints(&constraints(), &before);
+        assert_eq!(before.len(), 1);
+
+        // 已超出数量限制, 移除持有者不拦截
+        let after = HashMap::from([(1, vec![1]), (2, vec![1]), (3, vec![]), (4, vec![10])]);
+        let after = check_constraints(&constraints(), &after);
+        assert!(new_violations(&before, after).is_empty());
+
+        // 新增持有者、新的互斥角色均拦截
+        let after = HashMap::from([
+            (1, vec![1]),
+            (2, vec![1]),
+            (3, vec![1]),
+            (4, vec![1, 10, 11]),
+        ]);
+        let after = check_constraints(&constraints(), &after);
+        let res = new_violations(&before, after);
+        assert_eq!(res.len(), 2);
+        assert_eq!(res[0].user_ids, vec![4]);
+        assert_eq!(res[1].user_ids, vec![1, 2, 3, 4]);
+    }
+}
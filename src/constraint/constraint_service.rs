@@ -0,0 +1,245 @@
+use std::collections::HashMap;
+
+use super::{
+    check_constraint_by_id, check_constraint_param,
+    constraint_check::{check_constraints, ConstraintViolation},
+    get_constraints, ConstraintListListData, ConstraintListQuery, ConstraintUpdateData,
+    CreateConstraintData,
+};
+use crate::{
+    entity::{role_constraint_entity::RoleConstraintEntity, user_role_entity::UserRoleEntity},
+    response::{MyError, ResponseBody},
+    user::{
+        check_user_by_user_id,
+        user_role_service::{check_role_exists, ALL_USER_ROLE_SQL},
+    },
+    util::{
+        common::{get_tenant_id, get_transaction_tx},
+        structs::{CreateByData, Status},
+    },
+    RB,
+};
+use actix_web::{delete, get, post, web, HttpRequest, Responder};
+use rbs::to_value;
+use rs_service_util::{
+    sql_tool::{SqlTool, SqlToolPageData},
+    time::get_current_time_fmt,
+};
+
+#[utoipa::path(
+    tag = "constraint",
+    responses( (status = 200))
+)]
+#[post("/create_constraint")]
+async fn create_constraint(
+    req_data: web::Json<CreateConstraintData>,
+    req: HttpRequest,
+) -> Result<impl Responder, MyError> {
+    let tenant_id = get_tenant_id(&req);
+    if check_user_by_user_id(req_data.create_by, tenant_id)
+        .await
+        .is_none()
+    {
+        return Err(MyError::UserNotExist);
+    }
+    let max_count = req_data.max_count.unwrap_or(1);
+    if !check_constraint_param(req_data.constraint_type, &req_data.role_ids, max_count) {
+        return Err(MyError::ConstraintParamError);
+    }
+    if check_role_exists(&req_data.role_ids, tenant_id)
+        .await
+        .is_none()
+    {
+        return Err(MyError::RoleNotExist);
+    }
+
+    let new_constraint = RoleConstraintEntity {
+        id: None,
+        create_time: get_current_time_fmt(),
+        update_time: get_current_time_fmt(),
+        name: req_data.name.clone(),
+        constraint_type: req_data.constraint_type,
+        role_ids: serde_json::to_string(&req_data.role_ids).expect("msg"),
+        max_count,
+        create_by: req_data.create_by,
+        status: Status::ACTIVE as i8,
+        tenant_id,
+    };
+
+    let tx = get_transaction_tx().await.unwrap();
+    let insert_res = RoleConstraintEntity::insert(&tx, &new_constraint).await;
+    if let Err(rbs::Error::E(error)) = insert_res {
+        log::error!(" {} {error}", MyError::CreateConstraintError);
+        tx.rollback().await.expect("rollback error");
+        return Err(MyError::CreateConstraintError);
+    }
+    tx.commit().await.expect("commit error");
+
+    Ok(ResponseBody::success("角色约束创建成功"))
+}
+
+#[utoipa::path(
+    tag = "constraint",
+    responses( (status = 200))
+)]
+#[post("/get_constraint_list")]
+async fn get_constraint_list(
+    req_data: web::Json<ConstraintListQuery>,
+    req: HttpRequest,
+) -> impl Responder {
+    let tenant_id = get_tenant_id(&req);
+    let ex_db = RB.acquire().await.expect("msg");
+    let mut tool = SqlTool::init("select * from role_constraint", "order by create_time desc");
+
+    if let Some(name) = req_data.name.clone() {
+        tool.append_sql_filed("name", to_value!(name));
+    }
+    if let Some(constraint_type) = req_data.constraint_type {
+        tool.append_sql_filed("constraint_type", to_value!(constraint_type));
+    }
+    tool.append_sql_filed("tenant_id", to_value!(tenant_id));
+    tool.append_sql_filed("status", to_value!(1));
+
+    let page_sql = tool.gen_page_sql(req_data.page_no, req_data.take);
+    let db_res: Vec<RoleConstraintEntity> = ex_db
+        .query_decode(&page_sql, tool.opt_val.clone())
+        .await
+        .expect("msg");
+
+    let mut records: Vec<ConstraintListListData> = vec![];
+    for val in db_res {
+        let create_by: Option<CreateByData> = ex_db
+            .query_decode(
+                "select id, name from user where id=?",
+                vec![to_value!(val.create_by)],
+            )
+            .await
+            .expect("err");
+        records.push(ConstraintListListData {
+            id: val.id.expect("msg"),
+            create_time: val.create_time,
+            update_time: val.update_time,
+            name: val.name,
+            constraint_type: val.constraint_type,
+            role_ids: serde_json::from_str(&val.role_ids).unwrap_or_default(),
+            max_count: val.max_count,
+            create_by,
+            status: val.status,
+        });
+    }
+    let conf = SqlToolPageData {
+        ex_db,
+        table: "role_constraint".to_string(),
+        records,
+        page_no: req_data.page_no as u64,
+        page_size: req_data.take as u64,
+    };
+    let db_res = tool.page_query(conf).await;
+
+    ResponseBody::default(Some(db_res))
+}
+
+#[utoipa::path(
+    tag = "constraint",
+    responses( (status = 200))
+)]
+#[post("/update_constraint")]
+pub async fn update_constraint_by_id(
+    req_data: web::Json<ConstraintUpdateData>,
+    req: HttpRequest,
+) -> Result<impl Responder, MyError> {
+    let tenant_id = get_tenant_id(&req);
+    match check_constraint_by_id(req_data.id, tenant_id).await {
+        None => {
+            return Err(MyError::ConstraintNotExist);
+        }
+        Some(mut constraint) => {
+            let role_ids: Vec<i32> = match &req_data.role_ids {
+                Some(role_ids) => role_ids.clone(),
+                None => serde_json::from_str(&constraint.role_ids).unwrap_or_default(),
+            };
+            let max_count = req_data.max_count.unwrap_or(constraint.max_count);
+            if !check_constraint_param(constraint.constraint_type, &role_ids, max_count) {
+                return Err(MyError::ConstraintParamError);
+            }
+            if check_role_exists(&role_ids, tenant_id).await.is_none() {
+                return Err(MyError::RoleNotExist);
+            }
+
+            constraint.name = req_data.name.clone().unwrap_or(constraint.name);
+            constraint.role_ids = serde_json::to_string(&role_ids).expect("msg");
+            constraint.max_count = max_count;
+            constraint.update_time = get_current_time_fmt();
+
+            let tx = get_transaction_tx().await.expect("get tx err");
+            let update_res = RoleConstraintEntity::update_by_column(&tx, &constraint, "id").await;
+            if let Err(rbs::Error::E(error)) = update_res {
+                log::error!(" {} {error}", MyError::UpdateConstraintError);
+                tx.rollback().await.expect("rollback error");
+                return Err(MyError::UpdateConstraintError);
+            }
+            tx.commit().await.expect("msg");
+        }
+    }
+
+    Ok(ResponseBody::success("角色约束更新成功"))
+}
+
+#[utoipa::path(
+    tag = "constraint",
+    params(("id", description = "constraint id") ),
+    responses( (status = 200))
+)]
+#[delete("/{id}")]
+pub async fn delete_constraint(
+    id: web::Path<i32>,
+    req: HttpRequest,
+) -> Result<impl Responder, MyError> {
+    let tenant_id = get_tenant_id(&req);
+    let mut constraint = check_constraint_by_id(id.into_inner(), tenant_id)
+        .await
+        .ok_or(MyError::ConstraintNotExist)?;
+
+    constraint.status = Status::DEACTIVE as i8;
+    constraint.update_time = get_current_time_fmt();
+    let tx = get_transaction_tx().await.expect("get tx err");
+    let update_res = RoleConstraintEntity::update_by_column(&tx, &constraint, "id").await;
+    if let Err(rbs::Error::E(error)) = update_res {
+        log::error!(" {} {error}", MyError::UpdateConstraintError);
+        tx.rollback().await.expect("rollback error");
+        return Err(MyError::UpdateConstraintError);
+    }
+    tx.commit().await.expect("msg");
+
+    Ok(ResponseBody::success("角色约束删除成功"))
+}
+
+/// 现有数据中违反约束的情况, 约束创建前已存在的绑定不会被拦截, 需要据此清理
+#[utoipa::path(
+    tag = "constraint",
+    responses( (status = 200))
+)]
+#[get("/violations")]
+pub async fn get_violations(req: HttpRequest) -> impl Responder {
+    let tenant_id = get_tenant_id(&req);
+    let constraints = get_constraints(tenant_id).await;
+    if constraints.is_empty() {
+        let empty: Vec<ConstraintViolation> = vec![];
+        return ResponseBody::default(Some(empty));
+    }
+
+    let ex_db = RB.acquire().await.expect("msg");
+    let list: Vec<UserRoleEntity> = ex_db
+        .query_decode(
+            &format!("select * from ({ALL_USER_ROLE_SQL}) as t where t.tenant_id = ?"),
+            vec![to_value!(tenant_id)],
+        )
+        .await
+        .expect("查询用户角色失败");
+    let mut user_roles: HashMap<i32, Vec<i32>> = HashMap::new();
+    for val in list {
+        user_roles.entry(val.user_id).or_default().push(val.role_id);
+    }
+
+    ResponseBody::default(Some(check_constraints(&constraints, &user_roles)))
+}
@@ -0,0 +1,178 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use utoipa_actix_web::service_config::ServiceConfig;
+
+use crate::{
+    entity::role_constraint_entity::RoleConstraintEntity,
+    response::MyError,
+    user::user_role_service::{get_role_users, get_user_role_ids},
+    util::{
+        access_guard::RouteAccess,
+        structs::{ConstraintType, CreateByData},
+    },
+    RB,
+};
+use constraint_check::{check_constraints, new_violations, RoleConstraint};
+
+mod constraint_service;
+
+pub mod constraint_check;
+
+pub fn configure() -> impl FnOnce(&mut ServiceConfig) {
+    |config: &mut ServiceConfig| {
+        config.service(constraint_service::create_constraint);
+        config.service(constraint_service::get_constraint_list);
+        config.service(constraint_service::update_constraint_by_id);
+        config.service(constraint_service::delete_constraint);
+        config.service(constraint_service::get_violations);
+    }
+}
+
+/// 接口所需权限, 由 `access_guard` 中间件校验
+pub const ROUTE_ACCESS: &[RouteAccess] = &[
+    RouteAccess::new(
+        "POST",
+        "/api/constraint/create_constraint",
+        "CONSTRAINT_CREATE",
+    ),
+    RouteAccess::new(
+        "POST",
+        "/api/constraint/get_constraint_list",
+        "CONSTRAINT_LIST",
+    ),
+    RouteAccess::new(
+        "POST",
+        "/api/constraint/update_constraint",
+        "CONSTRAINT_UPDATE",
+    ),
+    RouteAccess::new("DELETE", "/api/constraint/{id}", "CONSTRAINT_DELETE"),
+    RouteAccess::new("GET", "/api/constraint/violations", "CONSTRAINT_LIST"),
+];
+
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct CreateConstraintData {
+    pub name: String,
+    pub constraint_type: i8,
+    pub role_ids: Vec<i32>,
+    pub max_count: Option<i32>, // 数量限制时必填
+    pub create_by: i32,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct ConstraintUpdateData {
+    pub id: i32,
+    pub name: Option<String>,
+    pub role_ids: Option<Vec<i32>>,
+    pub max_count: Option<i32>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct ConstraintListQuery {
+    pub name: Option<String>,
+    pub constraint_type: Option<i8>,
+    pub page_no: i32,
+    pub take: i32,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ConstraintListListData {
+    pub id: i32,
+    pub create_time: String,
+    pub update_time: String,
+    pub name: String,
+    pub constraint_type: i8,
+    pub role_ids: Vec<i32>,
+    pub max_count: i32,
+    pub create_by: Option<CreateByData>,
+    pub status: i8,
+}
+
+pub async fn check_constraint_by_id(id: i32, tenant_id: i32) -> Option<RoleConstraintEntity> {
+    let ex_db = RB.acquire().await.expect("get db ex error");
+    RoleConstraintEntity::select_by_id(&ex_db, id, tenant_id)
+        .await
+        .expect("角色约束查询失败")
+}
+
+/// 互斥至少需要两个角色, 数量限制至少允许一个用户
+pub fn check_constraint_param(constraint_type: i8, role_ids: &[i32], max_count: i32) -> bool {
+    match ConstraintType::from(constraint_type) {
+        None => false,
+        Some(ConstraintType::MUTEX) => role_ids.len() > 1,
+        Some(ConstraintType::CARDINALITY) => !role_ids.is_empty() && max_count > 0,
+    }
+}
+
+/// 租户下所有有效的约束
+pub async fn get_constraints(tenant_id: i32) -> Vec<RoleConstraint> {
+    let ex_db = RB.acquire().await.expect("get db ex error");
+    let list: Vec<RoleConstraintEntity> = RoleConstraintEntity::select_by_tenant(&ex_db, tenant_id)
+        .await
+        .expect("角色约束查询失败");
+    list.iter().filter_map(RoleConstraint::parse).collect()
+}
+
+/// 校验用户有效角色的变化, next 为变化后用户的有效角色
+///
+/// 只拦截新产生的违规, 避免已有违规的数据阻塞无关的变更
+pub async fn check_role_change(
+    next: HashMap<i32, Vec<i32>>,
+    tenant_id: i32,
+) -> Result<(), MyError> {
+    let constraints = get_constraints(tenant_id).await;
+    if constraints.is_empty() || next.is_empty() {
+        return Ok(());
+    }
+
+    // 数量限制需要知道所有持有者, 只记录其持有的受限角色即可
+    let mut before: HashMap<i32, Vec<i32>> = HashMap::new();
+    for constraint in constraints
+        .iter()
+        .filter(|val| val.constraint_type == ConstraintType::CARDINALITY)
+    {
+        for role_id in constraint.role_ids.iter() {
+            for user in get_role_users(*role_id).await {
+                before.entry(user.id).or_default().push(*role_id);
+            }
+        }
+    }
+    for user_id in next.keys() {
+        before.insert(*user_id, get_user_role_ids(*user_id).await);
+    }
+    let mut after = before.clone();
+    after.extend(next);
+
+    let violations = new_violations(
+        &check_constraints(&constraints, &before),
+        check_constraints(&constraints, &after),
+    );
+    if !violations.is_empty() {
+        log::info!("违反角色约束 {violations:?}");
+        return Err(MyError::RoleConstraintViolated);
+    }
+    Ok(())
+}
+
+/// 用户通过用户组或部门获得额外角色前的校验
+pub async fn check_users_gain_roles(
+    user_ids: &[i32],
+    role_ids: &[i32],
+    tenant_id: i32,
+) -> Result<(), MyError> {
+    if user_ids.is_empty() || role_ids.is_empty() {
+        return Ok(());
+    }
+    let mut next: HashMap<i32, Vec<i32>> = HashMap::new();
+    for user_id in user_ids {
+        let mut roles = get_user_role_ids(*user_id).await;
+        for id in role_ids {
+            if !roles.contains(id) {
+                roles.push(*id);
+            }
+        }
+        next.insert(*user_id, roles);
+    }
+    check_role_change(next, tenant_id).await
+}
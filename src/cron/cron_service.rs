@@ -0,0 +1,13 @@
+use actix_web::{get, Responder};
+
+use super::leader::get_leader_status;
+use crate::response::ResponseBody;
+
+#[utoipa::path(
+    tag = "cron",
+    responses( (status = 200))
+)]
+#[get("/status")]
+pub async fn get_cron_status() -> impl Responder {
+    ResponseBody::default(Some(get_leader_status().await))
+}
@@ -0,0 +1,125 @@
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::{
+    cache::{cache, CacheStore},
+    REDIS_KEY,
+};
+
+/// 租约有效秒数, 持有者退出后最多这么久由其他实例接管
+pub const LEASE_TTL: u64 = 15;
+/// 抢占/续期间隔秒数
+pub const RENEW_INTERVAL: u32 = 5;
+
+lazy_static::lazy_static! {
+    /// 实例标识, k8s 中 `HOSTNAME` 为 pod 名
+    static ref INSTANCE_ID: String = format!(
+        "{}-{}",
+        std::env::var("HOSTNAME").unwrap_or("local".to_string()),
+        std::process::id()
+    );
+}
+
+/// 本实例租约的到期时间, 续期失败或续期任务退出后自然失效
+static LEASE_UNTIL: Mutex<Option<Instant>> = Mutex::new(None);
+
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct LeaderStatus {
+    pub instance_id: String,
+    /// 本实例是否开启定时任务 (`CORN`)
+    pub enabled: bool,
+    pub is_leader: bool,
+    /// 当前持有租约的实例
+    pub leader: Option<String>,
+    /// 租约剩余秒数, 无人持有时小于 0
+    pub lease_ttl: i64,
+}
+
+fn lease_key() -> String {
+    format!("{}_cron_leader", REDIS_KEY.to_string())
+}
+
+/// 本实例是否持有租约, 定时任务只在持有者上执行
+pub fn is_leader() -> bool {
+    LEASE_UNTIL
+        .lock()
+        .expect("lease lock")
+        .is_some_and(|until| until > Instant::now())
+}
+
+/// 续期自己的租约, 没有租约时尝试抢占
+///
+/// 本地到期时间从请求前开始计算, 不会晚于 Redis 中 key 的过期时间
+pub async fn keep_lease() {
+    let key = lease_key();
+    let start = Instant::now();
+    let held = cache().expire_if_eq(&key, &INSTANCE_ID, LEASE_TTL).await
+        || cache()
+            .set_nx_ex(&key, INSTANCE_ID.to_string(), LEASE_TTL)
+            .await;
+
+    let was_leader = is_leader();
+    let mut until = LEASE_UNTIL.lock().expect("lease lock");
+    if held {
+        if !was_leader {
+            log::info!("实例 {} 获得定时任务租约", *INSTANCE_ID);
+        }
+        *until = Some(start + Duration::from_secs(LEASE_TTL));
+    } else {
+        if was_leader {
+            log::warn!("实例 {} 失去定时任务租约", *INSTANCE_ID);
+        }
+        *until = None;
+    }
+}
+
+pub async fn get_leader_status() -> LeaderStatus {
+    let key = lease_key();
+    LeaderStatus {
+        instance_id: INSTANCE_ID.to_string(),
+        enabled: std::env::var("CORN").is_ok_and(|val| val == "true"),
+        is_leader: is_leader(),
+        leader: cache().get(&key).await,
+        lease_ttl: cache().ttl(&key).await,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{is_leader, keep_lease, lease_key, LEASE_TTL};
+    use crate::cache::{cache, init_memory_cache, CacheStore};
+
+    #[actix_rt::test]
+    async fn test_keep_lease() {
+        init_memory_cache();
+        let key = lease_key();
+        cache().del(&key).await;
+
+        // 无人持有时抢占
+        keep_lease().await;
+        assert!(is_leader());
+        let holder = cache().get(&key).await;
+        assert!(holder.is_some());
+
+        // 持有者续期
+        keep_lease().await;
+        assert!(is_leader());
+        assert_eq!(cache().get(&key).await, holder);
+
+        // 租约被其他实例持有后失去
+        cache().set_ex(&key, "other".to_string(), LEASE_TTL).await;
+        keep_lease().await;
+        assert!(!is_leader());
+        assert_eq!(cache().get(&key).await, Some("other".to_string()));
+
+        // 其他实例的租约过期后重新抢占
+        cache().del(&key).await;
+        keep_lease().await;
+        assert!(is_leader());
+    }
+}
@@ -1 +1,18 @@
-pub mod sync_auth;
\ No newline at end of file
+use utoipa_actix_web::service_config::ServiceConfig;
+
+use crate::util::access_guard::RouteAccess;
+
+mod cron_service;
+pub mod leader;
+pub mod outbox;
+pub mod sync_auth;
+
+pub fn configure() -> impl FnOnce(&mut ServiceConfig) {
+    |config: &mut ServiceConfig| {
+        config.service(cron_service::get_cron_status);
+    }
+}
+
+/// 接口所需权限, 由 `access_guard` 中间件校验
+pub const ROUTE_ACCESS: &[RouteAccess] =
+    &[RouteAccess::new("GET", "/api/cron/status", "CRON_STATUS")];
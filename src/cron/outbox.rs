@@ -0,0 +1,83 @@
+use std::collections::BTreeSet;
+
+use crate::{
+    cache::{cache, CacheStore},
+    entity::{cache_outbox_entity::CacheOutboxEntity, user_entity::UserEntity},
+    group::get_group_members,
+    org::{check_org_by_id, get_sub_tree_users},
+    role::role_access_service::refresh_role_access,
+    user::user_role_service::{get_role_users, refresh_user_roles},
+    util::{common::RedisKeys, structs::OutboxEvent},
+    RB,
+};
+
+/// 每次处理的事件数
+const OUTBOX_BATCH: u64 = 500;
+
+/// 消费缓存变更事件, 同一批中重复的目标只处理一次, 处理完成后删除事件
+pub async fn process_outbox() {
+    let ex = RB.acquire().await.expect("msg");
+    let events: Vec<CacheOutboxEntity> = CacheOutboxEntity::select_pending(&ex, OUTBOX_BATCH)
+        .await
+        .expect("查询缓存变更事件失败");
+    if events.is_empty() {
+        return;
+    }
+    log::info!("process_outbox {} events", events.len());
+
+    // (租户id, 角色id) / (租户id, 用户id)
+    let mut roles: BTreeSet<(i32, i32)> = BTreeSet::new();
+    let mut users: BTreeSet<(i32, i32)> = BTreeSet::new();
+    for val in events.iter() {
+        match OutboxEvent::from(val.event_type) {
+            Some(OutboxEvent::UserRoles) => {
+                users.insert((val.tenant_id, val.target_id));
+            }
+            Some(OutboxEvent::RoleAccess) => {
+                roles.insert((val.tenant_id, val.target_id));
+            }
+            Some(OutboxEvent::GroupMembers) => {
+                for user in get_group_members(val.target_id).await {
+                    users.insert((val.tenant_id, user.id));
+                }
+            }
+            Some(OutboxEvent::OrgMembers) => {
+                if let Some(org) = check_org_by_id(val.target_id, val.tenant_id).await {
+                    for user in get_sub_tree_users(&org.path, val.tenant_id).await {
+                        users.insert((val.tenant_id, user.id));
+                    }
+                }
+            }
+            None => log::warn!("未知的缓存变更事件 {val:?}"),
+        }
+    }
+
+    for (tenant_id, role_id) in roles {
+        refresh_role_access(role_id, tenant_id).await;
+        for user in get_role_users(role_id).await {
+            users.insert((tenant_id, user.id));
+        }
+    }
+
+    for (tenant_id, user_id) in users {
+        let db_user = UserEntity::select_by_id(&ex, user_id, tenant_id)
+            .await
+            .expect("用户查询失败");
+        match db_user {
+            Some(_) => {
+                if let Err(error) = refresh_user_roles(user_id, tenant_id).await {
+                    log::error!("刷新用户 {user_id} 权限失败, {error}");
+                }
+            }
+            None => {
+                let key = RedisKeys::UserRoles.id_key(tenant_id, user_id);
+                cache().del(&key).await;
+            }
+        }
+    }
+
+    let ids: Vec<i64> = events.into_iter().filter_map(|val| val.id).collect();
+    CacheOutboxEntity::delete_in_column(&ex, "id", &ids)
+        .await
+        .expect("删除缓存变更事件失败");
+}
@@ -1,66 +1,226 @@
-use redis::AsyncCommands;
-use rs_service_util::redis_conn;
-
 use crate::{
+    cache::{cache, parse_ids, CacheOp, CacheStore},
     entity::{role_access_entity::RoleAccessEntity, user_role_entity::UserRoleEntity},
-    util::common::RedisKeys,
-    RB,
+    user::user_role_service::ALL_USER_ROLE_SQL,
+    util::{
+        common::{get_tenant_ids, RedisKeys, CACHE_SCHEMA_VERSION},
+        sync_opt::replace_sets,
+    },
+    RB, REDIS_KEY,
 };
-use std::collections::{hash_set::HashSet, HashMap};
+use std::collections::{hash_set::HashSet, BTreeMap, BTreeSet, HashMap};
+
+/// 一次全量同步中各类 key 的数量
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SyncReport {
+    /// 缓存中没有, 新写入
+    pub added: usize,
+    /// 成员与数据库不一致, 已覆盖
+    pub updated: usize,
+    /// 数据库中已没有记录, 已删除
+    pub removed: usize,
+    pub unchanged: usize,
+}
 
 /// 同步用户角色关系
-pub async fn sync_user_role() {
+pub async fn sync_user_role() -> SyncReport {
     log::info!("sync_user_role start");
     let ex = RB.acquire().await.expect("msg");
-    let list: Vec<UserRoleEntity> = UserRoleEntity::select_all(&ex).await.expect("msg");
-    let mut map: HashMap<i32, HashSet<i32>> = HashMap::new();
+    // 包含用户组和部门继承的角色
+    let list: Vec<UserRoleEntity> = ex
+        .query_decode(ALL_USER_ROLE_SQL, vec![])
+        .await
+        .expect("msg");
+    let mut map: HashMap<(i32, i32), HashSet<i32>> = HashMap::new();
 
     list.into_iter().for_each(|val| {
-        if map.contains_key(&val.user_id) {
-            let s = map.get_mut(&val.user_id).unwrap();
-            s.insert(val.role_id);
-        } else {
-            let mut set: HashSet<i32> = HashSet::new();
-            set.insert(val.role_id);
-            map.insert(val.user_id, set);
-        }
+        map.entry((val.tenant_id, val.user_id))
+            .or_default()
+            .insert(val.role_id);
     });
 
-    log::info!("sync_user_role map {map:?}");
-    redis_action(RedisKeys::UserRoles.to_string(), &map).await;
-    log::info!("sync_user_role end");
+    let report = redis_action(RedisKeys::UserRoles, &map).await;
+    log::info!("sync_user_role end {report:?}");
+    report
 }
 
 /// 同步角色权限关系
-pub async fn sync_role_access() {
-    log::info!("async_user_role start");
+pub async fn sync_role_access() -> SyncReport {
+    log::info!("sync_role_access start");
     let ex = RB.acquire().await.expect("msg");
     let list: Vec<RoleAccessEntity> = RoleAccessEntity::select_all(&ex).await.expect("msg");
-    let mut map: HashMap<i32, HashSet<i32>> = HashMap::new();
+    let mut map: HashMap<(i32, i32), HashSet<i32>> = HashMap::new();
 
     list.into_iter().for_each(|val| {
-        if map.contains_key(&val.role_id) {
-            let s = map.get_mut(&val.role_id).unwrap();
-            s.insert(val.access_id);
-        } else {
-            let mut set: HashSet<i32> = HashSet::new();
-            set.insert(val.access_id);
-            map.insert(val.role_id, set);
-        }
+        map.entry((val.tenant_id, val.role_id))
+            .or_default()
+            .insert(val.access_id);
     });
-    redis_action(RedisKeys::RoleAccess.to_string(), &map).await;
-    log::info!("sync_role_access map {map:?}");
+
+    let report = redis_action(RedisKeys::RoleAccess, &map).await;
+    log::info!("sync_role_access end {report:?}");
+    report
+}
+
+/// 删除本前缀下低于当前结构版本的 key, 返回删除的数量
+///
+/// 升级 `CACHE_SCHEMA_VERSION` 后新版本从空缓存按需重建, 旧 key 不再读取;
+/// 滚动升级时旧实例仍在使用的更高版本不删除, 登录信息保留, 避免用户被迫重新登录
+pub async fn clean_old_schema() -> usize {
+    let prefix = format!("{}:v", REDIS_KEY.to_string());
+    let keys: Vec<String> = cache()
+        .scan_prefix(&prefix)
+        .await
+        .into_iter()
+        .filter(|key| is_old_schema(key, &prefix))
+        .collect();
+    for chunk in keys.chunks(500) {
+        let ops: Vec<CacheOp> = chunk.iter().cloned().map(CacheOp::Del).collect();
+        cache().exec(ops).await;
+    }
+    if !keys.is_empty() {
+        log::info!("clean_old_schema 删除旧版本 key {}", keys.len());
+    }
+    keys.len()
 }
 
-async fn redis_action(key: String, map: &HashMap<i32, HashSet<i32>>) {
-    let mut conn = redis_conn!().await;
-    for (id, set) in map.into_iter() {
-        let key = format!("{key}_{id}");
-        let ids: Vec<i32> = set.to_owned().into_iter().map(|val| val).collect();
-        log::info!("key {key}");
-        let _: () = conn.del(key.clone()).await.expect("msg");
-        for id in ids {
-            let _: () = conn.sadd(key.clone(), id).await.expect("msg");
+fn is_old_schema(key: &str, prefix: &str) -> bool {
+    let Some((version, rest)) = key.strip_prefix(prefix).and_then(|val| val.split_once(':')) else {
+        return false;
+    };
+    // rest 为 {tenant}:{name}
+    let is_login = rest
+        .split_once(':')
+        .is_some_and(|(_, name)| name.starts_with(&format!("{}_", RedisKeys::Login)));
+    !is_login
+        && version
+            .parse::<u32>()
+            .is_ok_and(|version| version < CACHE_SCHEMA_VERSION)
+}
+
+/// map 的 key 为 (租户id, 用户/角色id)
+///
+/// 成员不变的 key 不写入, 变化的 key 经临时 key 原子替换, 数据库中已没有记录的 key 删除
+async fn redis_action(key: RedisKeys, map: &HashMap<(i32, i32), HashSet<i32>>) -> SyncReport {
+    let expected: BTreeMap<String, BTreeSet<i32>> = map
+        .iter()
+        .map(|((tenant_id, id), set)| (key.id_key(*tenant_id, *id), set.iter().copied().collect()))
+        .collect();
+
+    // 缓存中已有的 key, 包括数据库中已没有记录的
+    let mut tenants: BTreeSet<i32> = get_tenant_ids().await.into_iter().collect();
+    tenants.extend(map.keys().map(|(tenant_id, _)| *tenant_id));
+    let mut cache_keys: Vec<String> = vec![];
+    for tenant_id in tenants {
+        let prefix = format!("{}_", key.key(tenant_id));
+        cache_keys.extend(
+            cache()
+                .scan_prefix(&prefix)
+                .await
+                .into_iter()
+                .filter(|val| {
+                    val.strip_prefix(&prefix)
+                        .is_some_and(|id| id.parse::<i32>().is_ok())
+                }),
+        );
+    }
+    let members = cache().smembers_many(&cache_keys).await;
+    let current: BTreeMap<String, BTreeSet<i32>> = cache_keys
+        .into_iter()
+        .zip(members)
+        .map(|(cache_key, list)| (cache_key, parse_ids(list).into_iter().collect()))
+        .collect();
+
+    let (report, sets) = diff_sets(&expected, &current);
+    log::info!("redis_action {key} {report:?}");
+    replace_sets(sets).await;
+    report
+}
+
+/// 对比数据库与缓存, 返回统计和需要写入的集合, 成员为空表示删除
+fn diff_sets(
+    expected: &BTreeMap<String, BTreeSet<i32>>,
+    current: &BTreeMap<String, BTreeSet<i32>>,
+) -> (SyncReport, Vec<(String, Vec<i32>)>) {
+    let mut report = SyncReport::default();
+    let mut sets: Vec<(String, Vec<i32>)> = vec![];
+    for (key, ids) in expected {
+        match current.get(key) {
+            None => report.added += 1,
+            Some(cached) if cached == ids => {
+                report.unchanged += 1;
+                continue;
+            }
+            Some(_) => report.updated += 1,
         }
+        sets.push((key.clone(), ids.iter().copied().collect()));
+    }
+    for key in current.keys().filter(|key| !expected.contains_key(*key)) {
+        report.removed += 1;
+        sets.push((key.clone(), vec![]));
+    }
+    (report, sets)
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::{BTreeMap, BTreeSet};
+
+    use super::{diff_sets, is_old_schema, SyncReport};
+    use crate::util::common::CACHE_SCHEMA_VERSION;
+
+    fn sets(list: &[(&str, &[i32])]) -> BTreeMap<String, BTreeSet<i32>> {
+        list.iter()
+            .map(|(key, ids)| (key.to_string(), ids.iter().copied().collect()))
+            .collect()
+    }
+
+    #[test]
+    fn test_diff_sets() {
+        let expected = sets(&[
+            ("1:user_roles_1", &[1, 2]),
+            ("1:user_roles_2", &[3]),
+            ("1:user_roles_3", &[4]),
+        ]);
+        let current = sets(&[
+            ("1:user_roles_1", &[1, 2]),
+            ("1:user_roles_2", &[5]),
+            ("1:user_roles_4", &[1]),
+        ]);
+        let (report, list) = diff_sets(&expected, &current);
+        assert_eq!(
+            report,
+            SyncReport {
+                added: 1,
+                updated: 1,
+                removed: 1,
+                unchanged: 1,
+            }
+        );
+        assert_eq!(
+            list,
+            vec![
+                ("1:user_roles_2".to_string(), vec![3]),
+                ("1:user_roles_3".to_string(), vec![4]),
+                ("1:user_roles_4".to_string(), vec![]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_is_old_schema() {
+        let prefix = "user_service:v";
+        let old = CACHE_SCHEMA_VERSION - 1;
+        let new = CACHE_SCHEMA_VERSION + 1;
+        assert!(is_old_schema(&format!("{prefix}{old}:1:user_ids"), prefix));
+        assert!(!is_old_schema(
+            &format!("{prefix}{CACHE_SCHEMA_VERSION}:1:user_ids"),
+            prefix
+        ));
+        assert!(!is_old_schema("user_service:vip:1:user_ids", prefix));
+        // 更高版本属于滚动升级中的其它实例
+        assert!(!is_old_schema(&format!("{prefix}{new}:1:user_ids"), prefix));
+        // 登录信息保留
+        assert!(!is_old_schema(&format!("{prefix}{old}:1:login_5"), prefix));
     }
 }
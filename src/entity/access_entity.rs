@@ -2,7 +2,7 @@ use rbatis::{crud, impl_select, impl_select_page};
 use rs_service_util::time::get_current_time_fmt;
 use serde::{Deserialize, Serialize};
 
-use crate::util::structs::Status;
+use crate::util::{common::DEFAULT_TENANT_ID, structs::Status};
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct AccessEntity {
@@ -13,6 +13,7 @@ pub struct AccessEntity {
     pub create_by: i32, // 创建的用户id
     pub status: i8,
     pub value: u64,
+    pub tenant_id: i32,
 }
 impl AccessEntity {
     pub fn default_adm_access(adm_user_id: i32) -> Self {
@@ -24,11 +25,12 @@ impl AccessEntity {
             update_time: get_current_time_fmt(),
             name: "ADMIN".to_string(),
             value: 0,
+            tenant_id: DEFAULT_TENANT_ID,
         }
     }
 }
 crud!(AccessEntity {}, "access");
-impl_select_page!(AccessEntity{select_page() => "`where status=1 order by create_time desc`" }, "access" );
-impl_select_page!(AccessEntity{select_page_by_name(name:&str) => "`where status=1 and name = #{name} order by create_time desc`" }, "access" );
-impl_select!( AccessEntity{ select_by_id(id:i32) -> Option => "`where id = #{id} and status=1`" }, "access" );
-impl_select!( AccessEntity{ select_by_name(name:&str) -> Option => "`where name = #{name} and status=1`" }, "access" );
+impl_select_page!(AccessEntity{select_page(tenant_id:i32) => "`where status=1 and tenant_id = #{tenant_id} order by create_time desc`" }, "access" );
+impl_select_page!(AccessEntity{select_page_by_name(name:&str, tenant_id:i32) => "`where status=1 and tenant_id = #{tenant_id} and name = #{name} order by create_time desc`" }, "access" );
+impl_select!( AccessEntity{ select_by_id(id:i32, tenant_id:i32) -> Option => "`where id = #{id} and tenant_id = #{tenant_id} and status=1`" }, "access" );
+impl_select!( AccessEntity{ select_by_name(name:&str, tenant_id:i32) -> Option => "`where name = #{name} and tenant_id = #{tenant_id} and status=1`" }, "access" );
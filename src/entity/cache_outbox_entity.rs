@@ -0,0 +1,15 @@
+use rbatis::{crud, impl_select};
+use serde::{Deserialize, Serialize};
+
+/// 与业务变更在同一事务中写入的缓存变更事件, 由 cron 中的 worker 消费
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CacheOutboxEntity {
+    pub id: Option<i64>,
+    pub create_time: String,
+    pub event_type: i8, // 见 OutboxEvent
+    pub target_id: i32, // 用户/角色/用户组/部门id
+    pub tenant_id: i32,
+}
+
+crud!(CacheOutboxEntity {}, "cache_outbox");
+impl_select!(CacheOutboxEntity{ select_pending(limit:u64) => "`order by id asc limit #{limit}`" }, "cache_outbox");
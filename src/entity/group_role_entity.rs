@@ -0,0 +1,14 @@
+use rbatis::{crud, impl_delete, impl_select};
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GroupRoleEntity {
+    pub id: Option<i32>,
+    pub group_id: i32,
+    pub role_id: i32,
+    pub tenant_id: i32,
+}
+
+crud!(GroupRoleEntity {}, "group_role");
+impl_delete!(GroupRoleEntity { delete_by_group_and_role(gid:i32, rid:i32)=> "`where group_id = #{gid} and role_id = #{rid}`"  },"group_role");
+impl_select!(GroupRoleEntity {select_by_group(gid:i32)=>"`where group_id = #{gid}`"}, "group_role");
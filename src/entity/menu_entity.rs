@@ -0,0 +1,23 @@
+use rbatis::{crud, impl_select};
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MenuEntity {
+    pub id: Option<i32>,
+    pub create_time: String,
+    pub update_time: String,
+    pub name: String,
+    pub parent_id: i32,       // 上级菜单id, 0 为顶级菜单
+    pub menu_type: i8,        // 1 : 页面 2 : 按钮
+    pub path: Option<String>, // 前端路由
+    pub icon: Option<String>,
+    pub sort: i32,              // 同级菜单按升序排列
+    pub access_id: Option<i32>, // 可见所需的权限, 为空时所有用户可见
+    pub create_by: i32,         // 创建的用户id
+    pub status: i8,
+    pub tenant_id: i32,
+}
+
+crud!(MenuEntity {}, "menu");
+impl_select!(MenuEntity{ select_by_id(id:i32, tenant_id:i32) -> Option => "`where id = #{id} and tenant_id = #{tenant_id} and status=1`" }, "menu");
+impl_select!(MenuEntity{ select_by_tenant(tenant_id:i32) => "`where tenant_id = #{tenant_id} and status=1 order by sort asc, id asc`" }, "menu");
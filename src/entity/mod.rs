@@ -1,5 +1,15 @@
-pub mod user_entity;
-pub mod role_entity;
 pub mod access_entity;
+pub mod cache_outbox_entity;
+pub mod group_role_entity;
+pub mod menu_entity;
+pub mod org_role_entity;
+pub mod org_unit_entity;
+pub mod policy_entity;
 pub mod role_access_entity;
-pub mod user_role_entity;
\ No newline at end of file
+pub mod role_constraint_entity;
+pub mod role_entity;
+pub mod user_entity;
+pub mod user_group_entity;
+pub mod user_group_member_entity;
+pub mod user_org_entity;
+pub mod user_role_entity;
@@ -0,0 +1,14 @@
+use rbatis::{crud, impl_delete, impl_select};
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct OrgRoleEntity {
+    pub id: Option<i32>,
+    pub org_id: i32,
+    pub role_id: i32,
+    pub tenant_id: i32,
+}
+
+crud!(OrgRoleEntity {}, "org_role");
+impl_delete!(OrgRoleEntity { delete_by_org_and_role(oid:i32, rid:i32)=> "`where org_id = #{oid} and role_id = #{rid}`"  },"org_role");
+impl_select!(OrgRoleEntity {select_by_org(oid:i32)=>"`where org_id = #{oid}`"}, "org_role");
@@ -0,0 +1,21 @@
+use rbatis::{crud, impl_select};
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct OrgUnitEntity {
+    pub id: Option<i32>,
+    pub create_time: String,
+    pub update_time: String,
+    pub name: String,
+    pub parent_id: i32,            // 上级部门id, 0 为顶级部门
+    pub path: String,              // 从顶级部门到自身的id路径, 如 `/1/3/`
+    pub head_user_id: Option<i32>, // 部门负责人
+    pub create_by: i32,            // 创建的用户id
+    pub status: i8,
+    pub tenant_id: i32,
+}
+
+crud!(OrgUnitEntity {}, "org_unit");
+impl_select!(OrgUnitEntity{ select_by_id(id:i32, tenant_id:i32) -> Option => "`where id = #{id} and tenant_id = #{tenant_id} and status=1`" }, "org_unit");
+impl_select!(OrgUnitEntity{ select_by_tenant(tenant_id:i32) => "`where tenant_id = #{tenant_id} and status=1 order by create_time asc`" }, "org_unit");
+impl_select!(OrgUnitEntity{ select_sub_tree(path:&str, tenant_id:i32) => "`where path like concat(#{path}, '%') and tenant_id = #{tenant_id} and status=1 order by create_time asc`" }, "org_unit");
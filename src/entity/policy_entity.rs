@@ -0,0 +1,20 @@
+use rbatis::{crud, impl_select};
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PolicyEntity {
+    pub id: Option<i32>,
+    pub create_time: String,
+    pub update_time: String,
+    pub name: String,
+    pub access_id: i32,     // 作用的权限id
+    pub effect: i8,         // 1 : 允许 0 : 拒绝
+    pub conditions: String, // 条件列表 json
+    pub create_by: i32,     // 创建的用户id
+    pub status: i8,
+    pub tenant_id: i32,
+}
+
+crud!(PolicyEntity {}, "policy");
+impl_select!(PolicyEntity{ select_by_id(id:i32, tenant_id:i32) -> Option => "`where id = #{id} and tenant_id = #{tenant_id} and status=1`" }, "policy");
+impl_select!(PolicyEntity{ select_by_access(access_id:i32, tenant_id:i32) => "`where access_id = #{access_id} and tenant_id = #{tenant_id} and status=1`" }, "policy");
@@ -6,6 +6,7 @@ pub struct RoleAccessEntity {
     pub id: Option<i32>,
     pub role_id: i32,
     pub access_id: i32,
+    pub tenant_id: i32,
 }
 
 crud!(RoleAccessEntity {}, "role_access");
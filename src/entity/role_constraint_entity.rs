@@ -0,0 +1,20 @@
+use rbatis::{crud, impl_select};
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RoleConstraintEntity {
+    pub id: Option<i32>,
+    pub create_time: String,
+    pub update_time: String,
+    pub name: String,
+    pub constraint_type: i8, // 1 : 互斥 2 : 数量限制
+    pub role_ids: String,    // 约束的角色id列表 json
+    pub max_count: i32,      // 数量限制时持有这些角色的最大用户数
+    pub create_by: i32,      // 创建的用户id
+    pub status: i8,
+    pub tenant_id: i32,
+}
+
+crud!(RoleConstraintEntity {}, "role_constraint");
+impl_select!(RoleConstraintEntity{ select_by_id(id:i32, tenant_id:i32) -> Option => "`where id = #{id} and tenant_id = #{tenant_id} and status=1`" }, "role_constraint");
+impl_select!(RoleConstraintEntity{ select_by_tenant(tenant_id:i32) => "`where tenant_id = #{tenant_id} and status=1`" }, "role_constraint");
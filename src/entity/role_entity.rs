@@ -2,7 +2,10 @@ use rbatis::{crud, impl_select};
 use rs_service_util::time::get_current_time_fmt;
 use serde::{Deserialize, Serialize};
 
-use crate::util::structs::Status;
+use crate::util::{
+    common::DEFAULT_TENANT_ID,
+    structs::{DataScope, Status},
+};
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct RoleEntity {
@@ -12,6 +15,8 @@ pub struct RoleEntity {
     pub name: String,
     pub create_by: i32, // 创建的用户id
     pub status: i8,
+    pub tenant_id: i32,
+    pub data_scope: i8, // 数据范围
 }
 
 impl RoleEntity {
@@ -23,10 +28,12 @@ impl RoleEntity {
             name: "ADMIN".to_string(),
             create_by: adm_user_id,
             status: Status::ACTIVE as i8,
+            tenant_id: DEFAULT_TENANT_ID,
+            data_scope: DataScope::ALL as i8,
         }
     }
 }
 
 crud!(RoleEntity {}, "role");
-impl_select!( RoleEntity{ select_by_id(id:i32) -> Option => "`where id = #{id} and status=1`" }, "role" );
-impl_select!( RoleEntity{ select_by_name(name:&str) -> Option => "`where name=#{name} and status=1`" }, "role" );
+impl_select!( RoleEntity{ select_by_id(id:i32, tenant_id:i32) -> Option => "`where id = #{id} and tenant_id = #{tenant_id} and status=1`" }, "role" );
+impl_select!( RoleEntity{ select_by_name(name:&str, tenant_id:i32) -> Option => "`where name=#{name} and tenant_id = #{tenant_id} and status=1`" }, "role" );
@@ -2,7 +2,10 @@ use rbatis::{crud, impl_select};
 use rs_service_util::time::get_current_time_fmt;
 use serde::{Deserialize, Serialize};
 
-use crate::util::structs::{Status, UserType};
+use crate::util::{
+    common::DEFAULT_TENANT_ID,
+    structs::{Status, UserType},
+};
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct UserEntity {
@@ -16,6 +19,7 @@ pub struct UserEntity {
     pub introduce: Option<String>,
     pub user_type: i16,
     pub status: i16,
+    pub tenant_id: i32,
 }
 
 impl UserEntity {
@@ -31,11 +35,12 @@ impl UserEntity {
             introduce: None,
             user_type: UserType::ADMIN as i16,
             status: Status::ACTIVE as i16,
+            tenant_id: DEFAULT_TENANT_ID,
         }
     }
 }
 
 crud!(UserEntity {}, "user");
-impl_select!(UserEntity{select_by_id(id:i32) -> Option => "`where id = #{id} and status=1`"}, "user");
-impl_select!(UserEntity{select_by_name_phone(name:&str, phone:&str) -> Option => "`where name = #{name} or phone= #{phone}  and status=1`"}, "user");
-impl_select!(UserEntity{select_by_name(name:&str) -> Option => "`where name = #{name} and status=1`"}, "user");
+impl_select!(UserEntity{select_by_id(id:i32, tenant_id:i32) -> Option => "`where id = #{id} and tenant_id = #{tenant_id} and status=1`"}, "user");
+impl_select!(UserEntity{select_by_name_phone(name:&str, phone:&str, tenant_id:i32) -> Option => "`where (name = #{name} or phone= #{phone}) and tenant_id = #{tenant_id} and status=1`"}, "user");
+impl_select!(UserEntity{select_by_name(name:&str, tenant_id:i32) -> Option => "`where name = #{name} and tenant_id = #{tenant_id} and status=1`"}, "user");
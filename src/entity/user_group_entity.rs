@@ -0,0 +1,16 @@
+use rbatis::{crud, impl_select};
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct UserGroupEntity {
+    pub id: Option<i32>,
+    pub create_time: String,
+    pub update_time: String,
+    pub name: String,
+    pub create_by: i32, // 创建的用户id
+    pub status: i8,
+    pub tenant_id: i32,
+}
+
+crud!(UserGroupEntity {}, "user_group");
+impl_select!(UserGroupEntity{ select_by_id(id:i32, tenant_id:i32) -> Option => "`where id = #{id} and tenant_id = #{tenant_id} and status=1`" }, "user_group");
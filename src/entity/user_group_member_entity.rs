@@ -0,0 +1,14 @@
+use rbatis::{crud, impl_delete, impl_select};
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct UserGroupMemberEntity {
+    pub id: Option<i32>,
+    pub group_id: i32,
+    pub user_id: i32,
+    pub tenant_id: i32,
+}
+
+crud!(UserGroupMemberEntity {}, "user_group_member");
+impl_delete!(UserGroupMemberEntity { delete_by_group_and_user(gid:i32, uid:i32)=> "`where group_id = #{gid} and user_id = #{uid}`"  },"user_group_member");
+impl_select!(UserGroupMemberEntity {select_by_group(gid:i32)=>"`where group_id = #{gid}`"}, "user_group_member");
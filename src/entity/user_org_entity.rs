@@ -0,0 +1,15 @@
+use rbatis::{crud, impl_delete, impl_select};
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct UserOrgEntity {
+    pub id: Option<i32>,
+    pub user_id: i32,
+    pub org_id: i32,
+    pub tenant_id: i32,
+}
+
+crud!(UserOrgEntity {}, "user_org");
+impl_delete!(UserOrgEntity { delete_by_org_and_user(oid:i32, uid:i32)=> "`where org_id = #{oid} and user_id = #{uid}`"  },"user_org");
+impl_select!(UserOrgEntity {select_by_org(oid:i32)=>"`where org_id = #{oid}`"}, "user_org");
+impl_select!(UserOrgEntity {select_by_user(uid:i32)=>"`where user_id = #{uid}`"}, "user_org");
@@ -6,6 +6,7 @@ pub struct UserRoleEntity {
     pub id: Option<i32>,
     pub role_id: i32,
     pub user_id: i32,
+    pub tenant_id: i32,
 }
 
 crud!(UserRoleEntity {}, "user_role");
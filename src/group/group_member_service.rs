@@ -0,0 +1,289 @@
+use super::{
+    check_group_by_id, get_group_members, sync_group_auth, BindGroupRoleData, BindGroupUserData,
+};
+use crate::{
+    constraint::check_users_gain_roles,
+    entity::{
+        group_role_entity::GroupRoleEntity, role_entity::RoleEntity, user_entity::UserEntity,
+        user_group_entity::UserGroupEntity, user_group_member_entity::UserGroupMemberEntity,
+    },
+    response::{MyError, ResponseBody},
+    user::{
+        check_user_by_user_id,
+        user_role_service::{check_role_exists, refresh_user_roles},
+        OptionData,
+    },
+    util::{
+        common::{diff_ids, get_tenant_id, get_transaction_tx},
+        outbox::add_events,
+        structs::OutboxEvent,
+    },
+    RB,
+};
+use actix_web::{get, post, web, HttpRequest, Responder};
+use rbs::to_value;
+
+#[utoipa::path(
+    tag = "group",
+    responses( (status = 200))
+)]
+#[post("/bind_users")]
+pub async fn bind_users(
+    req_data: web::Json<BindGroupUserData>,
+    req: HttpRequest,
+) -> Result<impl Responder, MyError> {
+    let tenant_id = get_tenant_id(&req);
+    check_group_by_id(req_data.group_id, tenant_id)
+        .await
+        .ok_or(MyError::GroupNotExist)?;
+    for user_id in req_data.user_ids.iter() {
+        if check_user_by_user_id(*user_id, tenant_id).await.is_none() {
+            return Err(MyError::UserNotExist);
+        }
+    }
+
+    let ex = RB.acquire().await.expect("msg");
+    let current: Vec<UserGroupMemberEntity> =
+        UserGroupMemberEntity::select_by_group(&ex, req_data.group_id)
+            .await
+            .expect("查询用户组成员失败");
+    drop(ex);
+    let current_ids: Vec<i32> = current.into_iter().map(|val| val.user_id).collect();
+    let (add_ids, sub_ids) = diff_ids(&req_data.user_ids, &current_ids);
+
+    let ex = RB.acquire().await.expect("msg");
+    let group_roles: Vec<GroupRoleEntity> =
+        GroupRoleEntity::select_by_group(&ex, req_data.group_id)
+            .await
+            .expect("查询用户组角色失败");
+    drop(ex);
+    let group_role_ids: Vec<i32> = group_roles.into_iter().map(|val| val.role_id).collect();
+    check_users_gain_roles(&add_ids, &group_role_ids, tenant_id).await?;
+
+    log::debug!("add_ids {add_ids:?}");
+    log::debug!("sub_ids {sub_ids:?}");
+
+    let tx = get_transaction_tx().await.expect("get tx err");
+    for id in sub_ids.iter() {
+        let sub_res =
+            UserGroupMemberEntity::delete_by_group_and_user(&tx, req_data.group_id, *id).await;
+        if let Err(rbs::Error::E(error)) = sub_res {
+            log::error!("{}, {error}", MyError::BindGroupUserError);
+            tx.rollback().await.expect("msg");
+            return Err(MyError::BindGroupUserError);
+        }
+    }
+    if !add_ids.is_empty() {
+        let add_tabs: Vec<UserGroupMemberEntity> = add_ids
+            .iter()
+            .map(|id| UserGroupMemberEntity {
+                id: None,
+                group_id: req_data.group_id,
+                user_id: *id,
+                tenant_id,
+            })
+            .collect();
+        let add_res =
+            UserGroupMemberEntity::insert_batch(&tx, &add_tabs, add_tabs.len() as u64).await;
+        if let Err(rbs::Error::E(error)) = add_res {
+            log::error!("{}, {error}", MyError::BindGroupUserError);
+            tx.rollback().await.expect("msg");
+            return Err(MyError::BindGroupUserError);
+        }
+    }
+    // 加入或离开用户组的成员继承的角色发生变化
+    let changed: Vec<i32> = add_ids.into_iter().chain(sub_ids).collect();
+    let event_res = add_events(&tx, OutboxEvent::UserRoles, &changed, tenant_id).await;
+    if let Err(rbs::Error::E(error)) = event_res {
+        log::error!("{}, {error}", MyError::BindGroupUserError);
+        tx.rollback().await.expect("msg");
+        return Err(MyError::BindGroupUserError);
+    }
+    tx.commit().await.expect("msg");
+
+    if !changed.is_empty() {
+        let ex = RB.acquire().await.expect("msg");
+        let users: Vec<UserEntity> = UserEntity::select_in_column(&ex, "id", &changed)
+            .await
+            .expect("查询用户失败");
+        drop(ex);
+        for user in users {
+            refresh_user_roles(user.id.expect("msg"), tenant_id).await?;
+        }
+    }
+
+    Ok(ResponseBody::success("绑定成功"))
+}
+
+#[utoipa::path(
+    tag = "group",
+    params(("id", description = "group id") ),
+    responses( (status = 200))
+)]
+#[get("/group_users/{id}")]
+pub async fn get_group_users(
+    id: web::Path<i32>,
+    req: HttpRequest,
+) -> Result<impl Responder, MyError> {
+    let tenant_id = get_tenant_id(&req);
+    let group_id = id.into_inner();
+    check_group_by_id(group_id, tenant_id)
+        .await
+        .ok_or(MyError::GroupNotExist)?;
+
+    let users: Vec<OptionData> = get_group_members(group_id).await;
+
+    Ok(ResponseBody::default(Some(users)))
+}
+
+#[utoipa::path(
+    tag = "group",
+    params(("id", description = "user id") ),
+    responses( (status = 200))
+)]
+#[get("/user_groups/{id}")]
+pub async fn get_user_groups(
+    id: web::Path<i32>,
+    req: HttpRequest,
+) -> Result<impl Responder, MyError> {
+    let tenant_id = get_tenant_id(&req);
+    let user_id = id.into_inner();
+    if check_user_by_user_id(user_id, tenant_id).await.is_none() {
+        return Err(MyError::UserNotExist);
+    }
+
+    let ex = RB.acquire().await.expect("msg");
+    let groups: Vec<UserGroupEntity> = ex
+        .query_decode(
+            "select user_group.* from user_group_member inner join user_group on user_group.id = user_group_member.group_id where user_group_member.user_id = ? and user_group.status = 1",
+            vec![to_value!(user_id)],
+        )
+        .await
+        .expect("查询用户所在用户组失败");
+
+    Ok(ResponseBody::default(Some(groups)))
+}
+
+/// role_ids    bind_ids
+///
+/// [1,2]       [1,2,3,4]    remove 3,4
+///
+/// [1,2 ,5]    [1,2,3,4]    remove 3,4 add 5
+pub async fn check_group_role_bind(group_id: i32, role_ids: &Vec<i32>) -> (Vec<i32>, Vec<i32>) {
+    let ex = RB.acquire().await.expect("msg");
+    let binds: Vec<GroupRoleEntity> = GroupRoleEntity::select_by_group(&ex, group_id)
+        .await
+        .expect("查询用户组角色失败");
+    let bind_ids: Vec<i32> = binds.into_iter().map(|val| val.role_id).collect();
+    log::info!("group bind role ids {bind_ids:?}");
+    diff_ids(role_ids, &bind_ids)
+}
+
+#[utoipa::path(
+    tag = "group",
+    responses( (status = 200))
+)]
+#[post("/bind_roles")]
+pub async fn bind_roles(
+    req_data: web::Json<BindGroupRoleData>,
+    req: HttpRequest,
+) -> Result<impl Responder, MyError> {
+    let tenant_id = get_tenant_id(&req);
+    check_group_by_id(req_data.group_id, tenant_id)
+        .await
+        .ok_or(MyError::GroupNotExist)?;
+    if check_role_exists(&req_data.role_ids, tenant_id)
+        .await
+        .is_none()
+    {
+        return Err(MyError::RoleNotExist);
+    }
+
+    let (add_ids, sub_ids) = check_group_role_bind(req_data.group_id, &req_data.role_ids).await;
+
+    let member_ids: Vec<i32> = get_group_members(req_data.group_id)
+        .await
+        .into_iter()
+        .map(|val| val.id)
+        .collect();
+    check_users_gain_roles(&member_ids, &add_ids, tenant_id).await?;
+
+    log::debug!("add_ids {add_ids:?}");
+    log::debug!("sub_ids {sub_ids:?}");
+
+    let tx = get_transaction_tx().await.expect("get tx err");
+    for id in sub_ids.iter() {
+        let sub_res = GroupRoleEntity::delete_by_group_and_role(&tx, req_data.group_id, *id).await;
+        if let Err(rbs::Error::E(error)) = sub_res {
+            log::error!("{}, {error}", MyError::BindGroupRoleError);
+            tx.rollback().await.expect("msg");
+            return Err(MyError::BindGroupRoleError);
+        }
+    }
+    if !add_ids.is_empty() {
+        let add_tabs: Vec<GroupRoleEntity> = add_ids
+            .iter()
+            .map(|id| GroupRoleEntity {
+                id: None,
+                group_id: req_data.group_id,
+                role_id: *id,
+                tenant_id,
+            })
+            .collect();
+        let add_res = GroupRoleEntity::insert_batch(&tx, &add_tabs, add_tabs.len() as u64).await;
+        if let Err(rbs::Error::E(error)) = add_res {
+            log::error!("{}, {error}", MyError::BindGroupRoleError);
+            tx.rollback().await.expect("msg");
+            return Err(MyError::BindGroupRoleError);
+        }
+    }
+    if !add_ids.is_empty() || !sub_ids.is_empty() {
+        let event_res = add_events(
+            &tx,
+            OutboxEvent::GroupMembers,
+            &[req_data.group_id],
+            tenant_id,
+        )
+        .await;
+        if let Err(rbs::Error::E(error)) = event_res {
+            log::error!("{}, {error}", MyError::BindGroupRoleError);
+            tx.rollback().await.expect("msg");
+            return Err(MyError::BindGroupRoleError);
+        }
+    }
+    tx.commit().await.expect("msg");
+
+    if !add_ids.is_empty() || !sub_ids.is_empty() {
+        sync_group_auth(req_data.group_id, tenant_id).await?;
+    }
+
+    Ok(ResponseBody::success("绑定成功"))
+}
+
+#[utoipa::path(
+    tag = "group",
+    params(("id", description = "group id") ),
+    responses( (status = 200))
+)]
+#[get("/group_roles/{id}")]
+pub async fn get_group_roles(
+    id: web::Path<i32>,
+    req: HttpRequest,
+) -> Result<impl Responder, MyError> {
+    let tenant_id = get_tenant_id(&req);
+    let group_id = id.into_inner();
+    check_group_by_id(group_id, tenant_id)
+        .await
+        .ok_or(MyError::GroupNotExist)?;
+
+    let ex = RB.acquire().await.expect("msg");
+    let roles: Vec<RoleEntity> = ex
+        .query_decode(
+            "select role.* from group_role left join role on group_role.role_id = role.id where group_role.group_id = ? and role.status = 1",
+            vec![to_value!(group_id)],
+        )
+        .await
+        .expect("获取用户组绑定角色失败");
+
+    Ok(ResponseBody::default(Some(roles)))
+}
@@ -0,0 +1,203 @@
+use super::{
+    check_group_by_id, get_group_members, CreateGroupData, GroupListListData, GroupListQuery,
+    GroupUpdateData,
+};
+use crate::{
+    entity::{
+        group_role_entity::GroupRoleEntity, user_group_entity::UserGroupEntity,
+        user_group_member_entity::UserGroupMemberEntity,
+    },
+    response::{MyError, ResponseBody},
+    user::{check_user_by_user_id, user_role_service::refresh_user_roles},
+    util::{
+        common::{get_tenant_id, get_transaction_tx},
+        outbox::add_events,
+        structs::{CreateByData, OutboxEvent, Status},
+    },
+    RB,
+};
+use actix_web::{delete, post, web, HttpRequest, Responder};
+use rbs::to_value;
+use rs_service_util::{
+    sql_tool::{SqlTool, SqlToolPageData},
+    time::get_current_time_fmt,
+};
+
+#[utoipa::path(
+    tag = "group",
+    responses( (status = 200))
+)]
+#[post("/create_group")]
+async fn create_group(
+    req_data: web::Json<CreateGroupData>,
+    req: HttpRequest,
+) -> Result<impl Responder, MyError> {
+    let tenant_id = get_tenant_id(&req);
+    if check_user_by_user_id(req_data.create_by, tenant_id)
+        .await
+        .is_none()
+    {
+        return Err(MyError::UserNotExist);
+    }
+
+    let new_group = UserGroupEntity {
+        id: None,
+        create_time: get_current_time_fmt(),
+        update_time: get_current_time_fmt(),
+        name: req_data.name.clone(),
+        create_by: req_data.create_by,
+        status: Status::ACTIVE as i8,
+        tenant_id,
+    };
+
+    let tx = get_transaction_tx().await.unwrap();
+    let insert_res = UserGroupEntity::insert(&tx, &new_group).await;
+    if let Err(rbs::Error::E(error)) = insert_res {
+        log::error!(" {} {error}", MyError::CreateGroupError);
+        tx.rollback().await.expect("rollback error");
+        return Err(MyError::CreateGroupError);
+    }
+    tx.commit().await.expect("commit error");
+
+    Ok(ResponseBody::success("用户组创建成功"))
+}
+
+#[utoipa::path(
+    tag = "group",
+    responses( (status = 200))
+)]
+#[post("/get_group_list")]
+async fn get_group_list(req_data: web::Json<GroupListQuery>, req: HttpRequest) -> impl Responder {
+    let tenant_id = get_tenant_id(&req);
+    let ex_db = RB.acquire().await.expect("msg");
+    let mut tool = SqlTool::init("select * from user_group", "order by create_time desc");
+
+    if let Some(name) = req_data.name.clone() {
+        tool.append_sql_filed("name", to_value!(name));
+    }
+    if let Some(create_by) = req_data.create_by {
+        tool.append_sql_filed("create_by", to_value!(create_by));
+    }
+    tool.append_sql_filed("tenant_id", to_value!(tenant_id));
+    tool.append_sql_filed("status", to_value!(1));
+
+    let page_sql = tool.gen_page_sql(req_data.page_no, req_data.take);
+    let db_res: Vec<UserGroupEntity> = ex_db
+        .query_decode(&page_sql, tool.opt_val.clone())
+        .await
+        .expect("msg");
+
+    let mut records: Vec<GroupListListData> = vec![];
+    for val in db_res {
+        let create_by: Option<CreateByData> = ex_db
+            .query_decode(
+                "select id, name from user where id=?",
+                vec![to_value!(val.create_by)],
+            )
+            .await
+            .expect("err");
+        records.push(GroupListListData {
+            id: val.id.expect("msg"),
+            create_time: val.create_time,
+            update_time: val.update_time,
+            name: val.name,
+            create_by,
+            status: val.status,
+        });
+    }
+    let conf = SqlToolPageData {
+        ex_db,
+        table: "user_group".to_string(),
+        records,
+        page_no: req_data.page_no as u64,
+        page_size: req_data.take as u64,
+    };
+    let db_res = tool.page_query(conf).await;
+
+    ResponseBody::default(Some(db_res))
+}
+
+#[utoipa::path(
+    tag = "group",
+    responses( (status = 200))
+)]
+#[post("/update_group")]
+pub async fn update_group_by_id(
+    req_data: web::Json<GroupUpdateData>,
+    req: HttpRequest,
+) -> Result<impl Responder, MyError> {
+    let tenant_id = get_tenant_id(&req);
+    match check_group_by_id(req_data.id, tenant_id).await {
+        None => {
+            return Err(MyError::GroupNotExist);
+        }
+        Some(mut group) => {
+            group.name = req_data.name.clone().unwrap_or(group.name);
+            group.update_time = get_current_time_fmt();
+
+            let tx = get_transaction_tx().await.expect("get tx err");
+            let update_res = UserGroupEntity::update_by_column(&tx, &group, "id").await;
+            if let Err(rbs::Error::E(error)) = update_res {
+                log::error!(" {} {error}", MyError::UpdateGroupError);
+                tx.rollback().await.expect("rollback error");
+                return Err(MyError::UpdateGroupError);
+            }
+            tx.commit().await.expect("msg");
+        }
+    }
+
+    Ok(ResponseBody::success("用户组更新成功"))
+}
+
+#[utoipa::path(
+    tag = "group",
+    params(("id", description = "group id") ),
+    responses( (status = 200))
+)]
+#[delete("/{id}")]
+pub async fn delete_group(id: web::Path<i32>, req: HttpRequest) -> Result<impl Responder, MyError> {
+    let tenant_id = get_tenant_id(&req);
+    let id = id.into_inner();
+    let mut group = check_group_by_id(id, tenant_id)
+        .await
+        .ok_or(MyError::GroupNotExist)?;
+
+    // 先记下成员, 删除后刷新他们的权限
+    let members = get_group_members(id).await;
+
+    group.status = Status::DEACTIVE as i8;
+    group.update_time = get_current_time_fmt();
+    let tx = get_transaction_tx().await.expect("get tx err");
+    let update_res = UserGroupEntity::update_by_column(&tx, &group, "id").await;
+    if let Err(rbs::Error::E(error)) = update_res {
+        log::error!(" {} {error}", MyError::UpdateGroupError);
+        tx.rollback().await.expect("rollback error");
+        return Err(MyError::UpdateGroupError);
+    }
+    let member_res = UserGroupMemberEntity::delete_by_column(&tx, "group_id", id).await;
+    if let Err(rbs::Error::E(error)) = member_res {
+        log::error!(" {} {error}", MyError::BindGroupUserError);
+        tx.rollback().await.expect("rollback error");
+        return Err(MyError::BindGroupUserError);
+    }
+    let role_res = GroupRoleEntity::delete_by_column(&tx, "group_id", id).await;
+    if let Err(rbs::Error::E(error)) = role_res {
+        log::error!(" {} {error}", MyError::BindGroupRoleError);
+        tx.rollback().await.expect("rollback error");
+        return Err(MyError::BindGroupRoleError);
+    }
+    let member_ids: Vec<i32> = members.iter().map(|val| val.id).collect();
+    let event_res = add_events(&tx, OutboxEvent::UserRoles, &member_ids, tenant_id).await;
+    if let Err(rbs::Error::E(error)) = event_res {
+        log::error!(" {} {error}", MyError::UpdateGroupError);
+        tx.rollback().await.expect("rollback error");
+        return Err(MyError::UpdateGroupError);
+    }
+    tx.commit().await.expect("msg");
+
+    for user in members {
+        refresh_user_roles(user.id, tenant_id).await?;
+    }
+
+    Ok(ResponseBody::success("用户组删除成功"))
+}
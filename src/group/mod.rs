@@ -0,0 +1,112 @@
+use rbs::to_value;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use utoipa_actix_web::service_config::ServiceConfig;
+
+use crate::{
+    entity::user_group_entity::UserGroupEntity,
+    response::MyError,
+    user::{user_role_service::refresh_user_roles, OptionData},
+    util::{access_guard::RouteAccess, structs::CreateByData},
+    RB,
+};
+
+mod group_member_service;
+mod group_service;
+
+pub fn configure() -> impl FnOnce(&mut ServiceConfig) {
+    |config: &mut ServiceConfig| {
+        config.service(group_service::create_group);
+        config.service(group_service::get_group_list);
+        config.service(group_service::update_group_by_id);
+        config.service(group_service::delete_group);
+
+        config.service(group_member_service::bind_users);
+        config.service(group_member_service::get_group_users);
+        config.service(group_member_service::get_user_groups);
+        config.service(group_member_service::bind_roles);
+        config.service(group_member_service::get_group_roles);
+    }
+}
+
+/// 接口所需权限, 由 `access_guard` 中间件校验
+pub const ROUTE_ACCESS: &[RouteAccess] = &[
+    RouteAccess::new("POST", "/api/group/create_group", "GROUP_CREATE"),
+    RouteAccess::new("POST", "/api/group/get_group_list", "GROUP_LIST"),
+    RouteAccess::new("POST", "/api/group/update_group", "GROUP_UPDATE"),
+    RouteAccess::new("DELETE", "/api/group/{id}", "GROUP_DELETE"),
+    RouteAccess::new("POST", "/api/group/bind_users", "GROUP_BIND_USER"),
+    RouteAccess::new("GET", "/api/group/group_users/{id}", "GROUP_LIST"),
+    RouteAccess::new("GET", "/api/group/user_groups/{id}", "GROUP_LIST"),
+    RouteAccess::new("POST", "/api/group/bind_roles", "GROUP_BIND_ROLE"),
+    RouteAccess::new("GET", "/api/group/group_roles/{id}", "GROUP_LIST"),
+];
+
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct CreateGroupData {
+    pub name: String,
+    pub create_by: i32,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct GroupUpdateData {
+    pub id: i32,
+    pub name: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct GroupListQuery {
+    pub name: Option<String>,
+    pub create_by: Option<i32>,
+    pub page_no: i32,
+    pub take: i32,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GroupListListData {
+    pub id: i32,
+    pub create_time: String,
+    pub update_time: String,
+    pub name: String,
+    pub create_by: Option<CreateByData>,
+    pub status: i8,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct BindGroupUserData {
+    pub group_id: i32,
+    pub user_ids: Vec<i32>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct BindGroupRoleData {
+    pub group_id: i32,
+    pub role_ids: Vec<i32>,
+}
+
+pub async fn check_group_by_id(id: i32, tenant_id: i32) -> Option<UserGroupEntity> {
+    let ex_db = RB.acquire().await.expect("get db ex error");
+    UserGroupEntity::select_by_id(&ex_db, id, tenant_id)
+        .await
+        .expect("用户组查询失败")
+}
+
+/// 用户组的所有成员
+pub async fn get_group_members(group_id: i32) -> Vec<OptionData> {
+    let ex = RB.acquire().await.expect("get ex error");
+    ex.query_decode(
+        "select user.id, user.name from user_group_member inner join user on user.id = user_group_member.user_id where user_group_member.group_id = ? and user.status = 1",
+        vec![to_value!(group_id)],
+    )
+    .await
+    .expect("查询用户组成员失败")
+}
+
+/// 用户组角色变更或用户组删除后, 刷新成员的角色缓存与权限
+pub async fn sync_group_auth(group_id: i32, tenant_id: i32) -> Result<(), MyError> {
+    let users = get_group_members(group_id).await;
+    for user in users {
+        refresh_user_roles(user.id, tenant_id).await?;
+    }
+    Ok(())
+}
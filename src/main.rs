@@ -2,7 +2,9 @@ use actix_cors::Cors;
 use actix_web::middleware::{from_fn, Compress, Logger};
 use actix_web::{http, App, HttpServer};
 use chrono::Utc;
-use cron::sync_auth::{sync_role_access, sync_user_role};
+use cron::leader;
+use cron::outbox::process_outbox;
+use cron::sync_auth::{clean_old_schema, sync_role_access, sync_user_role};
 use env::dotenv;
 use env_logger;
 use once_cell::sync::OnceCell;
@@ -17,8 +19,17 @@ use utoipa_actix_web::AppExt;
 use utoipa_scalar::{Scalar, Servable as ScalarServiceable};
 
 mod access;
+mod cache;
+mod cache_check;
+mod constraint;
 mod cron;
 mod entity;
+mod group;
+mod menu;
+mod operation;
+mod org;
+mod policy;
+mod rbac;
 mod response;
 mod role;
 mod user;
@@ -30,6 +41,15 @@ mod util;
         (name = "user", description = "user 接口"),
         (name = "role", description = "role 接口"),
         (name = "access", description = "权限接口"),
+        (name = "policy", description = "策略接口"),
+        (name = "org", description = "部门接口"),
+        (name = "group", description = "用户组接口"),
+        (name = "menu", description = "菜单接口"),
+        (name = "constraint", description = "角色约束接口"),
+        (name = "operation", description = "后台任务接口"),
+        (name = "rbac", description = "权限配置导入导出接口"),
+        (name = "cache", description = "缓存检查接口"),
+        (name = "cron", description = "定时任务接口"),
         (name = "auth", description = "验权接口")
     ),
     modifiers(&JWT),
@@ -40,7 +60,8 @@ mod util;
 struct ApiDoc;
 
 lazy_static::lazy_static! {
-    static ref REDIS_KEY:String = "user_service".to_string();
+    /// 缓存 key 的前缀, 共用一个 Redis 的环境需配置不同的 `CACHE_PREFIX`
+    static ref REDIS_KEY:String = std::env::var("CACHE_PREFIX").unwrap_or("user_service".to_string());
     static ref RB:RBatis=RBatis::new();
     static ref REDIS: OnceCell<RedisTool> = OnceCell::new();
 }
@@ -49,11 +70,19 @@ lazy_static::lazy_static! {
 async fn main() {
     dotenv().expect("Failed to load .env file");
     env_logger::init();
-    let redis_url = std::env::var("REDIS_URL").expect("REDIS_URL must be set");
-    let _ = REDIS.set(RedisTool::new(redis_url).await);
+    cache::init_cache().await;
 
     init_db().await;
 
+    // 命令行: cache-check [--repair]
+    let args: Vec<String> = std::env::args().collect();
+    if args.get(1).is_some_and(|val| val == "cache-check") {
+        let repair = args.iter().any(|val| val == "--repair");
+        let res = cache_check::check_all_tenants(repair).await;
+        println!("{}", serde_json::to_string_pretty(&res).expect("msg"));
+        return;
+    }
+
     init_corn().await;
 
     let _ = check_adm().await;
@@ -65,9 +94,21 @@ async fn main() {
             .service(utoipa_actix_web::scope("/api/user").configure(user::configure()))
             .service(utoipa_actix_web::scope("/api/role").configure(role::configure()))
             .service(utoipa_actix_web::scope("/api/access").configure(access::configure()))
+            .service(utoipa_actix_web::scope("/api/policy").configure(policy::configure()))
+            .service(utoipa_actix_web::scope("/api/org").configure(org::configure()))
+            .service(utoipa_actix_web::scope("/api/group").configure(group::configure()))
+            .service(utoipa_actix_web::scope("/api/menu").configure(menu::configure()))
+            .service(utoipa_actix_web::scope("/api/constraint").configure(constraint::configure()))
+            .service(utoipa_actix_web::scope("/api/operation").configure(operation::configure()))
+            .service(utoipa_actix_web::scope("/api/rbac").configure(rbac::configure()))
+            .service(utoipa_actix_web::scope("/api/cache").configure(cache_check::configure()))
+            .service(utoipa_actix_web::scope("/api/cron").configure(cron::configure()))
             .service(utoipa_actix_web::scope("/api/auth").configure(user::auth_configure()))
             .service(utoipa_actix_web::scope("/api/obs").configure(user::obs_configure()))
-            .openapi_service(|api| Scalar::with_url("/doc", api))
+            .openapi_service(|mut api| {
+                util::access_guard::add_route_access_doc(&mut api);
+                Scalar::with_url("/doc", api)
+            })
             .into_app()
             .wrap(
                 Cors::default()
@@ -77,16 +118,14 @@ async fn main() {
                         http::header::AUTHORIZATION,
                         http::header::ACCEPT,
                         http::header::CONTENT_TYPE,
+                        http::header::HeaderName::from_static("x-tenant-id"),
                     ]),
             )
             .wrap(Compress::default())
             .wrap(Logger::default())
             .wrap(Logger::new("t %P %s %{service_call}i"))
-            .wrap(from_fn(|req, next| {
-                let rds = crate::REDIS.get().expect("msg");
-                let conn = rds.conn.clone();
-                rs_service_util::middleware::jwt_mw(req, next, conn)
-            }))
+            .wrap(from_fn(util::access_guard::access_guard))
+            .wrap(from_fn(util::jwt_guard::jwt_guard))
     })
     .keep_alive(None)
     .shutdown_timeout(5)
@@ -117,10 +156,32 @@ async fn init_corn() {
         log::info!("corn is close");
         return;
     }
+    // 多副本时只有持有租约的实例执行下面的任务
+    actix_rt::spawn(async move {
+        leader::keep_lease().await;
+        let lease_corn = every(leader::RENEW_INTERVAL)
+            .seconds()
+            .in_timezone(&Utc)
+            .perform(|| async { leader::keep_lease().await });
+        lease_corn.await;
+    });
+    // 变更事件只重建受影响的缓存
+    actix_rt::spawn(async move {
+        let outbox_corn = every(1).seconds().in_timezone(&Utc).perform(|| async {
+            if leader::is_leader() {
+                process_outbox().await;
+            }
+        });
+        outbox_corn.await;
+    });
+    // 全量同步作为兜底
     actix_rt::spawn(async move {
-        let user_role_corn = every(10).seconds().in_timezone(&Utc).perform(|| async {
-            sync_user_role().await;
-            sync_role_access().await;
+        let user_role_corn = every(10).minutes().in_timezone(&Utc).perform(|| async {
+            if leader::is_leader() {
+                clean_old_schema().await;
+                sync_user_role().await;
+                sync_role_access().await;
+            }
         });
         user_role_corn.await;
     });
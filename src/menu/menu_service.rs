@@ -0,0 +1,216 @@
+use std::collections::HashMap;
+
+use super::{build_menu_tree, check_menu_by_id, check_menu_parent, CreateMenuData, MenuUpdateData};
+use crate::{
+    access::check_access_by_id,
+    entity::{access_entity::AccessEntity, menu_entity::MenuEntity},
+    response::{MyError, ResponseBody},
+    user::{
+        auth_service::{get_login_auth, is_adm_auth},
+        check_user_by_user_id,
+    },
+    util::{
+        common::{get_jwt_from_req, get_tenant_id, get_transaction_tx},
+        structs::{MenuType, Status},
+    },
+    RB,
+};
+use actix_web::{delete, get, post, web, HttpRequest, Responder};
+use rs_service_util::time::get_current_time_fmt;
+
+#[utoipa::path(
+    tag = "menu",
+    responses( (status = 200))
+)]
+#[post("/create_menu")]
+async fn create_menu(
+    req_data: web::Json<CreateMenuData>,
+    req: HttpRequest,
+) -> Result<impl Responder, MyError> {
+    let tenant_id = get_tenant_id(&req);
+    if check_user_by_user_id(req_data.create_by, tenant_id)
+        .await
+        .is_none()
+    {
+        return Err(MyError::UserNotExist);
+    }
+    if MenuType::from(req_data.menu_type).is_none() {
+        return Err(MyError::MenuParamError);
+    }
+    let parent_id = req_data.parent_id.unwrap_or(0);
+    if parent_id != 0 && check_menu_by_id(parent_id, tenant_id).await.is_none() {
+        return Err(MyError::MenuNotExist);
+    }
+    if let Some(access_id) = req_data.access_id {
+        if check_access_by_id(access_id, tenant_id).await.is_none() {
+            return Err(MyError::AccessNotExist);
+        }
+    }
+
+    let new_menu = MenuEntity {
+        id: None,
+        create_time: get_current_time_fmt(),
+        update_time: get_current_time_fmt(),
+        name: req_data.name.clone(),
+        parent_id,
+        menu_type: req_data.menu_type,
+        path: req_data.path.clone(),
+        icon: req_data.icon.clone(),
+        sort: req_data.sort.unwrap_or(0),
+        access_id: req_data.access_id,
+        create_by: req_data.create_by,
+        status: Status::ACTIVE as i8,
+        tenant_id,
+    };
+
+    let tx = get_transaction_tx().await.unwrap();
+    let insert_res = MenuEntity::insert(&tx, &new_menu).await;
+    if let Err(rbs::Error::E(error)) = insert_res {
+        log::error!(" {} {error}", MyError::CreateMenuError);
+        tx.rollback().await.expect("rollback error");
+        return Err(MyError::CreateMenuError);
+    }
+    tx.commit().await.expect("commit error");
+
+    Ok(ResponseBody::success("菜单创建成功"))
+}
+
+/// 完整的菜单树, 用于菜单管理
+#[utoipa::path(
+    tag = "menu",
+    responses( (status = 200))
+)]
+#[get("/get_menu_tree")]
+async fn get_menu_tree(req: HttpRequest) -> impl Responder {
+    let tenant_id = get_tenant_id(&req);
+    let ex_db = RB.acquire().await.expect("msg");
+    let list: Vec<MenuEntity> = MenuEntity::select_by_tenant(&ex_db, tenant_id)
+        .await
+        .expect("菜单查询失败");
+
+    ResponseBody::default(Some(build_menu_tree(list, |_| true)))
+}
+
+/// 当前用户可见的菜单树, 按登录缓存中的权限值过滤; 持有 ADMIN 权限可见全部
+#[utoipa::path(
+    tag = "menu",
+    responses( (status = 200))
+)]
+#[get("/user_menu")]
+async fn get_user_menu(req: HttpRequest) -> impl Responder {
+    let tenant_id = get_tenant_id(&req);
+    let login = get_jwt_from_req(&req);
+    let auth = get_login_auth(&login).await;
+    let is_adm = is_adm_auth(auth).await;
+
+    let ex_db = RB.acquire().await.expect("msg");
+    let list: Vec<MenuEntity> = MenuEntity::select_by_tenant(&ex_db, tenant_id)
+        .await
+        .expect("菜单查询失败");
+    let access_ids: Vec<i32> = list.iter().filter_map(|val| val.access_id).collect();
+    let mut access_values: HashMap<i32, u64> = HashMap::new();
+    if !access_ids.is_empty() {
+        let access_list: Vec<AccessEntity> =
+            AccessEntity::select_in_column(&ex_db, "id", &access_ids)
+                .await
+                .expect("权限查询失败");
+        access_values = access_list
+            .into_iter()
+            .filter(|val| val.status == Status::ACTIVE as i8)
+            .map(|val| (val.id.unwrap_or_default(), val.value))
+            .collect();
+    }
+
+    let tree = build_menu_tree(list, |menu| match menu.access_id {
+        None => true,
+        Some(_) if is_adm => true,
+        Some(id) => access_values
+            .get(&id)
+            .map(|value| auth & value != 0)
+            .unwrap_or(false),
+    });
+    ResponseBody::default(Some(tree))
+}
+
+#[utoipa::path(
+    tag = "menu",
+    responses( (status = 200))
+)]
+#[post("/update_menu")]
+pub async fn update_menu_by_id(
+    req_data: web::Json<MenuUpdateData>,
+    req: HttpRequest,
+) -> Result<impl Responder, MyError> {
+    let tenant_id = get_tenant_id(&req);
+    let mut menu = check_menu_by_id(req_data.id, tenant_id)
+        .await
+        .ok_or(MyError::MenuNotExist)?;
+
+    if let Some(parent_id) = req_data.parent_id {
+        let ex_db = RB.acquire().await.expect("msg");
+        let list: Vec<MenuEntity> = MenuEntity::select_by_tenant(&ex_db, tenant_id)
+            .await
+            .expect("菜单查询失败");
+        if !check_menu_parent(req_data.id, parent_id, &list) {
+            return Err(MyError::MenuParamError);
+        }
+        menu.parent_id = parent_id;
+    }
+    if let Some(access_id) = req_data.access_id {
+        if check_access_by_id(access_id, tenant_id).await.is_none() {
+            return Err(MyError::AccessNotExist);
+        }
+        menu.access_id = Some(access_id);
+    }
+    menu.name = req_data.name.clone().unwrap_or(menu.name);
+    menu.path = req_data.path.clone().or(menu.path);
+    menu.icon = req_data.icon.clone().or(menu.icon);
+    menu.sort = req_data.sort.unwrap_or(menu.sort);
+    menu.update_time = get_current_time_fmt();
+
+    let tx = get_transaction_tx().await.expect("get tx err");
+    let update_res = MenuEntity::update_by_column(&tx, &menu, "id").await;
+    if let Err(rbs::Error::E(error)) = update_res {
+        log::error!(" {} {error}", MyError::UpdateMenuError);
+        tx.rollback().await.expect("rollback error");
+        return Err(MyError::UpdateMenuError);
+    }
+    tx.commit().await.expect("msg");
+
+    Ok(ResponseBody::success("菜单更新成功"))
+}
+
+#[utoipa::path(
+    tag = "menu",
+    params(("id", description = "menu id") ),
+    responses( (status = 200))
+)]
+#[delete("/{id}")]
+pub async fn delete_menu(id: web::Path<i32>, req: HttpRequest) -> Result<impl Responder, MyError> {
+    let tenant_id = get_tenant_id(&req);
+    let id = id.into_inner();
+    let mut menu = check_menu_by_id(id, tenant_id)
+        .await
+        .ok_or(MyError::MenuNotExist)?;
+
+    let ex_db = RB.acquire().await.expect("msg");
+    let list: Vec<MenuEntity> = MenuEntity::select_by_tenant(&ex_db, tenant_id)
+        .await
+        .expect("菜单查询失败");
+    if list.iter().any(|val| val.parent_id == id) {
+        return Err(MyError::MenuHasChildren);
+    }
+
+    menu.status = Status::DEACTIVE as i8;
+    menu.update_time = get_current_time_fmt();
+    let tx = get_transaction_tx().await.expect("get tx err");
+    let update_res = MenuEntity::update_by_column(&tx, &menu, "id").await;
+    if let Err(rbs::Error::E(error)) = update_res {
+        log::error!(" {} {error}", MyError::UpdateMenuError);
+        tx.rollback().await.expect("rollback error");
+        return Err(MyError::UpdateMenuError);
+    }
+    tx.commit().await.expect("msg");
+
+    Ok(ResponseBody::success("菜单删除成功"))
+}
@@ -0,0 +1,190 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use utoipa_actix_web::service_config::ServiceConfig;
+
+use crate::{entity::menu_entity::MenuEntity, util::access_guard::RouteAccess, RB};
+
+mod menu_service;
+
+pub fn configure() -> impl FnOnce(&mut ServiceConfig) {
+    |config: &mut ServiceConfig| {
+        config.service(menu_service::create_menu);
+        config.service(menu_service::get_menu_tree);
+        config.service(menu_service::get_user_menu);
+        config.service(menu_service::update_menu_by_id);
+        config.service(menu_service::delete_menu);
+    }
+}
+
+/// 接口所需权限, 由 `access_guard` 中间件校验
+pub const ROUTE_ACCESS: &[RouteAccess] = &[
+    RouteAccess::new("POST", "/api/menu/create_menu", "MENU_CREATE"),
+    RouteAccess::new("GET", "/api/menu/get_menu_tree", "MENU_LIST"),
+    RouteAccess::new("POST", "/api/menu/update_menu", "MENU_UPDATE"),
+    RouteAccess::new("DELETE", "/api/menu/{id}", "MENU_DELETE"),
+];
+
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct CreateMenuData {
+    pub name: String,
+    pub parent_id: Option<i32>,
+    pub menu_type: i8,
+    pub path: Option<String>,
+    pub icon: Option<String>,
+    pub sort: Option<i32>,
+    pub access_id: Option<i32>,
+    pub create_by: i32,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct MenuUpdateData {
+    pub id: i32,
+    pub name: Option<String>,
+    pub parent_id: Option<i32>,
+    pub path: Option<String>,
+    pub icon: Option<String>,
+    pub sort: Option<i32>,
+    pub access_id: Option<i32>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MenuTreeNode {
+    pub id: i32,
+    pub name: String,
+    pub parent_id: i32,
+    pub menu_type: i8,
+    pub path: Option<String>,
+    pub icon: Option<String>,
+    pub sort: i32,
+    pub access_id: Option<i32>,
+    pub children: Vec<MenuTreeNode>,
+}
+
+pub async fn check_menu_by_id(id: i32, tenant_id: i32) -> Option<MenuEntity> {
+    let ex_db = RB.acquire().await.expect("get db ex error");
+    MenuEntity::select_by_id(&ex_db, id, tenant_id)
+        .await
+        .expect("菜单查询失败")
+}
+
+/// 把菜单列表组装为树, 同级按 sort 升序
+///
+/// visible 为 false 的菜单及其子菜单都不返回; 找不到上级的菜单作为顶级
+pub fn build_menu_tree(
+    list: Vec<MenuEntity>,
+    visible: impl Fn(&MenuEntity) -> bool,
+) -> Vec<MenuTreeNode> {
+    let ids: Vec<i32> = list.iter().filter_map(|val| val.id).collect();
+    let mut nodes: Vec<MenuTreeNode> = list
+        .into_iter()
+        .filter(|val| visible(val))
+        .map(|val| MenuTreeNode {
+            id: val.id.expect("msg"),
+            name: val.name,
+            parent_id: val.parent_id,
+            menu_type: val.menu_type,
+            path: val.path,
+            icon: val.icon,
+            sort: val.sort,
+            access_id: val.access_id,
+            children: vec![],
+        })
+        .collect();
+    nodes.sort_by_key(|node| (node.sort, node.id));
+
+    fn attach(parent_id: i32, nodes: &Vec<MenuTreeNode>) -> Vec<MenuTreeNode> {
+        nodes
+            .iter()
+            .filter(|node| node.parent_id == parent_id)
+            .map(|node| {
+                let mut node = node.clone();
+                node.children = attach(node.id, nodes);
+                node
+            })
+            .collect()
+    }
+
+    nodes
+        .iter()
+        .filter(|node| !ids.contains(&node.parent_id))
+        .map(|node| {
+            let mut node = node.clone();
+            node.children = attach(node.id, &nodes);
+            node
+        })
+        .collect()
+}
+
+/// 上级菜单不能是自身或自身的子菜单
+pub fn check_menu_parent(id: i32, parent_id: i32, list: &[MenuEntity]) -> bool {
+    let mut current = parent_id;
+    while current != 0 {
+        if current == id {
+            return false;
+        }
+        match list.iter().find(|val| val.id == Some(current)) {
+            None => return false,
+            Some(parent) => current = parent.parent_id,
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod test {
+    use super::{build_menu_tree, check_menu_parent};
+    use crate::entity::menu_entity::MenuEntity;
+
+    fn menu(id: i32, parent_id: i32, sort: i32, access_id: Option<i32>) -> MenuEntity {
+        MenuEntity {
+            id: Some(id),
+            create_time: "".to_string(),
+            update_time: "".to_string(),
+            name: format!("menu_{id}"),
+            parent_id,
+            menu_type: 1,
+            path: None,
+            icon: None,
+            sort,
+            access_id,
+            create_by: 1,
+            status: 1,
+            tenant_id: 0,
+        }
+    }
+
+    #[test]
+    fn test_build_menu_tree() {
+        let list = vec![
+            menu(1, 0, 2, None),
+            menu(2, 0, 1, Some(10)),
+            menu(3, 1, 0, Some(11)),
+            menu(4, 2, 0, None),
+            menu(5, 1, 0, None),
+        ];
+        let tree = build_menu_tree(list.clone(), |_| true);
+        assert_eq!(tree.len(), 2);
+        assert_eq!(tree[0].id, 2);
+        assert_eq!(tree[1].children.len(), 2);
+
+        // 没有权限 10 时, 其子菜单 4 也不可见
+        let tree = build_menu_tree(list, |val| val.access_id != Some(10));
+        assert_eq!(tree.len(), 1);
+        assert_eq!(tree[0].id, 1);
+        assert_eq!(tree[0].children[0].id, 3);
+    }
+
+    #[test]
+    fn test_check_menu_parent() {
+        let list = vec![
+            menu(1, 0, 0, None),
+            menu(2, 1, 0, None),
+            menu(3, 2, 0, None),
+        ];
+        assert!(check_menu_parent(3, 1, &list));
+        assert!(check_menu_parent(1, 0, &list));
+        assert!(!check_menu_parent(1, 3, &list));
+        assert!(!check_menu_parent(2, 2, &list));
+        assert!(!check_menu_parent(2, 9, &list));
+    }
+}
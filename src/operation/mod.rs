@@ -0,0 +1,124 @@
+use std::future::Future;
+
+use rs_service_util::time::get_current_time_fmt;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use utoipa_actix_web::service_config::ServiceConfig;
+
+use crate::{
+    cache::{cache, CacheStore},
+    response::MyError,
+    util::{access_guard::RouteAccess, common::RedisKeys, structs::OperationStatus},
+};
+
+mod operation_service;
+
+pub fn configure() -> impl FnOnce(&mut ServiceConfig) {
+    |config: &mut ServiceConfig| {
+        config.service(operation_service::get_operation_by_id);
+    }
+}
+
+/// 接口所需权限, 由 `access_guard` 中间件校验
+pub const ROUTE_ACCESS: &[RouteAccess] = &[RouteAccess::new(
+    "GET",
+    "/api/operation/{id}",
+    "OPERATION_VIEW",
+)];
+
+/// 后台任务记录保留时间 (秒)
+const OPERATION_TTL: u64 = 60 * 60 * 24;
+
+/// 后台任务的进度, 保存在缓存中供查询
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct OperationData {
+    pub id: i32,
+    pub name: String,
+    pub status: i8,
+    pub total: usize,
+    pub done: usize,
+    pub failed: usize,
+    pub create_time: String,
+    pub update_time: String,
+}
+
+pub async fn get_operation(id: i32, tenant_id: i32) -> Option<OperationData> {
+    let cache_info: Option<String> = cache()
+        .get(&RedisKeys::Operation.id_key(tenant_id, id))
+        .await;
+    cache_info.and_then(|info| serde_json::from_str(&info).ok())
+}
+
+async fn save_operation(tenant_id: i32, op: &OperationData) {
+    let json = serde_json::to_string(op).expect("msg");
+    cache()
+        .set_ex(
+            &RedisKeys::Operation.id_key(tenant_id, op.id),
+            json,
+            OPERATION_TTL,
+        )
+        .await;
+}
+
+/// 在后台逐个处理 items, 立即返回任务信息, 进度通过 `GET /api/operation/{id}` 查询
+///
+/// 单项失败只记录到 failed, 不中断其余项
+pub async fn spawn_operation<T, F, Fut>(
+    name: &str,
+    tenant_id: i32,
+    items: Vec<T>,
+    job: F,
+) -> OperationData
+where
+    T: 'static,
+    F: Fn(T) -> Fut + 'static,
+    Fut: Future<Output = Result<(), MyError>>,
+{
+    let id = cache()
+        .incr(&RedisKeys::OperationSeq.key(tenant_id), 1)
+        .await as i32;
+    let mut op = OperationData {
+        id,
+        name: name.to_string(),
+        status: OperationStatus::RUNNING as i8,
+        total: items.len(),
+        done: 0,
+        failed: 0,
+        create_time: get_current_time_fmt(),
+        update_time: get_current_time_fmt(),
+    };
+    save_operation(tenant_id, &op).await;
+
+    let res = op.clone();
+    actix_rt::spawn(async move {
+        for (idx, item) in items.into_iter().enumerate() {
+            match job(item).await {
+                Ok(()) => op.done += 1,
+                Err(error) => {
+                    log::error!("后台任务 {} {} 执行失败 {error}", op.id, op.name);
+                    op.failed += 1;
+                }
+            }
+            if (idx + 1) % 100 == 0 {
+                op.update_time = get_current_time_fmt();
+                save_operation(tenant_id, &op).await;
+            }
+        }
+        op.status = if op.failed == 0 {
+            OperationStatus::SUCCESS as i8
+        } else {
+            OperationStatus::FAILED as i8
+        };
+        op.update_time = get_current_time_fmt();
+        save_operation(tenant_id, &op).await;
+        log::info!(
+            "后台任务 {} {} 完成 {}/{}",
+            op.id,
+            op.name,
+            op.done,
+            op.total
+        );
+    });
+
+    res
+}
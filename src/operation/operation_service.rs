@@ -0,0 +1,23 @@
+use super::get_operation;
+use crate::{
+    response::{MyError, ResponseBody},
+    util::common::get_tenant_id,
+};
+use actix_web::{get, web, HttpRequest, Responder};
+
+#[utoipa::path(
+    tag = "operation",
+    params(("id", description = "operation id") ),
+    responses( (status = 200))
+)]
+#[get("/{id}")]
+pub async fn get_operation_by_id(
+    id: web::Path<i32>,
+    req: HttpRequest,
+) -> Result<impl Responder, MyError> {
+    let tenant_id = get_tenant_id(&req);
+    let op = get_operation(id.into_inner(), tenant_id)
+        .await
+        .ok_or(MyError::OperationNotExist)?;
+    Ok(ResponseBody::default(Some(op)))
+}
@@ -0,0 +1,248 @@
+use rbs::to_value;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use utoipa_actix_web::service_config::ServiceConfig;
+
+use crate::{
+    entity::org_unit_entity::OrgUnitEntity,
+    response::MyError,
+    user::{user_role_service::refresh_user_roles, OptionData, RoidS},
+    util::access_guard::RouteAccess,
+    RB,
+};
+
+mod org_member_service;
+mod org_service;
+
+pub fn configure() -> impl FnOnce(&mut ServiceConfig) {
+    |config: &mut ServiceConfig| {
+        config.service(org_service::create_org);
+        config.service(org_service::get_org_tree);
+        config.service(org_service::update_org_by_id);
+        config.service(org_service::move_org);
+        config.service(org_service::get_sub_orgs);
+        config.service(org_service::delete_org);
+
+        config.service(org_member_service::bind_users);
+        config.service(org_member_service::get_org_users);
+        config.service(org_member_service::get_user_orgs);
+        config.service(org_member_service::bind_roles);
+        config.service(org_member_service::get_org_roles);
+    }
+}
+
+/// 接口所需权限, 由 `access_guard` 中间件校验
+pub const ROUTE_ACCESS: &[RouteAccess] = &[
+    RouteAccess::new("POST", "/api/org/create_org", "ORG_CREATE"),
+    RouteAccess::new("GET", "/api/org/get_org_tree", "ORG_LIST"),
+    RouteAccess::new("GET", "/api/org/sub_orgs/{id}", "ORG_LIST"),
+    RouteAccess::new("POST", "/api/org/update_org", "ORG_UPDATE"),
+    RouteAccess::new("POST", "/api/org/move_org", "ORG_UPDATE"),
+    RouteAccess::new("DELETE", "/api/org/{id}", "ORG_DELETE"),
+    RouteAccess::new("POST", "/api/org/bind_users", "ORG_BIND_USER"),
+    RouteAccess::new("POST", "/api/org/get_org_users", "ORG_LIST"),
+    RouteAccess::new("GET", "/api/org/user_orgs/{id}", "ORG_LIST"),
+    RouteAccess::new("POST", "/api/org/bind_roles", "ORG_BIND_ROLE"),
+    RouteAccess::new("GET", "/api/org/org_roles/{id}", "ORG_LIST"),
+];
+
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct CreateOrgData {
+    pub name: String,
+    pub parent_id: Option<i32>,
+    pub head_user_id: Option<i32>,
+    pub create_by: i32,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct OrgUpdateData {
+    pub id: i32,
+    pub name: Option<String>,
+    pub head_user_id: Option<i32>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct MoveOrgData {
+    pub id: i32,
+    pub parent_id: i32, // 0 为移动到顶级
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct BindOrgUserData {
+    pub org_id: i32,
+    pub user_ids: Vec<i32>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct BindOrgRoleData {
+    pub org_id: i32,
+    pub role_ids: Vec<i32>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct OrgUserQuery {
+    pub org_id: i32,
+    pub with_sub: Option<bool>, // 是否包含子部门成员
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct OrgTreeNode {
+    pub id: i32,
+    pub name: String,
+    pub parent_id: i32,
+    pub head_user_id: Option<i32>,
+    pub children: Vec<OrgTreeNode>,
+}
+
+pub async fn check_org_by_id(id: i32, tenant_id: i32) -> Option<OrgUnitEntity> {
+    let ex_db = RB.acquire().await.expect("get db ex error");
+    OrgUnitEntity::select_by_id(&ex_db, id, tenant_id)
+        .await
+        .expect("部门查询失败")
+}
+
+/// 部门路径: 上级路径 + 自身id
+pub fn gen_org_path(parent_path: &str, id: i32) -> String {
+    format!("{parent_path}{id}/")
+}
+
+/// 把部门列表组装为树, 找不到上级的部门作为顶级
+pub fn build_org_tree(list: Vec<OrgUnitEntity>) -> Vec<OrgTreeNode> {
+    let ids: Vec<i32> = list.iter().filter_map(|val| val.id).collect();
+    let nodes: Vec<OrgTreeNode> = list
+        .into_iter()
+        .map(|val| OrgTreeNode {
+            id: val.id.expect("msg"),
+            name: val.name,
+            parent_id: val.parent_id,
+            head_user_id: val.head_user_id,
+            children: vec![],
+        })
+        .collect();
+
+    fn attach(parent_id: i32, nodes: &Vec<OrgTreeNode>) -> Vec<OrgTreeNode> {
+        nodes
+            .iter()
+            .filter(|node| node.parent_id == parent_id)
+            .map(|node| {
+                let mut node = node.clone();
+                node.children = attach(node.id, nodes);
+                node
+            })
+            .collect()
+    }
+
+    nodes
+        .iter()
+        .filter(|node| !ids.contains(&node.parent_id))
+        .map(|node| {
+            let mut node = node.clone();
+            node.children = attach(node.id, &nodes);
+            node
+        })
+        .collect()
+}
+
+/// 部门及其子部门的所有成员
+pub async fn get_sub_tree_users(path: &str, tenant_id: i32) -> Vec<OptionData> {
+    let ex = RB.acquire().await.expect("get ex error");
+    ex.query_decode(
+        "select distinct user.id, user.name from user_org inner join org_unit on org_unit.id = user_org.org_id inner join user on user.id = user_org.user_id where org_unit.path like concat(?, '%') and org_unit.tenant_id = ? and org_unit.status = 1 and user.status = 1",
+        vec![to_value!(path), to_value!(tenant_id)],
+    )
+    .await
+    .expect("查询部门成员失败")
+}
+
+/// 路径上的所有部门id, 从顶级到自身
+pub fn org_path_ids(path: &str) -> Vec<i32> {
+    path.split('/').filter_map(|id| id.parse().ok()).collect()
+}
+
+/// 路径上所有部门 (自身及全部上级) 绑定的角色, 即部门成员继承的角色
+pub async fn get_org_role_ids(path: &str, tenant_id: i32) -> Vec<i32> {
+    let ids: Vec<String> = org_path_ids(path).iter().map(|id| id.to_string()).collect();
+    if ids.is_empty() {
+        return vec![];
+    }
+    // 只拼接解析出的整数id
+    let ids = ids.join(",");
+    let ex = RB.acquire().await.expect("get ex error");
+    let list: Vec<RoidS> = ex
+        .query_decode(
+            &format!("select distinct org_role.role_id from org_role inner join org_unit on org_unit.id = org_role.org_id and org_unit.status = 1 where org_role.org_id in ({ids}) and org_role.tenant_id = ?"),
+            vec![to_value!(tenant_id)],
+        )
+        .await
+        .expect("查询部门角色失败");
+    list.into_iter().map(|val| val.role_id).collect()
+}
+
+/// 部门角色或成员变更后, 刷新部门及其子部门成员的权限
+pub async fn sync_sub_tree_auth(path: &str, tenant_id: i32) -> Result<(), MyError> {
+    let users = get_sub_tree_users(path, tenant_id).await;
+    for user in users {
+        refresh_user_roles(user.id, tenant_id).await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{build_org_tree, gen_org_path, org_path_ids};
+    use crate::entity::org_unit_entity::OrgUnitEntity;
+
+    fn org(id: i32, parent_id: i32, path: &str) -> OrgUnitEntity {
+        OrgUnitEntity {
+            id: Some(id),
+            create_time: "".to_string(),
+            update_time: "".to_string(),
+            name: format!("org_{id}"),
+            parent_id,
+            path: path.to_string(),
+            head_user_id: None,
+            create_by: 1,
+            status: 1,
+            tenant_id: 0,
+        }
+    }
+
+    #[test]
+    fn test_gen_org_path() {
+        assert_eq!(gen_org_path("/", 1), "/1/");
+        assert_eq!(gen_org_path("/1/", 3), "/1/3/");
+    }
+
+    #[test]
+    fn test_org_path_ids() {
+        // 移动到 /1/2/ 下时, 1 和 2 绑定的角色都会被继承
+        assert_eq!(org_path_ids("/1/2/"), vec![1, 2]);
+        assert_eq!(org_path_ids(&gen_org_path("/1/2/", 5)), vec![1, 2, 5]);
+        assert!(org_path_ids("/").is_empty());
+    }
+
+    #[test]
+    fn test_build_org_tree() {
+        let list = vec![
+            org(1, 0, "/1/"),
+            org(2, 1, "/1/2/"),
+            org(3, 2, "/1/2/3/"),
+            org(4, 0, "/4/"),
+        ];
+        let tree = build_org_tree(list);
+        assert_eq!(tree.len(), 2);
+        assert_eq!(tree[0].id, 1);
+        assert_eq!(tree[0].children[0].id, 2);
+        assert_eq!(tree[0].children[0].children[0].id, 3);
+        assert!(tree[1].children.is_empty());
+    }
+
+    #[test]
+    fn test_build_sub_tree() {
+        // 子树查询时上级不在列表中, 作为顶级
+        let list = vec![org(2, 1, "/1/2/"), org(3, 2, "/1/2/3/")];
+        let tree = build_org_tree(list);
+        assert_eq!(tree.len(), 1);
+        assert_eq!(tree[0].children[0].id, 3);
+    }
+}
@@ -0,0 +1,270 @@
+use super::{
+    check_org_by_id, get_org_role_ids, get_sub_tree_users, sync_sub_tree_auth, BindOrgRoleData,
+    BindOrgUserData, OrgUserQuery,
+};
+use crate::{
+    constraint::check_users_gain_roles,
+    entity::{
+        org_role_entity::OrgRoleEntity, org_unit_entity::OrgUnitEntity, role_entity::RoleEntity,
+        user_entity::UserEntity, user_org_entity::UserOrgEntity,
+    },
+    response::{MyError, ResponseBody},
+    user::{
+        check_user_by_user_id,
+        user_role_service::{check_role_exists, refresh_user_roles},
+        OptionData,
+    },
+    util::{
+        common::{diff_ids, get_tenant_id, get_transaction_tx},
+        outbox::add_events,
+        structs::OutboxEvent,
+    },
+    RB,
+};
+use actix_web::{get, post, web, HttpRequest, Responder};
+use rbs::to_value;
+
+#[utoipa::path(
+    tag = "org",
+    responses( (status = 200))
+)]
+#[post("/bind_users")]
+pub async fn bind_users(
+    req_data: web::Json<BindOrgUserData>,
+    req: HttpRequest,
+) -> Result<impl Responder, MyError> {
+    let tenant_id = get_tenant_id(&req);
+    let org = check_org_by_id(req_data.org_id, tenant_id)
+        .await
+        .ok_or(MyError::OrgNotExist)?;
+    for user_id in req_data.user_ids.iter() {
+        if check_user_by_user_id(*user_id, tenant_id).await.is_none() {
+            return Err(MyError::UserNotExist);
+        }
+    }
+
+    let ex = RB.acquire().await.expect("msg");
+    let current: Vec<UserOrgEntity> = UserOrgEntity::select_by_org(&ex, req_data.org_id)
+        .await
+        .expect("查询部门成员失败");
+    drop(ex);
+    let current_ids: Vec<i32> = current.into_iter().map(|val| val.user_id).collect();
+    let (add_ids, sub_ids) = diff_ids(&req_data.user_ids, &current_ids);
+
+    let org_role_ids = get_org_role_ids(&org.path, tenant_id).await;
+    check_users_gain_roles(&add_ids, &org_role_ids, tenant_id).await?;
+
+    log::debug!("add_ids {add_ids:?}");
+    log::debug!("sub_ids {sub_ids:?}");
+
+    let tx = get_transaction_tx().await.expect("get tx err");
+    for id in sub_ids.iter() {
+        let sub_res = UserOrgEntity::delete_by_org_and_user(&tx, req_data.org_id, *id).await;
+        if let Err(rbs::Error::E(error)) = sub_res {
+            log::error!("{}, {error}", MyError::BindOrgUserError);
+            tx.rollback().await.expect("msg");
+            return Err(MyError::BindOrgUserError);
+        }
+    }
+    if !add_ids.is_empty() {
+        let add_tabs: Vec<UserOrgEntity> = add_ids
+            .iter()
+            .map(|id| UserOrgEntity {
+                id: None,
+                user_id: *id,
+                org_id: req_data.org_id,
+                tenant_id,
+            })
+            .collect();
+        let add_res = UserOrgEntity::insert_batch(&tx, &add_tabs, add_tabs.len() as u64).await;
+        if let Err(rbs::Error::E(error)) = add_res {
+            log::error!("{}, {error}", MyError::BindOrgUserError);
+            tx.rollback().await.expect("msg");
+            return Err(MyError::BindOrgUserError);
+        }
+    }
+    // 加入或离开部门的成员继承的角色发生变化
+    let changed: Vec<i32> = add_ids.into_iter().chain(sub_ids).collect();
+    let event_res = add_events(&tx, OutboxEvent::UserRoles, &changed, tenant_id).await;
+    if let Err(rbs::Error::E(error)) = event_res {
+        log::error!("{}, {error}", MyError::BindOrgUserError);
+        tx.rollback().await.expect("msg");
+        return Err(MyError::BindOrgUserError);
+    }
+    tx.commit().await.expect("msg");
+
+    if !changed.is_empty() {
+        let ex = RB.acquire().await.expect("msg");
+        let users: Vec<UserEntity> = UserEntity::select_in_column(&ex, "id", &changed)
+            .await
+            .expect("查询用户失败");
+        drop(ex);
+        for user in users {
+            refresh_user_roles(user.id.expect("msg"), tenant_id).await?;
+        }
+    }
+
+    Ok(ResponseBody::success("绑定成功"))
+}
+
+#[utoipa::path(
+    tag = "org",
+    responses( (status = 200))
+)]
+#[post("/get_org_users")]
+pub async fn get_org_users(
+    req_data: web::Json<OrgUserQuery>,
+    req: HttpRequest,
+) -> Result<impl Responder, MyError> {
+    let tenant_id = get_tenant_id(&req);
+    let org = check_org_by_id(req_data.org_id, tenant_id)
+        .await
+        .ok_or(MyError::OrgNotExist)?;
+
+    let users: Vec<OptionData> = if req_data.with_sub.unwrap_or(false) {
+        get_sub_tree_users(&org.path, tenant_id).await
+    } else {
+        let ex = RB.acquire().await.expect("msg");
+        ex.query_decode(
+            "select user.id, user.name from user_org inner join user on user.id = user_org.user_id where user_org.org_id = ? and user.status = 1",
+            vec![to_value!(req_data.org_id)],
+        )
+        .await
+        .expect("查询部门成员失败")
+    };
+
+    Ok(ResponseBody::default(Some(users)))
+}
+
+#[utoipa::path(
+    tag = "org",
+    params(("id", description = "user id") ),
+    responses( (status = 200))
+)]
+#[get("/user_orgs/{id}")]
+pub async fn get_user_orgs(
+    id: web::Path<i32>,
+    req: HttpRequest,
+) -> Result<impl Responder, MyError> {
+    let tenant_id = get_tenant_id(&req);
+    let user_id = id.into_inner();
+    if check_user_by_user_id(user_id, tenant_id).await.is_none() {
+        return Err(MyError::UserNotExist);
+    }
+
+    let ex = RB.acquire().await.expect("msg");
+    let orgs: Vec<OrgUnitEntity> = ex
+        .query_decode(
+            "select org_unit.* from user_org inner join org_unit on org_unit.id = user_org.org_id where user_org.user_id = ? and org_unit.status = 1",
+            vec![to_value!(user_id)],
+        )
+        .await
+        .expect("查询用户部门失败");
+
+    Ok(ResponseBody::default(Some(orgs)))
+}
+
+#[utoipa::path(
+    tag = "org",
+    responses( (status = 200))
+)]
+#[post("/bind_roles")]
+pub async fn bind_roles(
+    req_data: web::Json<BindOrgRoleData>,
+    req: HttpRequest,
+) -> Result<impl Responder, MyError> {
+    let tenant_id = get_tenant_id(&req);
+    let org = check_org_by_id(req_data.org_id, tenant_id)
+        .await
+        .ok_or(MyError::OrgNotExist)?;
+    if check_role_exists(&req_data.role_ids, tenant_id)
+        .await
+        .is_none()
+    {
+        return Err(MyError::RoleNotExist);
+    }
+
+    let ex = RB.acquire().await.expect("msg");
+    let current: Vec<OrgRoleEntity> = OrgRoleEntity::select_by_org(&ex, req_data.org_id)
+        .await
+        .expect("查询部门角色失败");
+    drop(ex);
+    let current_ids: Vec<i32> = current.into_iter().map(|val| val.role_id).collect();
+    let (add_ids, sub_ids) = diff_ids(&req_data.role_ids, &current_ids);
+
+    let member_ids: Vec<i32> = get_sub_tree_users(&org.path, tenant_id)
+        .await
+        .into_iter()
+        .map(|val| val.id)
+        .collect();
+    check_users_gain_roles(&member_ids, &add_ids, tenant_id).await?;
+
+    log::debug!("add_ids {add_ids:?}");
+    log::debug!("sub_ids {sub_ids:?}");
+
+    let tx = get_transaction_tx().await.expect("get tx err");
+    for id in sub_ids.iter() {
+        let sub_res = OrgRoleEntity::delete_by_org_and_role(&tx, req_data.org_id, *id).await;
+        if let Err(rbs::Error::E(error)) = sub_res {
+            log::error!("{}, {error}", MyError::BindOrgRoleError);
+            tx.rollback().await.expect("msg");
+            return Err(MyError::BindOrgRoleError);
+        }
+    }
+    if !add_ids.is_empty() {
+        let add_tabs: Vec<OrgRoleEntity> = add_ids
+            .iter()
+            .map(|id| OrgRoleEntity {
+                id: None,
+                org_id: req_data.org_id,
+                role_id: *id,
+                tenant_id,
+            })
+            .collect();
+        let add_res = OrgRoleEntity::insert_batch(&tx, &add_tabs, add_tabs.len() as u64).await;
+        if let Err(rbs::Error::E(error)) = add_res {
+            log::error!("{}, {error}", MyError::BindOrgRoleError);
+            tx.rollback().await.expect("msg");
+            return Err(MyError::BindOrgRoleError);
+        }
+    }
+    let event_res = add_events(&tx, OutboxEvent::OrgMembers, &[req_data.org_id], tenant_id).await;
+    if let Err(rbs::Error::E(error)) = event_res {
+        log::error!("{}, {error}", MyError::BindOrgRoleError);
+        tx.rollback().await.expect("msg");
+        return Err(MyError::BindOrgRoleError);
+    }
+    tx.commit().await.expect("msg");
+
+    sync_sub_tree_auth(&org.path, tenant_id).await?;
+
+    Ok(ResponseBody::success("绑定成功"))
+}
+
+#[utoipa::path(
+    tag = "org",
+    params(("id", description = "org id") ),
+    responses( (status = 200))
+)]
+#[get("/org_roles/{id}")]
+pub async fn get_org_roles(
+    id: web::Path<i32>,
+    req: HttpRequest,
+) -> Result<impl Responder, MyError> {
+    let tenant_id = get_tenant_id(&req);
+    let org_id = id.into_inner();
+    check_org_by_id(org_id, tenant_id)
+        .await
+        .ok_or(MyError::OrgNotExist)?;
+
+    let ex = RB.acquire().await.expect("msg");
+    let roles: Vec<RoleEntity> = ex
+        .query_decode(
+            "select role.* from org_role left join role on org_role.role_id = role.id where org_role.org_id = ? and role.status = 1",
+            vec![to_value!(org_id)],
+        )
+        .await
+        .expect("获取部门绑定角色失败");
+
+    Ok(ResponseBody::default(Some(roles)))
+}
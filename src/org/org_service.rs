@@ -0,0 +1,314 @@
+use super::{
+    build_org_tree, check_org_by_id, gen_org_path, get_org_role_ids, get_sub_tree_users,
+    sync_sub_tree_auth, CreateOrgData, MoveOrgData, OrgUpdateData,
+};
+use crate::{
+    constraint::check_users_gain_roles,
+    entity::{
+        org_role_entity::OrgRoleEntity, org_unit_entity::OrgUnitEntity,
+        user_org_entity::UserOrgEntity,
+    },
+    response::{MyError, ResponseBody},
+    user::{check_user_by_user_id, user_role_service::refresh_user_roles},
+    util::{
+        common::{get_tenant_id, get_transaction_tx},
+        outbox::add_events,
+        structs::{OutboxEvent, Status},
+    },
+    RB,
+};
+use actix_web::{delete, get, post, web, HttpRequest, Responder};
+use rbs::to_value;
+use rs_service_util::time::get_current_time_fmt;
+
+#[utoipa::path(
+    tag = "org",
+    responses( (status = 200))
+)]
+#[post("/create_org")]
+async fn create_org(
+    req_data: web::Json<CreateOrgData>,
+    req: HttpRequest,
+) -> Result<impl Responder, MyError> {
+    let tenant_id = get_tenant_id(&req);
+    if check_user_by_user_id(req_data.create_by, tenant_id)
+        .await
+        .is_none()
+    {
+        return Err(MyError::UserNotExist);
+    }
+    if let Some(head_user_id) = req_data.head_user_id {
+        if check_user_by_user_id(head_user_id, tenant_id)
+            .await
+            .is_none()
+        {
+            return Err(MyError::UserNotExist);
+        }
+    }
+
+    let parent_id = req_data.parent_id.unwrap_or(0);
+    let parent_path = if parent_id == 0 {
+        "/".to_string()
+    } else {
+        match check_org_by_id(parent_id, tenant_id).await {
+            None => return Err(MyError::OrgNotExist),
+            Some(parent) => parent.path,
+        }
+    };
+
+    let mut new_org = OrgUnitEntity {
+        id: None,
+        create_time: get_current_time_fmt(),
+        update_time: get_current_time_fmt(),
+        name: req_data.name.clone(),
+        parent_id,
+        path: parent_path.clone(),
+        head_user_id: req_data.head_user_id,
+        create_by: req_data.create_by,
+        status: Status::ACTIVE as i8,
+        tenant_id,
+    };
+
+    let tx = get_transaction_tx().await.unwrap();
+    let insert_res = OrgUnitEntity::insert(&tx, &new_org).await;
+    match insert_res {
+        Err(rbs::Error::E(error)) => {
+            log::error!(" {} {error}", MyError::CreateOrgError);
+            tx.rollback().await.expect("rollback error");
+            return Err(MyError::CreateOrgError);
+        }
+        Ok(res) => {
+            // 路径包含自身id, 插入后才能确定
+            let id = res.last_insert_id.as_i64().unwrap_or(0) as i32;
+            new_org.id = Some(id);
+            new_org.path = gen_org_path(&parent_path, id);
+            let update_res = OrgUnitEntity::update_by_column(&tx, &new_org, "id").await;
+            if let Err(rbs::Error::E(error)) = update_res {
+                log::error!(" {} {error}", MyError::CreateOrgError);
+                tx.rollback().await.expect("rollback error");
+                return Err(MyError::CreateOrgError);
+            }
+        }
+    }
+    tx.commit().await.expect("commit error");
+
+    Ok(ResponseBody::success("部门创建成功"))
+}
+
+#[utoipa::path(
+    tag = "org",
+    responses( (status = 200))
+)]
+#[get("/get_org_tree")]
+async fn get_org_tree(req: HttpRequest) -> impl Responder {
+    let tenant_id = get_tenant_id(&req);
+    let ex_db = RB.acquire().await.expect("msg");
+    let list: Vec<OrgUnitEntity> = OrgUnitEntity::select_by_tenant(&ex_db, tenant_id)
+        .await
+        .expect("部门查询失败");
+
+    ResponseBody::default(Some(build_org_tree(list)))
+}
+
+#[utoipa::path(
+    tag = "org",
+    params(("id", description = "org id") ),
+    responses( (status = 200))
+)]
+#[get("/sub_orgs/{id}")]
+async fn get_sub_orgs(id: web::Path<i32>, req: HttpRequest) -> Result<impl Responder, MyError> {
+    let tenant_id = get_tenant_id(&req);
+    let org = check_org_by_id(id.into_inner(), tenant_id)
+        .await
+        .ok_or(MyError::OrgNotExist)?;
+
+    let ex_db = RB.acquire().await.expect("msg");
+    let list: Vec<OrgUnitEntity> = OrgUnitEntity::select_sub_tree(&ex_db, &org.path, tenant_id)
+        .await
+        .expect("部门查询失败");
+
+    Ok(ResponseBody::default(Some(build_org_tree(list))))
+}
+
+#[utoipa::path(
+    tag = "org",
+    responses( (status = 200))
+)]
+#[post("/update_org")]
+pub async fn update_org_by_id(
+    req_data: web::Json<OrgUpdateData>,
+    req: HttpRequest,
+) -> Result<impl Responder, MyError> {
+    let tenant_id = get_tenant_id(&req);
+    match check_org_by_id(req_data.id, tenant_id).await {
+        None => {
+            return Err(MyError::OrgNotExist);
+        }
+        Some(mut org) => {
+            if let Some(head_user_id) = req_data.head_user_id {
+                if check_user_by_user_id(head_user_id, tenant_id)
+                    .await
+                    .is_none()
+                {
+                    return Err(MyError::UserNotExist);
+                }
+                org.head_user_id = Some(head_user_id);
+            }
+            org.name = req_data.name.clone().unwrap_or(org.name);
+            org.update_time = get_current_time_fmt();
+
+            let tx = get_transaction_tx().await.expect("get tx err");
+            let update_res = OrgUnitEntity::update_by_column(&tx, &org, "id").await;
+            if let Err(rbs::Error::E(error)) = update_res {
+                log::error!(" {} {error}", MyError::UpdateOrgError);
+                tx.rollback().await.expect("rollback error");
+                return Err(MyError::UpdateOrgError);
+            }
+            tx.commit().await.expect("msg");
+        }
+    }
+
+    Ok(ResponseBody::success("部门更新成功"))
+}
+
+#[utoipa::path(
+    tag = "org",
+    responses( (status = 200))
+)]
+#[post("/move_org")]
+pub async fn move_org(
+    req_data: web::Json<MoveOrgData>,
+    req: HttpRequest,
+) -> Result<impl Responder, MyError> {
+    let tenant_id = get_tenant_id(&req);
+    let org = check_org_by_id(req_data.id, tenant_id)
+        .await
+        .ok_or(MyError::OrgNotExist)?;
+
+    let parent_path = if req_data.parent_id == 0 {
+        "/".to_string()
+    } else {
+        let parent = check_org_by_id(req_data.parent_id, tenant_id)
+            .await
+            .ok_or(MyError::OrgNotExist)?;
+        // 不能移动到自身或子部门下
+        if parent.path.starts_with(&org.path) {
+            return Err(MyError::OrgMoveError);
+        }
+        parent.path
+    };
+    let new_path = gen_org_path(&parent_path, req_data.id);
+
+    // 移动后成员继承新上级部门及其所有上级的角色
+    if req_data.parent_id != 0 {
+        let member_ids: Vec<i32> = get_sub_tree_users(&org.path, tenant_id)
+            .await
+            .into_iter()
+            .map(|val| val.id)
+            .collect();
+        let parent_role_ids = get_org_role_ids(&parent_path, tenant_id).await;
+        check_users_gain_roles(&member_ids, &parent_role_ids, tenant_id).await?;
+    }
+
+    let tx = get_transaction_tx().await.expect("get tx err");
+    let update_res: Result<Option<()>, rbs::Error> = tx
+        .query_decode(
+            "update org_unit set path = concat(?, substring(path, ?)), update_time = ? where path like concat(?, '%') and tenant_id = ?",
+            vec![
+                to_value!(new_path.clone()),
+                to_value!(org.path.chars().count() as i32 + 1),
+                to_value!(get_current_time_fmt()),
+                to_value!(org.path.clone()),
+                to_value!(tenant_id),
+            ],
+        )
+        .await;
+    if let Err(rbs::Error::E(error)) = update_res {
+        log::error!(" {} {error}", MyError::UpdateOrgError);
+        tx.rollback().await.expect("rollback error");
+        return Err(MyError::UpdateOrgError);
+    }
+    let update_res: Result<Option<()>, rbs::Error> = tx
+        .query_decode(
+            "update org_unit set parent_id = ? where id = ?",
+            vec![to_value!(req_data.parent_id), to_value!(req_data.id)],
+        )
+        .await;
+    if let Err(rbs::Error::E(error)) = update_res {
+        log::error!(" {} {error}", MyError::UpdateOrgError);
+        tx.rollback().await.expect("rollback error");
+        return Err(MyError::UpdateOrgError);
+    }
+    let event_res = add_events(&tx, OutboxEvent::OrgMembers, &[req_data.id], tenant_id).await;
+    if let Err(rbs::Error::E(error)) = event_res {
+        log::error!(" {} {error}", MyError::UpdateOrgError);
+        tx.rollback().await.expect("rollback error");
+        return Err(MyError::UpdateOrgError);
+    }
+    tx.commit().await.expect("msg");
+
+    // 上级部门变化, 继承的角色也随之变化
+    sync_sub_tree_auth(&new_path, tenant_id).await?;
+
+    Ok(ResponseBody::success("部门移动成功"))
+}
+
+#[utoipa::path(
+    tag = "org",
+    params(("id", description = "org id") ),
+    responses( (status = 200))
+)]
+#[delete("/{id}")]
+pub async fn delete_org(id: web::Path<i32>, req: HttpRequest) -> Result<impl Responder, MyError> {
+    let tenant_id = get_tenant_id(&req);
+    let id = id.into_inner();
+    let mut org = check_org_by_id(id, tenant_id)
+        .await
+        .ok_or(MyError::OrgNotExist)?;
+
+    let ex_db = RB.acquire().await.expect("msg");
+    let sub_tree: Vec<OrgUnitEntity> = OrgUnitEntity::select_sub_tree(&ex_db, &org.path, tenant_id)
+        .await
+        .expect("部门查询失败");
+    drop(ex_db);
+    if sub_tree.len() > 1 {
+        return Err(MyError::OrgHasChildren);
+    }
+
+    // 先记下成员, 删除后刷新他们的权限
+    let members = get_sub_tree_users(&org.path, tenant_id).await;
+
+    org.status = Status::DEACTIVE as i8;
+    org.update_time = get_current_time_fmt();
+    let tx = get_transaction_tx().await.expect("get tx err");
+    let update_res = OrgUnitEntity::update_by_column(&tx, &org, "id").await;
+    if let Err(rbs::Error::E(error)) = update_res {
+        log::error!(" {} {error}", MyError::UpdateOrgError);
+        tx.rollback().await.expect("rollback error");
+        return Err(MyError::UpdateOrgError);
+    }
+    if let Err(rbs::Error::E(error)) = UserOrgEntity::delete_by_column(&tx, "org_id", id).await {
+        log::error!(" {} {error}", MyError::BindOrgUserError);
+        tx.rollback().await.expect("rollback error");
+        return Err(MyError::BindOrgUserError);
+    }
+    if let Err(rbs::Error::E(error)) = OrgRoleEntity::delete_by_column(&tx, "org_id", id).await {
+        log::error!(" {} {error}", MyError::BindOrgRoleError);
+        tx.rollback().await.expect("rollback error");
+        return Err(MyError::BindOrgRoleError);
+    }
+    let member_ids: Vec<i32> = members.iter().map(|val| val.id).collect();
+    let event_res = add_events(&tx, OutboxEvent::UserRoles, &member_ids, tenant_id).await;
+    if let Err(rbs::Error::E(error)) = event_res {
+        log::error!(" {} {error}", MyError::UpdateOrgError);
+        tx.rollback().await.expect("rollback error");
+        return Err(MyError::UpdateOrgError);
+    }
+    tx.commit().await.expect("msg");
+
+    for user in members {
+        refresh_user_roles(user.id, tenant_id).await?;
+    }
+
+    Ok(ResponseBody::success("部门删除成功"))
+}
@@ -0,0 +1,111 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use utoipa::ToSchema;
+use utoipa_actix_web::service_config::ServiceConfig;
+
+use crate::{
+    entity::policy_entity::PolicyEntity,
+    util::{access_guard::RouteAccess, structs::CreateByData},
+    RB,
+};
+
+mod policy_service;
+
+pub mod policy_eval;
+
+pub fn configure() -> impl FnOnce(&mut ServiceConfig) {
+    |config: &mut ServiceConfig| {
+        config.service(policy_service::create_policy);
+        config.service(policy_service::get_policy_list);
+        config.service(policy_service::update_policy_by_id);
+        config.service(policy_service::delete_policy);
+    }
+}
+
+/// 接口所需权限, 由 `access_guard` 中间件校验
+pub const ROUTE_ACCESS: &[RouteAccess] = &[
+    RouteAccess::new("POST", "/api/policy/create_policy", "POLICY_CREATE"),
+    RouteAccess::new("POST", "/api/policy/get_policy_list", "POLICY_LIST"),
+    RouteAccess::new("POST", "/api/policy/update_policy", "POLICY_UPDATE"),
+    RouteAccess::new("DELETE", "/api/policy/{id}", "POLICY_DELETE"),
+];
+
+/// 条件运算符
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum PolicyOp {
+    Eq,
+    Ne,
+    In,
+    NotIn,
+    Gt,
+    Ge,
+    Lt,
+    Le,
+}
+
+/// 单个条件, 例如 `resource.city eq subject.city`
+///
+/// `attr` / `ref_attr` 以 `subject.` 或 `resource.` 开头;
+/// `ref_attr` 存在时与另一个属性比较, 否则与常量 `value` 比较
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct PolicyCondition {
+    pub attr: String,
+    pub op: PolicyOp,
+    #[schema(value_type = Option<Object>)]
+    pub value: Option<Value>,
+    pub ref_attr: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct CreatePolicyData {
+    pub name: String,
+    pub access_id: i32,
+    pub effect: i8,
+    pub conditions: Vec<PolicyCondition>,
+    pub create_by: i32,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct PolicyUpdateData {
+    pub id: i32,
+    pub name: Option<String>,
+    pub effect: Option<i8>,
+    pub conditions: Option<Vec<PolicyCondition>>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct PolicyListQuery {
+    pub name: Option<String>,
+    pub access_id: Option<i32>,
+    pub page_no: i32,
+    pub take: i32,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PolicyListListData {
+    pub id: i32,
+    pub create_time: String,
+    pub update_time: String,
+    pub name: String,
+    pub access_id: i32,
+    pub effect: i8,
+    pub conditions: Vec<PolicyCondition>,
+    pub create_by: Option<CreateByData>, // 创建的用户id
+    pub status: i8,
+}
+
+pub async fn check_policy_by_id(id: i32, tenant_id: i32) -> Option<PolicyEntity> {
+    let ex_db = RB.acquire().await.expect("get db ex error");
+    PolicyEntity::select_by_id(&ex_db, id, tenant_id)
+        .await
+        .expect("策略查询失败")
+}
+
+/// 查询权限上挂载的所有策略
+pub async fn get_policies_by_access(access_id: i32, tenant_id: i32) -> Vec<PolicyEntity> {
+    let ex_db = RB.acquire().await.expect("get db ex error");
+    PolicyEntity::select_by_access(&ex_db, access_id, tenant_id)
+        .await
+        .expect("策略查询失败")
+}
@@ -0,0 +1,227 @@
+use std::cmp::Ordering;
+
+use serde_json::{Map, Value};
+
+use super::{PolicyCondition, PolicyOp};
+use crate::{entity::policy_entity::PolicyEntity, util::structs::PolicyEffect};
+
+/// 策略求值时可用的属性
+///
+/// subject: 当前用户的属性 (UserEntity 字段, 不含密码)
+/// resource: 调用方传入的资源属性
+#[derive(Clone, Debug, Default)]
+pub struct PolicyAttrs {
+    pub subject: Map<String, Value>,
+    pub resource: Map<String, Value>,
+}
+
+impl PolicyAttrs {
+    fn get(&self, path: &str) -> Option<&Value> {
+        let (scope, name) = path.split_once('.')?;
+        match scope {
+            "subject" => self.subject.get(name),
+            "resource" => self.resource.get(name),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum PolicyDecision {
+    /// 通过, 携带命中的允许策略id; 没有挂载允许策略时为 None
+    Allow(Option<i32>),
+    /// 拒绝, 携带命中的拒绝策略id; 允许策略均未命中时为 None
+    Deny(Option<i32>),
+}
+
+/// 解析并求值一组策略
+///
+/// 任一拒绝策略命中即拒绝; 存在允许策略时至少命中一条才通过
+pub fn eval_policies(policies: &[PolicyEntity], attrs: &PolicyAttrs) -> PolicyDecision {
+    let mut has_allow = false;
+    let mut allow_id: Option<i32> = None;
+
+    for policy in policies {
+        let conditions: Vec<PolicyCondition> = match serde_json::from_str(&policy.conditions) {
+            Ok(list) => list,
+            Err(e) => {
+                log::error!("策略 {:?} 条件解析失败 {e}", policy.id);
+                // 条件无法解析时按不命中处理, 拒绝策略则保守地视为命中
+                if PolicyEffect::from(policy.effect) == PolicyEffect::DENY {
+                    return PolicyDecision::Deny(policy.id);
+                }
+                has_allow = true;
+                continue;
+            }
+        };
+        let is_match = match_conditions(&conditions, attrs);
+        match PolicyEffect::from(policy.effect) {
+            PolicyEffect::DENY => {
+                if is_match {
+                    return PolicyDecision::Deny(policy.id);
+                }
+            }
+            PolicyEffect::ALLOW => {
+                has_allow = true;
+                if is_match && allow_id.is_none() {
+                    allow_id = policy.id;
+                }
+            }
+        }
+    }
+
+    match (has_allow, allow_id) {
+        (false, _) => PolicyDecision::Allow(None),
+        (true, Some(id)) => PolicyDecision::Allow(Some(id)),
+        (true, None) => PolicyDecision::Deny(None),
+    }
+}
+
+/// 所有条件都满足才算命中
+pub fn match_conditions(conditions: &[PolicyCondition], attrs: &PolicyAttrs) -> bool {
+    conditions.iter().all(|cond| match_condition(cond, attrs))
+}
+
+fn match_condition(cond: &PolicyCondition, attrs: &PolicyAttrs) -> bool {
+    let left = match attrs.get(&cond.attr) {
+        Some(val) => val,
+        None => return false,
+    };
+    let right = match (&cond.ref_attr, &cond.value) {
+        (Some(ref_attr), _) => attrs.get(ref_attr),
+        (None, Some(val)) => Some(val),
+        (None, None) => None,
+    };
+    let right = match right {
+        Some(val) => val,
+        None => return false,
+    };
+
+    match cond.op {
+        PolicyOp::Eq => value_eq(left, right),
+        PolicyOp::Ne => !value_eq(left, right),
+        PolicyOp::In => value_in(left, right),
+        PolicyOp::NotIn => !value_in(left, right),
+        PolicyOp::Gt => value_cmp(left, right) == Some(Ordering::Greater),
+        PolicyOp::Ge => matches!(
+            value_cmp(left, right),
+            Some(Ordering::Greater | Ordering::Equal)
+        ),
+        PolicyOp::Lt => value_cmp(left, right) == Some(Ordering::Less),
+        PolicyOp::Le => matches!(
+            value_cmp(left, right),
+            Some(Ordering::Less | Ordering::Equal)
+        ),
+    }
+}
+
+fn value_eq(left: &Value, right: &Value) -> bool {
+    match (left.as_f64(), right.as_f64()) {
+        (Some(l), Some(r)) => l == r,
+        _ => left == right,
+    }
+}
+
+fn value_in(left: &Value, right: &Value) -> bool {
+    match right.as_array() {
+        Some(list) => list.iter().any(|item| value_eq(left, item)),
+        None => false,
+    }
+}
+
+fn value_cmp(left: &Value, right: &Value) -> Option<Ordering> {
+    match (left, right) {
+        (Value::Number(l), Value::Number(r)) => l.as_f64()?.partial_cmp(&r.as_f64()?),
+        (Value::String(l), Value::String(r)) => Some(l.cmp(r)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::{eval_policies, PolicyAttrs, PolicyDecision};
+    use crate::entity::policy_entity::PolicyEntity;
+
+    fn policy(id: i32, effect: i8, conditions: serde_json::Value) -> PolicyEntity {
+        PolicyEntity {
+            id: Some(id),
+            create_time: "".to_string(),
+            update_time: "".to_string(),
+            name: format!("policy_{id}"),
+            access_id: 1,
+            effect,
+            conditions: conditions.to_string(),
+            create_by: 1,
+            status: 1,
+            tenant_id: 0,
+        }
+    }
+
+    fn attrs(subject: serde_json::Value, resource: serde_json::Value) -> PolicyAttrs {
+        PolicyAttrs {
+            subject: subject.as_object().unwrap().clone(),
+            resource: resource.as_object().unwrap().clone(),
+        }
+    }
+
+    #[test]
+    fn test_no_policy_allow() {
+        let res = eval_policies(&[], &PolicyAttrs::default());
+        assert_eq!(res, PolicyDecision::Allow(None));
+    }
+
+    #[test]
+    fn test_allow_by_ref_attr() {
+        // CLIENT 用户只能读自己的资料
+        let list = vec![
+            policy(
+                1,
+                1,
+                json!([{"attr": "subject.user_type", "op": "ne", "value": 1}]),
+            ),
+            policy(
+                2,
+                1,
+                json!([{"attr": "resource.user_id", "op": "eq", "ref_attr": "subject.id"}]),
+            ),
+        ];
+        let own = attrs(json!({"id": 5, "user_type": 1}), json!({"user_id": 5}));
+        let other = attrs(json!({"id": 5, "user_type": 1}), json!({"user_id": 6}));
+        let biz = attrs(json!({"id": 5, "user_type": 0}), json!({"user_id": 6}));
+
+        assert_eq!(eval_policies(&list, &own), PolicyDecision::Allow(Some(2)));
+        assert_eq!(eval_policies(&list, &other), PolicyDecision::Deny(None));
+        assert_eq!(eval_policies(&list, &biz), PolicyDecision::Allow(Some(1)));
+    }
+
+    #[test]
+    fn test_deny_first() {
+        let list = vec![
+            policy(1, 1, json!([])),
+            policy(
+                2,
+                0,
+                json!([{"attr": "resource.city", "op": "in", "value": ["a", "b"]}]),
+            ),
+        ];
+        let res = eval_policies(&list, &attrs(json!({}), json!({"city": "b"})));
+        assert_eq!(res, PolicyDecision::Deny(Some(2)));
+        let res = eval_policies(&list, &attrs(json!({}), json!({"city": "c"})));
+        assert_eq!(res, PolicyDecision::Allow(Some(1)));
+    }
+
+    #[test]
+    fn test_missing_attr_not_match() {
+        let list = vec![policy(
+            1,
+            1,
+            json!([{"attr": "resource.amount", "op": "le", "value": 100}]),
+        )];
+        let res = eval_policies(&list, &attrs(json!({}), json!({})));
+        assert_eq!(res, PolicyDecision::Deny(None));
+        let res = eval_policies(&list, &attrs(json!({}), json!({"amount": 99.5})));
+        assert_eq!(res, PolicyDecision::Allow(Some(1)));
+    }
+}
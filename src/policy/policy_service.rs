@@ -0,0 +1,198 @@
+use super::{
+    check_policy_by_id, CreatePolicyData, PolicyCondition, PolicyListListData, PolicyListQuery,
+    PolicyUpdateData,
+};
+use crate::{
+    access::check_access_by_id,
+    entity::policy_entity::PolicyEntity,
+    response::{MyError, ResponseBody},
+    user::check_user_by_user_id,
+    util::{
+        common::{get_tenant_id, get_transaction_tx},
+        structs::{CreateByData, Status},
+    },
+    RB,
+};
+use actix_web::{delete, post, web, HttpRequest, Responder};
+use rbs::to_value;
+use rs_service_util::{
+    sql_tool::{SqlTool, SqlToolPageData},
+    time::get_current_time_fmt,
+};
+
+#[utoipa::path(
+    tag = "policy",
+    responses( (status = 200))
+)]
+#[post("/create_policy")]
+async fn create_policy(
+    req_data: web::Json<CreatePolicyData>,
+    req: HttpRequest,
+) -> Result<impl Responder, MyError> {
+    let tenant_id = get_tenant_id(&req);
+    if check_user_by_user_id(req_data.create_by, tenant_id)
+        .await
+        .is_none()
+    {
+        return Err(MyError::UserNotExist);
+    }
+    if check_access_by_id(req_data.access_id, tenant_id)
+        .await
+        .is_none()
+    {
+        return Err(MyError::AccessNotExist);
+    }
+
+    let new_policy = PolicyEntity {
+        id: None,
+        create_time: get_current_time_fmt(),
+        update_time: get_current_time_fmt(),
+        name: req_data.name.clone(),
+        access_id: req_data.access_id,
+        effect: req_data.effect,
+        conditions: serde_json::to_string(&req_data.conditions).expect("msg"),
+        create_by: req_data.create_by,
+        status: Status::ACTIVE as i8,
+        tenant_id,
+    };
+
+    let tx = get_transaction_tx().await.unwrap();
+    let insert_res = PolicyEntity::insert(&tx, &new_policy).await;
+    if let Err(rbs::Error::E(error)) = insert_res {
+        log::error!(" {} {error}", MyError::CreatePolicyError);
+        tx.rollback().await.expect("rollback error");
+        return Err(MyError::CreatePolicyError);
+    }
+    tx.commit().await.expect("commit error");
+
+    Ok(ResponseBody::success("策略创建成功"))
+}
+
+#[utoipa::path(
+    tag = "policy",
+    responses( (status = 200))
+)]
+#[post("/get_policy_list")]
+async fn get_policy_list(req_data: web::Json<PolicyListQuery>, req: HttpRequest) -> impl Responder {
+    let tenant_id = get_tenant_id(&req);
+    let ex_db = RB.acquire().await.expect("msg");
+    let mut tool = SqlTool::init("select * from policy", "order by create_time desc");
+
+    if let Some(name) = req_data.name.clone() {
+        tool.append_sql_filed("name", to_value!(name));
+    }
+    if let Some(access_id) = req_data.access_id {
+        tool.append_sql_filed("access_id", to_value!(access_id));
+    }
+    tool.append_sql_filed("tenant_id", to_value!(tenant_id));
+    tool.append_sql_filed("status", to_value!(1));
+
+    let page_sql = tool.gen_page_sql(req_data.page_no, req_data.take);
+    let db_res: Vec<PolicyEntity> = ex_db
+        .query_decode(&page_sql, tool.opt_val.clone())
+        .await
+        .expect("msg");
+
+    let mut records: Vec<PolicyListListData> = vec![];
+    for val in db_res {
+        let create_by: Option<CreateByData> = ex_db
+            .query_decode(
+                "select id, name from user where id=?",
+                vec![to_value!(val.create_by)],
+            )
+            .await
+            .expect("err");
+        let conditions: Vec<PolicyCondition> =
+            serde_json::from_str(&val.conditions).unwrap_or_default();
+        records.push(PolicyListListData {
+            id: val.id.expect("msg"),
+            create_time: val.create_time,
+            update_time: val.update_time,
+            name: val.name,
+            access_id: val.access_id,
+            effect: val.effect,
+            conditions,
+            create_by,
+            status: val.status,
+        });
+    }
+    let conf = SqlToolPageData {
+        ex_db,
+        table: "policy".to_string(),
+        records,
+        page_no: req_data.page_no as u64,
+        page_size: req_data.take as u64,
+    };
+    let db_res = tool.page_query(conf).await;
+
+    ResponseBody::default(Some(db_res))
+}
+
+#[utoipa::path(
+    tag = "policy",
+    responses( (status = 200))
+)]
+#[post("/update_policy")]
+pub async fn update_policy_by_id(
+    req_data: web::Json<PolicyUpdateData>,
+    req: HttpRequest,
+) -> Result<impl Responder, MyError> {
+    let tenant_id = get_tenant_id(&req);
+    match check_policy_by_id(req_data.id, tenant_id).await {
+        None => {
+            return Err(MyError::PolicyNotExist);
+        }
+        Some(mut policy) => {
+            policy.name = req_data.name.clone().unwrap_or(policy.name);
+            policy.effect = req_data.effect.unwrap_or(policy.effect);
+            if let Some(conditions) = &req_data.conditions {
+                policy.conditions = serde_json::to_string(conditions).expect("msg");
+            }
+            policy.update_time = get_current_time_fmt();
+
+            let tx = get_transaction_tx().await.expect("get tx err");
+            let update_res = PolicyEntity::update_by_column(&tx, &policy, "id").await;
+            if let Err(rbs::Error::E(error)) = update_res {
+                log::error!(" {} {error}", MyError::UpdatePolicyError);
+                tx.rollback().await.expect("rollback error");
+                return Err(MyError::UpdatePolicyError);
+            }
+            tx.commit().await.expect("msg");
+        }
+    }
+
+    Ok(ResponseBody::success("策略更新成功"))
+}
+
+#[utoipa::path(
+    tag = "policy",
+    responses( (status = 200))
+)]
+#[delete("/{id}")]
+pub async fn delete_policy(
+    id: web::Path<i32>,
+    req: HttpRequest,
+) -> Result<impl Responder, MyError> {
+    let tenant_id = get_tenant_id(&req);
+    let id = id.into_inner();
+
+    match check_policy_by_id(id, tenant_id).await {
+        None => {
+            return Err(MyError::PolicyNotExist);
+        }
+        Some(mut policy) => {
+            policy.status = Status::DEACTIVE as i8;
+            policy.update_time = get_current_time_fmt();
+            let tx = get_transaction_tx().await.expect("get tx err");
+            let update_res = PolicyEntity::update_by_column(&tx, &policy, "id").await;
+            if let Err(rbs::Error::E(error)) = update_res {
+                log::error!(" {} {error}", MyError::UpdatePolicyError);
+                tx.rollback().await.expect("rollback error");
+                return Err(MyError::UpdatePolicyError);
+            }
+            tx.commit().await.expect("msg");
+        }
+    }
+
+    Ok(ResponseBody::success("策略删除成功"))
+}
@@ -0,0 +1,104 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use utoipa_actix_web::service_config::ServiceConfig;
+
+use crate::{
+    entity::{access_entity::AccessEntity, role_entity::RoleEntity},
+    operation::OperationData,
+    response::MyError,
+    util::access_guard::RouteAccess,
+};
+use rbac_plan::{PlanItem, RbacConfig};
+
+mod rbac_service;
+
+pub mod rbac_plan;
+
+pub fn configure() -> impl FnOnce(&mut ServiceConfig) {
+    |config: &mut ServiceConfig| {
+        config.service(rbac_service::export_rbac);
+        config.service(rbac_service::import_rbac);
+    }
+}
+
+/// 接口所需权限, 由 `access_guard` 中间件校验
+pub const ROUTE_ACCESS: &[RouteAccess] = &[
+    RouteAccess::new("POST", "/api/rbac/export", "RBAC_EXPORT"),
+    RouteAccess::new("POST", "/api/rbac/import", "RBAC_IMPORT"),
+];
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ConfigFormat {
+    Yaml,
+    Json,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct RbacExportData {
+    pub format: ConfigFormat,
+    pub with_users: Option<bool>, // 是否导出角色直接绑定的用户
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct RbacImportData {
+    pub format: ConfigFormat,
+    pub content: String,
+    pub dry_run: bool,
+    pub prune: Option<bool>, // 是否删除配置中不存在的权限和角色
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RbacImportRes {
+    pub dry_run: bool,
+    pub plan: Vec<PlanItem>,
+    pub operation: Option<OperationData>, // 刷新用户权限的后台任务
+}
+
+/// 租户下现有的权限配置, 以及名称到记录的映射
+pub struct RbacState {
+    pub config: RbacConfig,
+    pub access: HashMap<String, AccessEntity>,
+    pub roles: HashMap<String, RoleEntity>,
+    pub users: HashMap<String, i32>, // 计划中涉及的用户
+}
+
+impl RbacState {
+    pub fn access_id(&self, name: &str) -> i32 {
+        self.access
+            .get(name)
+            .and_then(|val| val.id)
+            .unwrap_or_default()
+    }
+
+    pub fn role_id(&self, name: &str) -> i32 {
+        self.roles
+            .get(name)
+            .and_then(|val| val.id)
+            .unwrap_or_default()
+    }
+
+    pub fn user_id(&self, name: &str) -> i32 {
+        self.users.get(name).copied().unwrap_or_default()
+    }
+}
+
+pub fn parse_config(format: ConfigFormat, content: &str) -> Result<RbacConfig, MyError> {
+    let res = match format {
+        ConfigFormat::Yaml => serde_yaml::from_str(content).map_err(|error| error.to_string()),
+        ConfigFormat::Json => serde_json::from_str(content).map_err(|error| error.to_string()),
+    };
+    res.map_err(|error| {
+        log::error!(" {} {error}", MyError::RbacConfigError);
+        MyError::RbacConfigError
+    })
+}
+
+pub fn dump_config(format: ConfigFormat, config: &RbacConfig) -> String {
+    match format {
+        ConfigFormat::Yaml => serde_yaml::to_string(config).expect("msg"),
+        ConfigFormat::Json => serde_json::to_string_pretty(config).expect("msg"),
+    }
+}
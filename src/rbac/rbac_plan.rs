@@ -0,0 +1,372 @@
+use std::collections::{HashMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+
+use crate::util::structs::DataScope;
+
+/// 不参与清理的内置权限和角色
+pub const BUILTIN_NAME: &str = "ADMIN";
+
+/// 以名称描述的权限配置, 可导出到 git 并按环境导入
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct RbacConfig {
+    #[serde(default)]
+    pub access: Vec<String>,
+    #[serde(default)]
+    pub roles: Vec<RoleConfig>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RoleConfig {
+    pub name: String,
+    #[serde(default = "default_data_scope")]
+    pub data_scope: i8,
+    #[serde(default)]
+    pub access: Vec<String>,
+    /// 为空时不处理该角色的用户绑定
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub users: Option<Vec<String>>,
+}
+
+/// 与新建角色一致, 默认本租户
+fn default_data_scope() -> i8 {
+    DataScope::TENANT as i8
+}
+
+/// 导入计划中的一项变更
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum PlanItem {
+    CreateAccess { name: String },
+    DeleteAccess { name: String },
+    CreateRole { name: String, data_scope: i8 },
+    UpdateRole { name: String, data_scope: i8 },
+    DeleteRole { name: String },
+    BindAccess { role: String, access: String },
+    UnbindAccess { role: String, access: String },
+    BindUser { role: String, user: String },
+    UnbindUser { role: String, user: String },
+}
+
+/// 名称不能重复, 数据范围必须是已知的值, 角色引用的权限必须在配置中声明
+pub fn check_config(config: &RbacConfig) -> Result<(), String> {
+    let mut access: HashSet<&str> = HashSet::new();
+    for name in config.access.iter() {
+        if !access.insert(name) {
+            return Err(format!("权限 {name} 重复"));
+        }
+    }
+    let mut roles: HashSet<&str> = HashSet::new();
+    for role in config.roles.iter() {
+        if !roles.insert(&role.name) {
+            return Err(format!("角色 {} 重复", role.name));
+        }
+        if !(DataScope::SELF as i8..=DataScope::ALL as i8).contains(&role.data_scope) {
+            return Err(format!(
+                "角色 {} 的数据范围 {} 不正确",
+                role.name, role.data_scope
+            ));
+        }
+        if let Some(name) = role
+            .access
+            .iter()
+            .find(|name| !access.contains(name.as_str()))
+        {
+            return Err(format!("角色 {} 引用了未声明的权限 {name}", role.name));
+        }
+    }
+    Ok(())
+}
+
+/// 对比目标配置和现有配置, 生成变更计划
+///
+/// prune 为 true 时删除配置中不存在的权限和角色 (ADMIN 除外)
+pub fn build_plan(target: &RbacConfig, current: &RbacConfig, prune: bool) -> Vec<PlanItem> {
+    let mut plan: Vec<PlanItem> = vec![];
+
+    for name in target.access.iter() {
+        if !current.access.contains(name) {
+            plan.push(PlanItem::CreateAccess { name: name.clone() });
+        }
+    }
+
+    let current_roles: HashMap<&str, &RoleConfig> = current
+        .roles
+        .iter()
+        .map(|role| (role.name.as_str(), role))
+        .collect();
+    for role in target.roles.iter() {
+        let exist = current_roles.get(role.name.as_str());
+        match exist {
+            None => plan.push(PlanItem::CreateRole {
+                name: role.name.clone(),
+                data_scope: role.data_scope,
+            }),
+            Some(exist) if exist.data_scope != role.data_scope => plan.push(PlanItem::UpdateRole {
+                name: role.name.clone(),
+                data_scope: role.data_scope,
+            }),
+            _ => {}
+        }
+
+        let exist_access = exist.map(|val| val.access.clone()).unwrap_or_default();
+        let (add, sub) = diff_names(&role.access, &exist_access);
+        plan.extend(add.into_iter().map(|access| PlanItem::BindAccess {
+            role: role.name.clone(),
+            access,
+        }));
+        plan.extend(sub.into_iter().map(|access| PlanItem::UnbindAccess {
+            role: role.name.clone(),
+            access,
+        }));
+
+        if let Some(users) = &role.users {
+            let exist_users = exist.and_then(|val| val.users.clone()).unwrap_or_default();
+            let (add, sub) = diff_names(users, &exist_users);
+            plan.extend(add.into_iter().map(|user| PlanItem::BindUser {
+                role: role.name.clone(),
+                user,
+            }));
+            plan.extend(sub.into_iter().map(|user| PlanItem::UnbindUser {
+                role: role.name.clone(),
+                user,
+            }));
+        }
+    }
+
+    if prune {
+        for role in current.roles.iter() {
+            let keep =
+                role.name == BUILTIN_NAME || target.roles.iter().any(|val| val.name == role.name);
+            if !keep {
+                plan.push(PlanItem::DeleteRole {
+                    name: role.name.clone(),
+                });
+            }
+        }
+        for name in current.access.iter() {
+            if name != BUILTIN_NAME && !target.access.contains(name) {
+                plan.push(PlanItem::DeleteAccess { name: name.clone() });
+            }
+        }
+    }
+
+    plan
+}
+
+/// 计划不能解绑 ADMIN 角色的权限和用户, 也不能把 ADMIN 权限授予任何角色
+pub fn check_plan(plan: &[PlanItem]) -> Result<(), String> {
+    for item in plan {
+        match item {
+            PlanItem::UnbindAccess { role, access } if role == BUILTIN_NAME => {
+                return Err(format!("不能解绑内置角色的权限 {access}"));
+            }
+            PlanItem::UnbindUser { role, user } if role == BUILTIN_NAME => {
+                return Err(format!("不能解绑内置角色的用户 {user}"));
+            }
+            PlanItem::BindAccess { role, access } if access == BUILTIN_NAME => {
+                return Err(format!("不能把内置权限授予角色 {role}"));
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// 计划执行后权限或用户可能变化的角色, 删除的权限涉及现有配置中绑定它的角色
+pub fn touched_roles(plan: &[PlanItem], current: &RbacConfig) -> Vec<String> {
+    let mut names: Vec<String> = vec![];
+    for item in plan {
+        let list: Vec<String> = match item {
+            PlanItem::BindAccess { role, .. }
+            | PlanItem::UnbindAccess { role, .. }
+            | PlanItem::BindUser { role, .. }
+            | PlanItem::UnbindUser { role, .. }
+            | PlanItem::DeleteRole { name: role } => vec![role.clone()],
+            PlanItem::DeleteAccess { name } => current
+                .roles
+                .iter()
+                .filter(|role| role.access.contains(name))
+                .map(|role| role.name.clone())
+                .collect(),
+            _ => vec![],
+        };
+        for name in list {
+            if !names.contains(&name) {
+                names.push(name);
+            }
+        }
+    }
+    names
+}
+
+/// 返回 (需要新增的, 需要删除的), 保持原有顺序
+fn diff_names(target: &[String], current: &[String]) -> (Vec<String>, Vec<String>) {
+    let add = target
+        .iter()
+        .filter(|name| !current.contains(name))
+        .cloned()
+        .collect();
+    let sub = current
+        .iter()
+        .filter(|name| !target.contains(name))
+        .cloned()
+        .collect();
+    (add, sub)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        build_plan, check_config, check_plan, touched_roles, PlanItem, RbacConfig, RoleConfig,
+    };
+    use crate::util::structs::DataScope;
+
+    fn role(name: &str, access: &[&str], users: Option<&[&str]>) -> RoleConfig {
+        RoleConfig {
+            name: name.to_string(),
+            data_scope: 1,
+            access: access.iter().map(|val| val.to_string()).collect(),
+            users: users.map(|list| list.iter().map(|val| val.to_string()).collect()),
+        }
+    }
+
+    #[test]
+    fn test_check_config() {
+        let mut config = RbacConfig {
+            access: vec!["A".to_string(), "B".to_string()],
+            roles: vec![role("r1", &["A"], None)],
+        };
+        assert!(check_config(&config).is_ok());
+        config.roles.push(role("r2", &["C"], None));
+        assert!(check_config(&config).is_err());
+        config.roles[1] = role("r1", &["B"], None);
+        assert!(check_config(&config).is_err());
+        config.roles[1] = role("r2", &["B"], None);
+        config.roles[1].data_scope = 5;
+        assert!(check_config(&config).is_err());
+    }
+
+    #[test]
+    fn test_default_data_scope() {
+        let target: RbacConfig = serde_json::from_str(
+            r#"{"access": ["A"], "roles": [{"name": "r1", "access": ["A"]}]}"#,
+        )
+        .unwrap();
+        assert!(check_config(&target).is_ok());
+        let plan = build_plan(&target, &RbacConfig::default(), false);
+        assert_eq!(
+            plan[1],
+            PlanItem::CreateRole {
+                name: "r1".to_string(),
+                data_scope: DataScope::TENANT as i8
+            }
+        );
+    }
+
+    #[test]
+    fn test_build_plan() {
+        let current = RbacConfig {
+            access: vec!["ADMIN".to_string(), "A".to_string(), "B".to_string()],
+            roles: vec![
+                role("ADMIN", &["ADMIN"], Some(&["root"])),
+                role("r1", &["A", "B"], Some(&["u1"])),
+                role("r2", &["B"], None),
+            ],
+        };
+        let mut r1 = role("r1", &["A", "C"], None);
+        r1.data_scope = 2;
+        let target = RbacConfig {
+            access: vec!["A".to_string(), "C".to_string()],
+            roles: vec![r1, role("r3", &["C"], Some(&["u2"]))],
+        };
+
+        let plan = build_plan(&target, &current, false);
+        assert_eq!(
+            plan,
+            vec![
+                PlanItem::CreateAccess {
+                    name: "C".to_string()
+                },
+                PlanItem::UpdateRole {
+                    name: "r1".to_string(),
+                    data_scope: 2
+                },
+                PlanItem::BindAccess {
+                    role: "r1".to_string(),
+                    access: "C".to_string()
+                },
+                PlanItem::UnbindAccess {
+                    role: "r1".to_string(),
+                    access: "B".to_string()
+                },
+                PlanItem::CreateRole {
+                    name: "r3".to_string(),
+                    data_scope: 1
+                },
+                PlanItem::BindAccess {
+                    role: "r3".to_string(),
+                    access: "C".to_string()
+                },
+                PlanItem::BindUser {
+                    role: "r3".to_string(),
+                    user: "u2".to_string()
+                },
+            ]
+        );
+
+        assert_eq!(touched_roles(&plan, &current), vec!["r1", "r3"]);
+
+        // 清理时保留 ADMIN
+        let plan = build_plan(&target, &current, true);
+        assert_eq!(touched_roles(&plan, &current), vec!["r1", "r3", "r2"]);
+        assert_eq!(
+            plan[plan.len() - 2..],
+            [
+                PlanItem::DeleteRole {
+                    name: "r2".to_string()
+                },
+                PlanItem::DeleteAccess {
+                    name: "B".to_string()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_check_plan() {
+        let current = RbacConfig {
+            access: vec!["ADMIN".to_string(), "A".to_string()],
+            roles: vec![role("ADMIN", &["ADMIN", "A"], Some(&["root", "u1"]))],
+        };
+
+        // 目标配置中去掉 ADMIN 的权限或用户
+        let target = RbacConfig {
+            access: current.access.clone(),
+            roles: vec![role("ADMIN", &["ADMIN"], None)],
+        };
+        assert!(check_plan(&build_plan(&target, &current, false)).is_err());
+        let target = RbacConfig {
+            access: current.access.clone(),
+            roles: vec![role("ADMIN", &["ADMIN", "A"], Some(&["root"]))],
+        };
+        assert!(check_plan(&build_plan(&target, &current, false)).is_err());
+
+        // 把 ADMIN 权限授予其他角色
+        let target = RbacConfig {
+            access: current.access.clone(),
+            roles: vec![role("r1", &["ADMIN"], None)],
+        };
+        assert!(check_plan(&build_plan(&target, &current, false)).is_err());
+
+        // 给 ADMIN 新增权限和用户不受限制
+        let target = RbacConfig {
+            access: current.access.clone(),
+            roles: vec![
+                role("ADMIN", &["ADMIN", "A"], Some(&["root", "u1", "u2"])),
+                role("r1", &["A"], None),
+            ],
+        };
+        assert_eq!(check_plan(&build_plan(&target, &current, false)), Ok(()));
+    }
+}
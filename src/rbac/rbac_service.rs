@@ -0,0 +1,500 @@
+use std::collections::HashMap;
+
+use super::{
+    dump_config, parse_config,
+    rbac_plan::{
+        build_plan, check_config, check_plan, touched_roles, PlanItem, RbacConfig, RoleConfig,
+    },
+    RbacExportData, RbacImportData, RbacImportRes, RbacState,
+};
+use crate::{
+    access::AccessMapItem,
+    constraint::check_role_change,
+    entity::{
+        access_entity::AccessEntity, role_access_entity::RoleAccessEntity, role_entity::RoleEntity,
+        user_entity::UserEntity, user_role_entity::UserRoleEntity,
+    },
+    operation::spawn_operation,
+    response::{MyError, ResponseBody},
+    role::{check_role_deletable, role_delete_unbind_tx},
+    user::{
+        user_role_service::{get_role_users, get_user_role_ids, refresh_user_roles},
+        OptionData,
+    },
+    util::{
+        common::{get_jwt_from_req, get_tenant_id, get_transaction_tx, RedisKeys},
+        data_scope::check_scope_grant,
+        outbox::add_events,
+        structs::{OutboxEvent, Status},
+        sync_opt::{self, DelOptData, SyncOptData},
+    },
+    RB,
+};
+use actix_web::{post, web, HttpRequest, Responder};
+use rbatis::executor::RBatisTxExecutorGuard;
+use rbs::to_value;
+use rs_service_util::{auth::gen_access_value, time::get_current_time_fmt};
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct RoleUserRow {
+    role_id: i32,
+    user_id: i32,
+    user_name: String,
+}
+
+#[utoipa::path(
+    tag = "rbac",
+    responses( (status = 200))
+)]
+#[post("/export")]
+pub async fn export_rbac(req_data: web::Json<RbacExportData>, req: HttpRequest) -> impl Responder {
+    let tenant_id = get_tenant_id(&req);
+    let mut config = load_state(tenant_id).await.config;
+    if !req_data.with_users.unwrap_or(false) {
+        config.roles.iter_mut().for_each(|role| role.users = None);
+    }
+    ResponseBody::default(Some(dump_config(req_data.format, &config)))
+}
+
+#[utoipa::path(
+    tag = "rbac",
+    responses( (status = 200))
+)]
+#[post("/import")]
+pub async fn import_rbac(
+    req_data: web::Json<RbacImportData>,
+    req: HttpRequest,
+) -> Result<impl Responder, MyError> {
+    let tenant_id = get_tenant_id(&req);
+    let target = parse_config(req_data.format, &req_data.content)?;
+    if let Err(error) = check_config(&target) {
+        log::error!(" {} {error}", MyError::RbacConfigError);
+        return Err(MyError::RbacConfigError);
+    }
+
+    let mut state = load_state(tenant_id).await;
+    let plan = build_plan(&target, &state.config, req_data.prune.unwrap_or(false));
+    if let Err(error) = check_plan(&plan) {
+        log::error!(" {} {error}", MyError::RbacConfigError);
+        return Err(MyError::RbacConfigError);
+    }
+    let login = get_jwt_from_req(&req);
+    for item in plan.iter() {
+        match item {
+            PlanItem::DeleteRole { name } => {
+                if let Some(role) = state.roles.get(name) {
+                    check_role_deletable(role)?;
+                }
+            }
+            PlanItem::CreateRole { data_scope, .. } | PlanItem::UpdateRole { data_scope, .. } => {
+                check_scope_grant(&login, *data_scope).await?;
+            }
+            _ => {}
+        }
+    }
+    resolve_plan_users(&plan, &mut state, tenant_id).await?;
+    check_plan_constraints(&plan, &state, tenant_id).await?;
+    if req_data.dry_run || plan.is_empty() {
+        return Ok(ResponseBody::default(Some(RbacImportRes {
+            dry_run: req_data.dry_run,
+            plan,
+            operation: None,
+        })));
+    }
+
+    // 变更前后拥有相关角色的用户都需要刷新
+    let touched = touched_roles(&plan, &state.config);
+    let mut users = get_touched_users(&touched, &state).await;
+
+    let tx = get_transaction_tx().await.expect("get tx err");
+    let mut apply_res = apply_plan(&tx, &plan, &mut state, tenant_id, login.id).await;
+    if apply_res.is_ok() {
+        let role_ids: Vec<i32> = touched
+            .iter()
+            .map(|name| state.role_id(name))
+            .filter(|id| *id > 0)
+            .collect();
+        let user_ids: Vec<i32> = users.iter().map(|val| val.id).collect();
+        apply_res = match add_events(&tx, OutboxEvent::RoleAccess, &role_ids, tenant_id).await {
+            Ok(_) => add_events(&tx, OutboxEvent::UserRoles, &user_ids, tenant_id).await,
+            err => err,
+        };
+    }
+    if let Err(rbs::Error::E(error)) = apply_res {
+        log::error!(" {} {error}", MyError::ImportRbacError);
+        tx.rollback().await.expect("rollback error");
+        return Err(MyError::ImportRbacError);
+    }
+    tx.commit().await.expect("commit error");
+
+    sync_plan_cache(&plan, &touched, &state, tenant_id).await;
+    for user in get_touched_users(&touched, &state).await {
+        if !users.iter().any(|val| val.id == user.id) {
+            users.push(user);
+        }
+    }
+    let op =
+        spawn_operation(
+            "import_rbac",
+            tenant_id,
+            users,
+            move |user: OptionData| async move {
+                refresh_user_roles(user.id, tenant_id).await.map(|_| ())
+            },
+        )
+        .await;
+
+    Ok(ResponseBody::default(Some(RbacImportRes {
+        dry_run: false,
+        plan,
+        operation: Some(op),
+    })))
+}
+
+/// 读取租户下有效的权限、角色及绑定关系, 角色的用户只包含直接绑定
+async fn load_state(tenant_id: i32) -> RbacState {
+    let ex = RB.acquire().await.expect("msg");
+    let access_list: Vec<AccessEntity> = ex
+        .query_decode(
+            "select * from access where status = 1 and tenant_id = ?",
+            vec![to_value!(tenant_id)],
+        )
+        .await
+        .expect("权限查询失败");
+    let role_list: Vec<RoleEntity> = ex
+        .query_decode(
+            "select * from role where status = 1 and tenant_id = ?",
+            vec![to_value!(tenant_id)],
+        )
+        .await
+        .expect("角色查询失败");
+    let role_access: Vec<RoleAccessEntity> =
+        RoleAccessEntity::select_by_column(&ex, "tenant_id", tenant_id)
+            .await
+            .expect("查询角色权限失败");
+    let role_users: Vec<RoleUserRow> = ex
+        .query_decode(
+            "select user_role.role_id, user.id as user_id, user.name as user_name from user_role \
+            inner join user on user.id = user_role.user_id and user.status = 1 \
+            where user_role.tenant_id = ?",
+            vec![to_value!(tenant_id)],
+        )
+        .await
+        .expect("查询用户角色失败");
+
+    let access_names: HashMap<i32, String> = access_list
+        .iter()
+        .map(|val| (val.id.unwrap_or_default(), val.name.clone()))
+        .collect();
+    let mut roles: HashMap<i32, RoleConfig> = role_list
+        .iter()
+        .map(|val| {
+            let role = RoleConfig {
+                name: val.name.clone(),
+                data_scope: val.data_scope,
+                access: vec![],
+                users: Some(vec![]),
+            };
+            (val.id.unwrap_or_default(), role)
+        })
+        .collect();
+    for val in role_access {
+        if let (Some(role), Some(name)) = (
+            roles.get_mut(&val.role_id),
+            access_names.get(&val.access_id),
+        ) {
+            role.access.push(name.clone());
+        }
+    }
+    let mut users: HashMap<String, i32> = HashMap::new();
+    for val in role_users {
+        if let Some(role) = roles.get_mut(&val.role_id) {
+            role.users
+                .get_or_insert_with(Vec::new)
+                .push(val.user_name.clone());
+            users.insert(val.user_name, val.user_id);
+        }
+    }
+
+    // 排序后导出的内容稳定, 便于在 git 中对比
+    let mut config = RbacConfig {
+        access: access_names.into_values().collect(),
+        roles: roles.into_values().collect(),
+    };
+    config.access.sort();
+    config.roles.sort_by(|a, b| a.name.cmp(&b.name));
+    for role in config.roles.iter_mut() {
+        role.access.sort();
+        role.access.dedup();
+        if let Some(users) = role.users.as_mut() {
+            users.sort();
+            users.dedup();
+        }
+    }
+
+    RbacState {
+        config,
+        access: access_list
+            .into_iter()
+            .map(|val| (val.name.clone(), val))
+            .collect(),
+        roles: role_list
+            .into_iter()
+            .map(|val| (val.name.clone(), val))
+            .collect(),
+        users,
+    }
+}
+
+/// 计划中新绑定的用户必须已存在
+async fn resolve_plan_users(
+    plan: &[PlanItem],
+    state: &mut RbacState,
+    tenant_id: i32,
+) -> Result<(), MyError> {
+    let ex = RB.acquire().await.expect("msg");
+    for item in plan {
+        if let PlanItem::BindUser { user, .. } = item {
+            if state.users.contains_key(user) {
+                continue;
+            }
+            let db_user = UserEntity::select_by_name(&ex, user, tenant_id)
+                .await
+                .expect("用户查询失败");
+            match db_user.and_then(|val| val.id) {
+                None => {
+                    log::error!("导入的配置中用户 {user} 不存在");
+                    return Err(MyError::UserNotExist);
+                }
+                Some(id) => {
+                    state.users.insert(user.clone(), id);
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// 新绑定的用户不能违反角色约束, 新建的角色不在任何约束中, 无需校验
+async fn check_plan_constraints(
+    plan: &[PlanItem],
+    state: &RbacState,
+    tenant_id: i32,
+) -> Result<(), MyError> {
+    let mut gain: HashMap<i32, Vec<i32>> = HashMap::new();
+    for item in plan {
+        if let PlanItem::BindUser { role, user } = item {
+            let role_id = state.role_id(role);
+            if role_id > 0 {
+                gain.entry(state.user_id(user)).or_default().push(role_id);
+            }
+        }
+    }
+    let mut next: HashMap<i32, Vec<i32>> = HashMap::new();
+    for (user_id, role_ids) in gain {
+        let mut roles = get_user_role_ids(user_id).await;
+        for id in role_ids {
+            if !roles.contains(&id) {
+                roles.push(id);
+            }
+        }
+        next.insert(user_id, roles);
+    }
+    check_role_change(next, tenant_id).await
+}
+
+async fn get_touched_users(touched: &[String], state: &RbacState) -> Vec<OptionData> {
+    let mut users: Vec<OptionData> = vec![];
+    for name in touched {
+        let role_id = state.role_id(name);
+        if role_id == 0 {
+            continue;
+        }
+        for user in get_role_users(role_id).await {
+            if !users.iter().any(|val| val.id == user.id) {
+                users.push(user);
+            }
+        }
+    }
+    users
+}
+
+/// 在同一事务中执行计划, 新建的权限和角色写回 state 供后续项使用
+async fn apply_plan(
+    tx: &RBatisTxExecutorGuard,
+    plan: &[PlanItem],
+    state: &mut RbacState,
+    tenant_id: i32,
+    create_by: i32,
+) -> Result<(), rbs::Error> {
+    for item in plan {
+        match item {
+            PlanItem::CreateAccess { name } => {
+                let mut access = AccessEntity {
+                    id: None,
+                    create_time: get_current_time_fmt(),
+                    update_time: get_current_time_fmt(),
+                    name: name.clone(),
+                    create_by,
+                    status: Status::ACTIVE as i8,
+                    value: 0,
+                    tenant_id,
+                };
+                let res = AccessEntity::insert(tx, &access).await?;
+                access.id = Some(res.last_insert_id.as_i64().unwrap_or(0) as i32);
+                access.value = gen_access_value(res.last_insert_id.as_u64().unwrap_or(0));
+                AccessEntity::update_by_column(tx, &access, "id").await?;
+                state.access.insert(name.clone(), access);
+            }
+            PlanItem::DeleteAccess { name } => {
+                if let Some(access) = state.access.get_mut(name) {
+                    access.status = Status::DEACTIVE as i8;
+                    access.update_time = get_current_time_fmt();
+                    AccessEntity::update_by_column(tx, access, "id").await?;
+                    RoleAccessEntity::delete_by_column(tx, "access_id", access.id).await?;
+                }
+            }
+            PlanItem::CreateRole { name, data_scope } => {
+                let mut role = RoleEntity {
+                    id: None,
+                    create_time: get_current_time_fmt(),
+                    update_time: get_current_time_fmt(),
+                    name: name.clone(),
+                    create_by,
+                    status: Status::ACTIVE as i8,
+                    tenant_id,
+                    data_scope: *data_scope,
+                };
+                let res = RoleEntity::insert(tx, &role).await?;
+                role.id = Some(res.last_insert_id.as_i64().unwrap_or(0) as i32);
+                state.roles.insert(name.clone(), role);
+            }
+            PlanItem::UpdateRole { name, data_scope } => {
+                if let Some(role) = state.roles.get_mut(name) {
+                    role.data_scope = *data_scope;
+                    role.update_time = get_current_time_fmt();
+                    RoleEntity::update_by_column(tx, role, "id").await?;
+                }
+            }
+            PlanItem::DeleteRole { name } => {
+                if let Some(role) = state.roles.get_mut(name) {
+                    role.status = Status::DEACTIVE as i8;
+                    role.update_time = get_current_time_fmt();
+                    RoleEntity::update_by_column(tx, role, "id").await?;
+                    role_delete_unbind_tx(tx, role.id.unwrap_or_default()).await?;
+                }
+            }
+            PlanItem::BindAccess { role, access } => {
+                let tab = RoleAccessEntity {
+                    id: None,
+                    role_id: state.role_id(role),
+                    access_id: state.access_id(access),
+                    tenant_id,
+                };
+                RoleAccessEntity::insert(tx, &tab).await?;
+            }
+            PlanItem::UnbindAccess { role, access } => {
+                RoleAccessEntity::delete_by_role_and_access(
+                    tx,
+                    state.role_id(role),
+                    state.access_id(access),
+                )
+                .await?;
+            }
+            PlanItem::BindUser { role, user } => {
+                let tab = UserRoleEntity {
+                    id: None,
+                    role_id: state.role_id(role),
+                    user_id: state.user_id(user),
+                    tenant_id,
+                };
+                UserRoleEntity::insert(tx, &tab).await?;
+            }
+            PlanItem::UnbindUser { role, user } => {
+                UserRoleEntity::delete_by_role_and_user(
+                    tx,
+                    state.role_id(role),
+                    state.user_id(user),
+                )
+                .await?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// 同步权限、角色缓存, 并重建相关角色的 `role_access_{id}`
+async fn sync_plan_cache(plan: &[PlanItem], touched: &[String], state: &RbacState, tenant_id: i32) {
+    for item in plan {
+        match item {
+            PlanItem::CreateAccess { name } => {
+                let access = &state.access[name];
+                let item = AccessMapItem {
+                    id: access.id.unwrap_or_default(),
+                    name: access.name.clone(),
+                    value: access.value,
+                };
+                sync_opt::sync(SyncOptData::default(
+                    RedisKeys::AccessMapIds,
+                    RedisKeys::AccessMap,
+                    tenant_id,
+                    item.id,
+                    item,
+                ))
+                .await;
+            }
+            PlanItem::DeleteAccess { name } => {
+                sync_opt::del(DelOptData::default(
+                    RedisKeys::AccessMapIds,
+                    RedisKeys::AccessMap,
+                    tenant_id,
+                    vec![state.access_id(name)],
+                ))
+                .await;
+            }
+            PlanItem::CreateRole { name, .. } | PlanItem::UpdateRole { name, .. } => {
+                let role_id = state.role_id(name);
+                sync_opt::sync(SyncOptData::default(
+                    RedisKeys::RoleIds,
+                    RedisKeys::RoleInfo,
+                    tenant_id,
+                    role_id,
+                    OptionData::default(name, role_id),
+                ))
+                .await;
+            }
+            PlanItem::DeleteRole { name } => {
+                sync_opt::del(DelOptData::default(
+                    RedisKeys::RoleIds,
+                    RedisKeys::RoleInfo,
+                    tenant_id,
+                    vec![state.role_id(name)],
+                ))
+                .await;
+            }
+            _ => {}
+        }
+    }
+
+    let role_ids: Vec<i32> = touched.iter().map(|name| state.role_id(name)).collect();
+    if role_ids.is_empty() {
+        return;
+    }
+    let ex = RB.acquire().await.expect("msg");
+    let list: Vec<RoleAccessEntity> = RoleAccessEntity::select_in_column(&ex, "role_id", &role_ids)
+        .await
+        .expect("查询角色权限失败");
+    let sets: Vec<(String, Vec<i32>)> = role_ids
+        .into_iter()
+        .map(|role_id| {
+            let access_ids: Vec<i32> = list
+                .iter()
+                .filter(|val| val.role_id == role_id)
+                .map(|val| val.access_id)
+                .collect();
+            (RedisKeys::RoleAccess.id_key(tenant_id, role_id), access_ids)
+        })
+        .collect();
+    sync_opt::replace_sets(sets).await;
+}
@@ -63,7 +63,6 @@ pub enum MyError {
     #[display("Obs AKSK cache失败")]
     CacheObsAkSkError,
 
-
     #[display("未登录")]
     NotLogin,
 
@@ -100,6 +99,12 @@ pub enum MyError {
     #[display("创建角色失败")]
     CreateRoleError,
 
+    #[display("角色名称已存在")]
+    RoleNameExist,
+
+    #[display("角色仍绑定了用户、用户组或部门")]
+    RoleIsBound,
+
     #[display("创建权限失败")]
     CreateAccessError,
 
@@ -117,6 +122,108 @@ pub enum MyError {
 
     #[display("更新用户失败")]
     UpdateUserError,
+
+    #[display("策略不存在")]
+    PolicyNotExist,
+
+    #[display("创建策略失败")]
+    CreatePolicyError,
+
+    #[display("更新策略失败")]
+    UpdatePolicyError,
+
+    #[display("部门不存在")]
+    OrgNotExist,
+
+    #[display("创建部门失败")]
+    CreateOrgError,
+
+    #[display("更新部门失败")]
+    UpdateOrgError,
+
+    #[display("不能移动到自身或子部门下")]
+    OrgMoveError,
+
+    #[display("部门下存在子部门")]
+    OrgHasChildren,
+
+    #[display("绑定部门成员失败")]
+    BindOrgUserError,
+
+    #[display("绑定部门角色失败")]
+    BindOrgRoleError,
+
+    #[display("用户组不存在")]
+    GroupNotExist,
+
+    #[display("创建用户组失败")]
+    CreateGroupError,
+
+    #[display("更新用户组失败")]
+    UpdateGroupError,
+
+    #[display("绑定用户组成员失败")]
+    BindGroupUserError,
+
+    #[display("绑定用户组角色失败")]
+    BindGroupRoleError,
+
+    #[display("角色约束不存在")]
+    ConstraintNotExist,
+
+    #[display("角色约束参数错误")]
+    ConstraintParamError,
+
+    #[display("创建角色约束失败")]
+    CreateConstraintError,
+
+    #[display("更新角色约束失败")]
+    UpdateConstraintError,
+
+    #[display("违反角色互斥或数量限制")]
+    RoleConstraintViolated,
+
+    #[display("后台任务不存在")]
+    OperationNotExist,
+
+    #[display("权限配置格式错误")]
+    RbacConfigError,
+
+    #[display("导入权限配置失败")]
+    ImportRbacError,
+
+    #[display("菜单不存在")]
+    MenuNotExist,
+
+    #[display("菜单参数错误")]
+    MenuParamError,
+
+    #[display("创建菜单失败")]
+    CreateMenuError,
+
+    #[display("更新菜单失败")]
+    UpdateMenuError,
+
+    #[display("菜单下还有子菜单")]
+    MenuHasChildren,
+
+    #[display("无权设置管理员类型")]
+    UserTypeForbidden,
+
+    #[display("无权设置全部数据范围")]
+    DataScopeForbidden,
+
+    #[display("内置权限不能删除")]
+    BuiltinAccessDelete,
+
+    #[display("内置角色不能删除")]
+    BuiltinRoleDelete,
+
+    #[display("无权绑定管理员权限或角色")]
+    AdmBindForbidden,
+
+    #[display("查询角色权限失败")]
+    QueryRoleAccessError,
 }
 
 impl error::ResponseError for MyError {
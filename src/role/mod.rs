@@ -1,33 +1,67 @@
-use crate::{entity::role_entity::RoleEntity, util::structs::CreateByData, RB};
+use crate::{
+    entity::role_entity::RoleEntity,
+    rbac::rbac_plan::BUILTIN_NAME,
+    response::MyError,
+    util::{access_guard::RouteAccess, common::DEFAULT_TENANT_ID, structs::CreateByData},
+    RB,
+};
+use rbatis::executor::RBatisTxExecutorGuard;
+use rbs::to_value;
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 use utoipa_actix_web::service_config::ServiceConfig;
 
-mod role_access_service;
+pub mod role_access_service;
 mod role_service;
 
 pub fn configure() -> impl FnOnce(&mut ServiceConfig) {
     |config: &mut ServiceConfig| {
         config.service(role_service::create_role);
+        config.service(role_service::clone_role);
         config.service(role_service::get_role_list);
         config.service(role_service::update_role_by_id);
         config.service(role_service::bind_access);
+        config.service(role_service::bind_access_dry_run);
         config.service(role_service::get_role_option);
 
         config.service(role_service::get_role_binds);
         config.service(role_service::delete_role_by_id);
     }
 }
+
+/// 接口所需权限, 由 `access_guard` 中间件校验
+pub const ROUTE_ACCESS: &[RouteAccess] = &[
+    RouteAccess::new("POST", "/api/role/create_role", "ROLE_CREATE"),
+    RouteAccess::new("POST", "/api/role/clone_role", "ROLE_CREATE"),
+    RouteAccess::new("POST", "/api/role/get_role_list", "ROLE_LIST"),
+    RouteAccess::new("GET", "/api/role/get_role_option", "ROLE_LIST"),
+    RouteAccess::new("GET", "/api/role/role_binds/{id}", "ROLE_LIST"),
+    RouteAccess::new("POST", "/api/role/update_role", "ROLE_UPDATE"),
+    RouteAccess::new("DELETE", "/api/role/{id}", "ROLE_DELETE"),
+    RouteAccess::new("POST", "/api/role/bind_access", "ROLE_BIND_ACCESS"),
+    RouteAccess::new("POST", "/api/role/bind_access_dry_run", "ROLE_BIND_ACCESS"),
+];
+
 #[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
 pub struct CreateRoleData {
     pub name: String,
     pub create_by: i32,
+    pub data_scope: Option<i8>,
+}
+
+/// 复制角色及其权限, 新角色沿用原角色的数据范围
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct CloneRoleData {
+    pub role_id: i32,
+    pub name: String,
+    pub create_by: i32,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
 pub struct RoleUpdateData {
     pub id: i32,
     pub name: Option<String>,
+    pub data_scope: Option<i8>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
@@ -57,13 +91,140 @@ pub struct RoleListListData {
     pub name: String,
     pub create_by: Option<CreateByData>, // 创建的用户id
     pub status: i8,
+    pub data_scope: i8,
 }
 
-pub async fn check_role_by_id(id: i32) -> Option<RoleEntity> {
+pub async fn check_role_by_id(id: i32, tenant_id: i32) -> Option<RoleEntity> {
     let ex_db = RB.acquire().await.expect("get db ex error");
-    let db_role = RoleEntity::select_by_id(&ex_db, id.into())
+    let db_role = RoleEntity::select_by_id(&ex_db, id, tenant_id)
         .await
         .expect("角色查询失败");
 
     db_role
 }
+
+/// 删除仍有绑定的角色时是否自动解绑, `ROLE_DELETE_MODE=refuse` 时拒绝删除
+pub fn role_delete_unbind() -> bool {
+    is_unbind_mode(&std::env::var("ROLE_DELETE_MODE").unwrap_or_default())
+}
+
+fn is_unbind_mode(mode: &str) -> bool {
+    !mode.eq("refuse")
+}
+
+/// 内置的 ADMIN 角色决定管理员的全部权限, 不能删除
+pub fn check_role_deletable(role: &RoleEntity) -> Result<(), MyError> {
+    if role.name == BUILTIN_NAME {
+        return Err(MyError::BuiltinRoleDelete);
+    }
+    Ok(())
+}
+
+/// refuse 模式下角色仍有绑定时拒绝删除
+pub fn check_role_delete_mode(unbind: bool, bound: bool) -> Result<(), MyError> {
+    if !unbind && bound {
+        return Err(MyError::RoleIsBound);
+    }
+    Ok(())
+}
+
+/// 角色的绑定关系: 直接绑定的用户、用户组、部门
+///
+/// 删除角色时解除这些绑定, `check_role_bound` 也按这些表判断
+pub const ROLE_BIND_TABLES: &[&str] = &["user_role", "group_role", "org_role"];
+
+fn role_bind_sql(table: &str, action: &str) -> String {
+    format!("{action} from {table} where role_id=?")
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct RoleIdRes {
+    role_id: i32,
+}
+
+/// 角色是否仍直接绑定了用户, 或绑定在用户组、部门上
+pub async fn check_role_bound(id: i32) -> bool {
+    let ex_db = RB.acquire().await.expect("get db ex error");
+    for table in ROLE_BIND_TABLES {
+        let bind: Option<RoleIdRes> = ex_db
+            .query_decode(
+                &format!("{} limit 1", role_bind_sql(table, "select role_id")),
+                vec![to_value!(id)],
+            )
+            .await
+            .expect("查询角色绑定失败");
+        if bind.is_some() {
+            return true;
+        }
+    }
+    false
+}
+
+/// 在事务中解除角色的所有绑定
+pub async fn role_delete_unbind_tx(
+    tx: &RBatisTxExecutorGuard,
+    role_id: i32,
+) -> Result<(), rbs::Error> {
+    for table in ROLE_BIND_TABLES {
+        tx.query_decode::<Option<()>>(&role_bind_sql(table, "delete"), vec![to_value!(role_id)])
+            .await?;
+    }
+    Ok(())
+}
+
+/// 获取 ADMIN 角色 id, 只存在于默认租户
+pub async fn get_adm_role_id() -> Option<i32> {
+    let ex = RB.acquire().await.expect("get ex error");
+    let adm_role = RoleEntity::select_by_name(&ex, BUILTIN_NAME, DEFAULT_TENANT_ID)
+        .await
+        .expect("角色查询失败");
+    adm_role.and_then(|val| val.id)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        check_role_deletable, check_role_delete_mode, is_unbind_mode, role_bind_sql,
+        ROLE_BIND_TABLES,
+    };
+    use crate::{entity::role_entity::RoleEntity, response::MyError};
+
+    #[test]
+    fn test_check_role_deletable() {
+        let mut role = RoleEntity::default_adm(1);
+        assert!(matches!(
+            check_role_deletable(&role),
+            Err(MyError::BuiltinRoleDelete)
+        ));
+        role.name = "运营".to_string();
+        assert!(check_role_deletable(&role).is_ok());
+    }
+
+    #[test]
+    fn test_role_delete_mode() {
+        assert!(is_unbind_mode(""));
+        assert!(is_unbind_mode("unbind"));
+        assert!(!is_unbind_mode("refuse"));
+
+        assert!(check_role_delete_mode(true, true).is_ok());
+        assert!(check_role_delete_mode(false, false).is_ok());
+        assert!(matches!(
+            check_role_delete_mode(false, true),
+            Err(MyError::RoleIsBound)
+        ));
+    }
+
+    #[test]
+    fn test_role_unbind_tables() {
+        // 用户、用户组、部门的绑定都要解除
+        assert_eq!(ROLE_BIND_TABLES, ["user_role", "group_role", "org_role"]);
+        assert_eq!(
+            role_bind_sql("group_role", "delete"),
+            "delete from group_role where role_id=?"
+        );
+        assert_eq!(
+            role_bind_sql("org_role", "select role_id"),
+            "select role_id from org_role where role_id=?"
+        );
+    }
+}
@@ -1,62 +1,76 @@
-use redis::AsyncCommands;
-use rs_service_util::redis_conn;
-
-use crate::{entity::role_access_entity::RoleAccessEntity, util::common::RedisKeys};
+use crate::{
+    cache::{smembers_ids, srem_ids},
+    entity::role_access_entity::RoleAccessEntity,
+    util::{
+        common::{diff_ids, RedisKeys},
+        sync_opt::replace_set,
+    },
+    RB,
+};
 
 /// role_ids    cache_id
 ///
 /// [1,2]       [1,2,3,4]    remove 3,4
 ///
 /// [1,2 ,5]    [1,2,3,4]    remove 3,4 add 5
-pub async fn check_role_access_bind(role_id: &i32, access_ids: &Vec<i32>) -> (Vec<i32>, Vec<i32>) {
-    let mut conn = redis_conn!().await;
-    let key = format!("{}_{}", RedisKeys::RoleAccess.to_string(), role_id);
-    let cache_ids: Vec<i32> = conn.smembers(key).await.expect("msg");
+pub async fn check_role_access_bind(
+    role_id: &i32,
+    access_ids: &Vec<i32>,
+    tenant_id: i32,
+) -> (Vec<i32>, Vec<i32>) {
+    let key = RedisKeys::RoleAccess.id_key(tenant_id, *role_id);
+    let cache_ids: Vec<i32> = smembers_ids(&key).await;
     log::info!("cache_role_access bind access ids {cache_ids:?}");
-    if cache_ids.is_empty() {
-        return (access_ids.clone(), vec![]);
-    }
-    // 查看交集
-    let mut add_ids: Vec<i32> = vec![];
-    let mut sub_ids: Vec<i32> = vec![];
-
-    access_ids.iter().for_each(|id| {
-        let is_contain = cache_ids.iter().find(|c_id| **c_id == *id);
-        if is_contain.is_none() {
-            add_ids.push(*id);
-        }
-    });
-
-    cache_ids.iter().for_each(|id| {
-        let is_contain = access_ids.iter().find(|c_id| **c_id == *id);
-        if is_contain.is_none() {
-            sub_ids.push(*id);
-        }
-    });
-
-    (add_ids, sub_ids)
+    diff_ids(access_ids, &cache_ids)
 }
 
-pub async fn unbind_access_from_cache(role_id: &i32, role_ids: &Vec<i32>) {
-    let mut conn = redis_conn!().await;
-    let key = format!("{}_{}", RedisKeys::RoleAccess.to_string(), role_id);
-
-    let _: () = conn.srem(key, role_ids.to_vec()).await.expect("msg");
+pub async fn unbind_access_from_cache(role_id: &i32, role_ids: &Vec<i32>, tenant_id: i32) {
+    srem_ids(RedisKeys::RoleAccess.id_key(tenant_id, *role_id), role_ids).await;
 }
 
-pub async fn bind_role_access(role_id: &i32, access_ids: &Vec<i32>) -> Vec<RoleAccessEntity> {
-    let mut conn = redis_conn!().await;
-    let key = format!("{}_{}", RedisKeys::RoleAccess.to_string(), role_id);
+/// 待写入的角色权限关系, 缓存在事务提交后由 `refresh_role_access` 重建
+pub fn role_access_tabs(
+    role_id: &i32,
+    access_ids: &[i32],
+    tenant_id: i32,
+) -> Vec<RoleAccessEntity> {
     let mut tabs: Vec<RoleAccessEntity> = vec![];
     for id in access_ids {
-        let _: () = conn.sadd(key.clone(), id).await.expect("msg");
-
         tabs.push(RoleAccessEntity {
             id: None,
             access_id: *id,
             role_id: *role_id,
+            tenant_id,
         });
     }
 
     tabs
 }
+
+/// 按数据库重建角色的权限缓存
+pub async fn refresh_role_access(role_id: i32, tenant_id: i32) {
+    let ex = RB.acquire().await.expect("msg");
+    let list: Vec<RoleAccessEntity> = RoleAccessEntity::select_by_column(&ex, "role_id", role_id)
+        .await
+        .expect("查询角色权限失败");
+    let access_ids: Vec<i32> = list.into_iter().map(|val| val.access_id).collect();
+    replace_set(RedisKeys::RoleAccess.id_key(tenant_id, role_id), access_ids).await;
+}
+
+#[cfg(test)]
+mod test {
+    use super::role_access_tabs;
+
+    #[test]
+    fn test_role_access_tabs() {
+        // 复制角色时按源角色的权限为新角色生成绑定
+        let tabs = role_access_tabs(&9, &[3, 5], 2);
+        let pairs: Vec<(i32, i32, i32)> = tabs
+            .iter()
+            .map(|val| (val.role_id, val.access_id, val.tenant_id))
+            .collect();
+        assert_eq!(pairs, vec![(9, 3, 2), (9, 5, 2)]);
+        assert!(tabs.iter().all(|val| val.id.is_none()));
+        assert!(role_access_tabs(&9, &[], 2).is_empty());
+    }
+}
@@ -1,28 +1,40 @@
-use actix_web::{delete, get, post, web, Responder};
+use actix_web::{delete, get, post, web, HttpRequest, Responder};
 use rbs::to_value;
-use redis::AsyncCommands;
 use rs_service_util::{
-    redis_conn,
     sql_tool::{SqlTool, SqlToolPageData},
     time::get_current_time_fmt,
 };
 
-use super::{BindAccessData, CreateRoleData, RoleListQueryData, RoleUpdateData};
+use super::{BindAccessData, CloneRoleData, CreateRoleData, RoleListQueryData, RoleUpdateData};
 use crate::{
-    access::check_access_by_ids,
+    access::{check_access_by_ids, get_adm_access_id},
+    cache::{sadd_ids, smembers_ids},
     entity::{
         access_entity::AccessEntity, role_access_entity::RoleAccessEntity, role_entity::RoleEntity,
     },
+    operation::spawn_operation,
     response::{MyError, ResponseBody},
     role::{
-        check_role_by_id,
-        role_access_service::{bind_role_access, check_role_access_bind, unbind_access_from_cache},
-        CreateByData, RoleListListData,
+        check_role_bound, check_role_by_id, check_role_deletable, check_role_delete_mode,
+        role_access_service::{check_role_access_bind, refresh_role_access, role_access_tabs},
+        role_delete_unbind, role_delete_unbind_tx, CreateByData, RoleListListData,
+    },
+    user::{
+        auth_service::check_adm_bind,
+        bind_simulate::{
+            collect_access, gen_user_change, get_role_access_map, BindSimRes, SimUserChange,
+        },
+        check_user_by_user_id,
+        user_role_service::{
+            get_role_users, get_user_role_ids, refresh_user_roles, sync_user_auth,
+        },
+        OptionData,
     },
-    user::{check_user_by_user_id, user_role_service::sync_user_auth, OptionData},
     util::{
-        common::{get_transaction_tx, rds_str_to_list, RedisKeys},
-        structs::Status,
+        common::{get_jwt_from_req, get_tenant_id, get_transaction_tx, rds_str_to_list, RedisKeys},
+        data_scope::{append_scope_filter, check_scope_grant, get_user_data_scope, scope_table},
+        outbox::add_events,
+        structs::{DataScope, OutboxEvent, Status},
         sync_opt::{self, DelOptData, SyncOptData},
     },
     RB,
@@ -33,10 +45,19 @@ use crate::{
   responses( (status = 200) )
 )]
 #[post("/create_role")]
-async fn create_role(req_data: web::Json<CreateRoleData>) -> Result<impl Responder, MyError> {
-    if check_user_by_user_id(req_data.create_by).await.is_none() {
+async fn create_role(
+    req_data: web::Json<CreateRoleData>,
+    req: HttpRequest,
+) -> Result<impl Responder, MyError> {
+    let tenant_id = get_tenant_id(&req);
+    if check_user_by_user_id(req_data.create_by, tenant_id)
+        .await
+        .is_none()
+    {
         return Err(MyError::RoleNotExist);
     }
+    let data_scope = DataScope::from(req_data.data_scope.unwrap_or(DataScope::TENANT as i8)) as i8;
+    check_scope_grant(&get_jwt_from_req(&req), data_scope).await?;
 
     let new_role = RoleEntity {
         id: None,
@@ -45,6 +66,8 @@ async fn create_role(req_data: web::Json<CreateRoleData>) -> Result<impl Respond
         name: req_data.name.clone(),
         create_by: req_data.create_by,
         status: Status::ACTIVE as i8,
+        tenant_id,
+        data_scope,
     };
 
     let tx = get_transaction_tx().await.unwrap();
@@ -68,6 +91,7 @@ async fn create_role(req_data: web::Json<CreateRoleData>) -> Result<impl Respond
     sync_opt::sync(SyncOptData::default(
         RedisKeys::RoleIds,
         RedisKeys::RoleInfo,
+        tenant_id,
         opt.id,
         opt,
     ))
@@ -76,14 +100,113 @@ async fn create_role(req_data: web::Json<CreateRoleData>) -> Result<impl Respond
     Ok(ResponseBody::success("角色创建成功"))
 }
 
+#[utoipa::path(
+  tag = "role",
+  responses( (status = 200) )
+)]
+#[post("/clone_role")]
+pub async fn clone_role(
+    req_data: web::Json<CloneRoleData>,
+    req: HttpRequest,
+) -> Result<impl Responder, MyError> {
+    let tenant_id = get_tenant_id(&req);
+    if check_user_by_user_id(req_data.create_by, tenant_id)
+        .await
+        .is_none()
+    {
+        return Err(MyError::UserNotExist);
+    }
+    let source = check_role_by_id(req_data.role_id, tenant_id)
+        .await
+        .ok_or(MyError::RoleNotExist)?;
+    check_scope_grant(&get_jwt_from_req(&req), source.data_scope).await?;
+
+    let ex = RB.acquire().await.expect("msg");
+    let same_name = RoleEntity::select_by_name(&ex, &req_data.name, tenant_id)
+        .await
+        .expect("角色查询失败");
+    if same_name.is_some() {
+        return Err(MyError::RoleNameExist);
+    }
+    let source_access: Vec<RoleAccessEntity> =
+        RoleAccessEntity::select_by_column(&ex, "role_id", req_data.role_id)
+            .await
+            .expect("查询角色权限失败");
+    drop(ex);
+    let access_ids: Vec<i32> = source_access.into_iter().map(|val| val.access_id).collect();
+
+    let new_role = RoleEntity {
+        id: None,
+        create_time: get_current_time_fmt(),
+        update_time: get_current_time_fmt(),
+        name: req_data.name.clone(),
+        create_by: req_data.create_by,
+        status: Status::ACTIVE as i8,
+        tenant_id,
+        data_scope: source.data_scope,
+    };
+
+    // 角色和权限关系在同一事务中写入
+    let tx = get_transaction_tx().await.expect("get tx err");
+    let role_id = match RoleEntity::insert(&tx, &new_role).await {
+        Err(rbs::Error::E(error)) => {
+            log::error!(" {} {error}", MyError::CreateRoleError);
+            tx.rollback().await.expect("rollback error");
+            return Err(MyError::CreateRoleError);
+        }
+        Ok(res) => res.last_insert_id.as_i64().unwrap_or(0) as i32,
+    };
+    if !access_ids.is_empty() {
+        let add_tabs: Vec<RoleAccessEntity> = role_access_tabs(&role_id, &access_ids, tenant_id);
+        let mut add_res = add_events(&tx, OutboxEvent::RoleAccess, &[role_id], tenant_id).await;
+        if add_res.is_ok() {
+            add_res = RoleAccessEntity::insert_batch(&tx, &add_tabs, add_tabs.len() as u64)
+                .await
+                .map(|_| ());
+        }
+        if let Err(rbs::Error::E(error)) = add_res {
+            log::error!(" {} {error}", MyError::CreateRoleError);
+            tx.rollback().await.expect("rollback error");
+            return Err(MyError::CreateRoleError);
+        }
+    }
+    tx.commit().await.expect("commit error");
+
+    let opt = OptionData::default(&req_data.name, role_id);
+    sync_opt::sync(SyncOptData::default(
+        RedisKeys::RoleIds,
+        RedisKeys::RoleInfo,
+        tenant_id,
+        role_id,
+        opt.clone(),
+    ))
+    .await;
+    if !access_ids.is_empty() {
+        sadd_ids(
+            RedisKeys::RoleAccess.id_key(tenant_id, role_id),
+            &access_ids,
+        )
+        .await;
+    }
+
+    Ok(ResponseBody::default(Some(opt)))
+}
+
 #[utoipa::path(
     tag = "role",
     responses( (status = 200) )
   )]
 #[post("/get_role_list")]
-async fn get_role_list(req_data: web::Json<RoleListQueryData>) -> impl Responder {
+async fn get_role_list(req_data: web::Json<RoleListQueryData>, req: HttpRequest) -> impl Responder {
+    let tenant_id = get_tenant_id(&req);
+    let jwt_user = get_jwt_from_req(&req);
+    let scope = get_user_data_scope(&jwt_user).await;
     let ex_db: rbatis::executor::RBatisConnExecutor = RB.acquire().await.expect("msg");
-    let mut tool = SqlTool::init("select * from role", "order by create_time desc");
+    let table = scope_table("role", &scope, "create_by", &jwt_user);
+    let mut tool = SqlTool::init(
+        &format!("select * from {table}"),
+        "order by create_time desc",
+    );
 
     if let Some(name) = &req_data.name {
         tool.append_sql_filed("name", to_value!(name));
@@ -91,6 +214,7 @@ async fn get_role_list(req_data: web::Json<RoleListQueryData>) -> impl Responder
     if let Some(create_by) = req_data.create_by {
         tool.append_sql_filed("create_by", to_value!(create_by));
     }
+    append_scope_filter(&mut tool, &scope, "create_by", &jwt_user, tenant_id);
     tool.append_sql_filed("status", to_value!(1));
 
     let page_sql = tool.gen_page_sql(req_data.page_no, req_data.take);
@@ -115,13 +239,14 @@ async fn get_role_list(req_data: web::Json<RoleListQueryData>) -> impl Responder
             update_time: val.update_time,
             name: val.name,
             status: val.status,
+            data_scope: val.data_scope,
         };
         records.push(val);
     }
 
     let conf = SqlToolPageData {
         ex_db,
-        table: "role".to_string(),
+        table,
         records,
         page_no: req_data.page_no as u64,
         page_size: req_data.take as u64,
@@ -138,13 +263,19 @@ async fn get_role_list(req_data: web::Json<RoleListQueryData>) -> impl Responder
 #[post("/update_role")]
 pub async fn update_role_by_id(
     req_data: web::Json<RoleUpdateData>,
+    req: HttpRequest,
 ) -> Result<impl Responder, MyError> {
-    match check_role_by_id(req_data.id).await {
+    let tenant_id = get_tenant_id(&req);
+    match check_role_by_id(req_data.id, tenant_id).await {
         None => {
             return Err(MyError::RoleNotExist);
         }
         Some(mut role) => {
             role.name = req_data.name.clone().unwrap_or(role.name);
+            if let Some(data_scope) = req_data.data_scope {
+                check_scope_grant(&get_jwt_from_req(&req), data_scope).await?;
+                role.data_scope = DataScope::from(data_scope) as i8;
+            }
             role.update_time = get_current_time_fmt();
             let tx = get_transaction_tx().await.expect("get tx err");
             let update_res = RoleEntity::update_by_column(&tx, &role, "id").await;
@@ -164,6 +295,7 @@ pub async fn update_role_by_id(
             sync_opt::sync(SyncOptData::default(
                 RedisKeys::RoleIds,
                 RedisKeys::RoleInfo,
+                tenant_id,
                 item.id,
                 item,
             ))
@@ -179,35 +311,65 @@ pub async fn update_role_by_id(
     responses( (status = 200) )
   )]
 #[delete("/{id}")]
-pub async fn delete_role_by_id(id: web::Path<i32>) -> Result<impl Responder, MyError> {
+pub async fn delete_role_by_id(
+    id: web::Path<i32>,
+    req: HttpRequest,
+) -> Result<impl Responder, MyError> {
+    let tenant_id = get_tenant_id(&req);
     let id: i32 = id.into_inner();
-    match check_role_by_id(id).await {
-        None => {
-            return Err(MyError::RoleNotExist);
-        }
-        Some(mut role) => {
-            role.status = Status::DEACTIVE as i8;
-            role.update_time = get_current_time_fmt();
-            let tx = get_transaction_tx().await.expect("get tx err");
-            let update_res = RoleEntity::update_by_column(&tx, &role, "id").await;
-            tx.commit().await.expect("msg");
-
-            if let Err(rbs::Error::E(error)) = update_res {
-                log::error!("{}, {}", error, MyError::UpdateRoleError);
-                tx.rollback().await.expect("msg");
-                return Err(MyError::UpdateRoleError);
-            }
-        }
+    let mut role = check_role_by_id(id, tenant_id)
+        .await
+        .ok_or(MyError::RoleNotExist)?;
+
+    check_role_deletable(&role)?;
+    check_role_delete_mode(role_delete_unbind(), check_role_bound(id).await)?;
+    let users = get_role_users(id).await;
+
+    // 停用角色并解除用户、用户组、部门的绑定
+    role.status = Status::DEACTIVE as i8;
+    role.update_time = get_current_time_fmt();
+    let tx = get_transaction_tx().await.expect("get tx err");
+    let update_res = RoleEntity::update_by_column(&tx, &role, "id").await;
+    if let Err(rbs::Error::E(error)) = update_res {
+        log::error!("{}, {}", error, MyError::UpdateRoleError);
+        tx.rollback().await.expect("msg");
+        return Err(MyError::UpdateRoleError);
+    }
+    if let Err(rbs::Error::E(error)) = role_delete_unbind_tx(&tx, id).await {
+        log::error!("{}, {}", error, MyError::UpdateRoleError);
+        tx.rollback().await.expect("msg");
+        return Err(MyError::UpdateRoleError);
+    }
+    let user_ids: Vec<i32> = users.iter().map(|val| val.id).collect();
+    let event_res = add_events(&tx, OutboxEvent::UserRoles, &user_ids, tenant_id).await;
+    if let Err(rbs::Error::E(error)) = event_res {
+        log::error!("{}, {}", error, MyError::UpdateRoleError);
+        tx.rollback().await.expect("msg");
+        return Err(MyError::UpdateRoleError);
     }
+    tx.commit().await.expect("msg");
 
     sync_opt::del(DelOptData::default(
         RedisKeys::RoleIds,
         RedisKeys::RoleInfo,
+        tenant_id,
         vec![id],
     ))
     .await;
 
-    Ok(ResponseBody::success("角色删除成功"))
+    // 受影响的用户在后台重建角色缓存和权限值
+    let op =
+        spawn_operation(
+            "delete_role",
+            tenant_id,
+            users,
+            move |user: OptionData| async move {
+                refresh_user_roles(user.id, tenant_id).await.map(|_| ())
+            },
+        )
+        .await;
+
+    Ok(ResponseBody::default(Some(op)))
 }
 
 #[utoipa::path(
@@ -215,97 +377,154 @@ pub async fn delete_role_by_id(id: web::Path<i32>) -> Result<impl Responder, MyE
     responses( (status = 200) )
   )]
 #[post("/bind_access")]
-pub async fn bind_access(req_data: web::Json<BindAccessData>) -> Result<impl Responder, MyError> {
-    let db_role = check_role_by_id(req_data.role_id).await;
-    let db_access = check_access_by_ids(&req_data.access_ids).await;
+pub async fn bind_access(
+    req_data: web::Json<BindAccessData>,
+    req: HttpRequest,
+) -> Result<impl Responder, MyError> {
+    let tenant_id = get_tenant_id(&req);
+    let db_role = check_role_by_id(req_data.role_id, tenant_id).await;
+    let db_access = check_access_by_ids(&req_data.access_ids, tenant_id).await;
     if db_role.is_none() {
         return Err(MyError::RoleNotExist);
     }
     if db_access.is_none() {
         return Err(MyError::AccessNotExist);
     }
-    let (add_ids, sub_ids) = check_role_access_bind(&req_data.role_id, &req_data.access_ids).await;
+    let (add_ids, sub_ids) =
+        check_role_access_bind(&req_data.role_id, &req_data.access_ids, tenant_id).await;
+    check_adm_bind(
+        &get_jwt_from_req(&req),
+        get_adm_access_id().await,
+        &add_ids,
+        &sub_ids,
+    )
+    .await?;
 
     log::debug!("add_ids {add_ids:?}");
     log::debug!("sub_ids {sub_ids:?}");
 
-    if !sub_ids.is_empty() {
-        unbind_access_from_cache(&req_data.role_id, &sub_ids).await;
+    let clear_all = sub_ids.is_empty() && req_data.access_ids.is_empty();
+    let add_tabs: Vec<RoleAccessEntity> = role_access_tabs(&req_data.role_id, &add_ids, tenant_id);
+    log::debug!("add_tabs {add_tabs:#?}");
+
+    if clear_all || !sub_ids.is_empty() || !add_tabs.is_empty() {
+        // 删除、新增和变更事件在同一事务中提交
         let tx = RB.acquire_begin().await.expect("msg");
-        for id in sub_ids {
-            let sub_res: Result<Option<()>, rbs::Error> = tx
-                .query_decode(
+        let mut bind_res =
+            add_events(&tx, OutboxEvent::RoleAccess, &[req_data.role_id], tenant_id).await;
+        if bind_res.is_ok() && clear_all {
+            bind_res = RoleAccessEntity::delete_by_column(&tx, "role_id", req_data.role_id)
+                .await
+                .map(|_| ());
+        }
+        for id in sub_ids.iter() {
+            if bind_res.is_err() {
+                break;
+            }
+            bind_res = tx
+                .query_decode::<Option<()>>(
                     "delete from role_access where access_id=? and role_id = ?",
                     vec![to_value!(id), to_value!(req_data.role_id)],
                 )
-                .await;
-            if let Err(rbs::Error::E(error)) = sub_res {
-                log::error!("{}, {error}", MyError::DelRoleAccessError);
-                tx.rollback().await.expect("msg");
-                return Err(MyError::DelRoleAccessError);
-            }
-            tx.commit().await.expect("msg");
+                .await
+                .map(|_| ());
         }
-    } else {
-        if req_data.access_ids.is_empty() {
-            let tx = RB.acquire_begin().await.expect("msg");
-            let sub_res =
-                RoleAccessEntity::delete_by_column(&tx, "role_id", req_data.role_id).await;
-            if let Err(rbs::Error::E(error)) = sub_res {
-                log::error!("{}, {error}", MyError::DelRoleAccessError);
-                tx.rollback().await.expect("msg");
-                return Err(MyError::DelRoleAccessError);
-            }
-            tx.commit().await.expect("msg");
+        if bind_res.is_ok() && !add_tabs.is_empty() {
+            bind_res = RoleAccessEntity::insert_batch(&tx, &add_tabs, add_tabs.len() as u64)
+                .await
+                .map(|_| ());
         }
-    }
 
-    if !add_ids.is_empty() {
-        let add_tabs: Vec<RoleAccessEntity> = bind_role_access(&req_data.role_id, &add_ids).await;
-        log::debug!("add_tabs {add_tabs:#?}");
-        let tx = RB.acquire_begin().await.expect("msg");
-        let add_res = RoleAccessEntity::insert_batch(&tx, &add_tabs, add_tabs.len() as u64).await;
-
-        if let Err(rbs::Error::E(error)) = add_res {
+        if let Err(rbs::Error::E(error)) = bind_res {
             log::error!("{}, {error}", MyError::DelRoleAccessError);
             tx.rollback().await.expect("msg");
             return Err(MyError::DelRoleAccessError);
         }
         tx.commit().await.expect("msg");
+        // 提交后按数据库重建缓存, 回滚时缓存不受影响
+        refresh_role_access(req_data.role_id, tenant_id).await;
     }
-    let tx = RB.acquire().await.expect("msg");
-    let user_list :Vec<OptionData> = tx.query_decode("select user.name, user.id from user_role left join user on user.id = user_role.user_id  where role_id = ?;", vec![to_value!(req_data.role_id)]).await.expect("msg");
-    drop(tx);
+    // 包括通过用户组和部门继承该角色的用户
+    let user_list: Vec<OptionData> = get_role_users(req_data.role_id).await;
     for ele in user_list.into_iter() {
-        sync_user_auth(ele.name).await?;
+        sync_user_auth(ele.id, tenant_id).await?;
     }
 
     Ok(ResponseBody::success("绑定成功"))
 }
 
+#[utoipa::path(
+    tag = "role",
+    responses( (status = 200) )
+  )]
+#[post("/bind_access_dry_run")]
+pub async fn bind_access_dry_run(
+    req_data: web::Json<BindAccessData>,
+    req: HttpRequest,
+) -> Result<impl Responder, MyError> {
+    let tenant_id = get_tenant_id(&req);
+    if check_role_by_id(req_data.role_id, tenant_id)
+        .await
+        .is_none()
+    {
+        return Err(MyError::RoleNotExist);
+    }
+    if check_access_by_ids(&req_data.access_ids, tenant_id)
+        .await
+        .is_none()
+    {
+        return Err(MyError::AccessNotExist);
+    }
+    let (add_ids, sub_ids) =
+        check_role_access_bind(&req_data.role_id, &req_data.access_ids, tenant_id).await;
+
+    let mut users: Vec<SimUserChange> = vec![];
+    for user in get_role_users(req_data.role_id).await {
+        let role_ids = get_user_role_ids(user.id).await;
+        let mut map = get_role_access_map(&role_ids).await;
+        let current_access = collect_access(&role_ids, &map);
+
+        let role_access = map.entry(req_data.role_id).or_default();
+        role_access.retain(|id| !sub_ids.contains(id));
+        role_access.extend(add_ids.iter().copied());
+        let next_access = collect_access(&role_ids, &map);
+
+        if let Some(change) = gen_user_change(user, &current_access, &next_access).await {
+            users.push(change);
+        }
+    }
+
+    Ok(ResponseBody::default(Some(BindSimRes {
+        add_ids,
+        sub_ids,
+        users,
+    })))
+}
+
 #[utoipa::path(
     tag = "role",
     responses( (status = 200) )
   )]
 #[get("/role_binds/{id}")]
-pub async fn get_role_binds(parma: web::Path<i32>) -> Result<impl Responder, MyError> {
+pub async fn get_role_binds(
+    parma: web::Path<i32>,
+    req: HttpRequest,
+) -> Result<impl Responder, MyError> {
+    let tenant_id = get_tenant_id(&req);
     let id = parma.into_inner();
-    let db_role = check_role_by_id(id.clone()).await;
+    let db_role = check_role_by_id(id.clone(), tenant_id).await;
     if db_role.is_none() {
         return Err(MyError::UserNotExist);
     }
 
-    let mut conn = redis_conn!().await;
-    let key: String = format!("{}_{}", RedisKeys::RoleAccess.to_string(), id);
-    let cache_ids: Vec<i32> = conn.smembers(key).await.expect("msg");
+    let key: String = RedisKeys::RoleAccess.id_key(tenant_id, id);
+    let cache_ids: Vec<i32> = smembers_ids(&key).await;
 
     let ex = RB.acquire().await.expect("msg");
     let search_res: Vec<AccessEntity> = if cache_ids.is_empty() {
         let access :Vec<AccessEntity>=  ex.query_decode("select access.* from role_access left join access on role_access.access_id = access.id where role_id=? and access.status = 1;", vec![to_value!(id)]).await.expect("msg");
-        let key = format!("{}_{}", RedisKeys::RoleAccess.to_string(), id);
-        for ele in access.iter() {
-            let _: () = conn.sadd(key.clone(), ele.id.unwrap()).await.expect("msg");
-        }
+        let access_ids: Vec<i32> = access.iter().filter_map(|ele| ele.id).collect();
+        sync_opt::replace_set(RedisKeys::RoleAccess.id_key(tenant_id, id), access_ids).await;
         access
     } else {
         let roles = AccessEntity::select_in_column(&ex, "id", &cache_ids)
@@ -321,34 +540,36 @@ pub async fn get_role_binds(parma: web::Path<i32>) -> Result<impl Responder, MyE
     responses( (status = 200) )
   )]
 #[get("/get_role_option")]
-pub async fn get_role_option() -> impl Responder {
-    let mut conn = redis_conn!().await;
-    let ids: Vec<i32> = conn
-        .smembers(RedisKeys::RoleIds.to_string())
+pub async fn get_role_option(req: HttpRequest) -> impl Responder {
+    let tenant_id = get_tenant_id(&req);
+    let ids: Vec<i32> = smembers_ids(&RedisKeys::RoleIds.key(tenant_id)).await;
+    let cache_res: Option<Vec<OptionData>> =
+        rds_str_to_list(ids, RedisKeys::RoleInfo, tenant_id).await;
+    if let Some(res) = cache_res {
+        return ResponseBody::default(Some(res));
+    }
+
+    // 缓存为空或无法解析时从数据库重建
+    let ex_db = RB.acquire().await.expect("get ex err");
+    let opt: Vec<OptionData> = ex_db
+        .query_decode(
+            "select id, name from role where status=1 and tenant_id = ?",
+            vec![to_value!(tenant_id)],
+        )
         .await
-        .expect("msg");
-    if !ids.is_empty() {
-        let res: Vec<OptionData> = rds_str_to_list(ids, RedisKeys::RoleInfo, |val| {
-            let user_data: OptionData = serde_json::from_str(&val).expect("msg");
-            user_data
-        })
-        .await;
-        ResponseBody::default(Some(res))
-    } else {
-        let ex_db = RB.acquire().await.expect("get ex err");
-        let opt: Vec<OptionData> = ex_db
-            .query_decode("select id, name from role where status=1", vec![])
-            .await
-            .expect("select db err");
-        for ele in opt.iter() {
-            sync_opt::sync(SyncOptData::default(
+        .expect("select db err");
+    let list = opt
+        .iter()
+        .map(|ele| {
+            SyncOptData::default(
                 RedisKeys::RoleIds,
                 RedisKeys::RoleInfo,
+                tenant_id,
                 ele.id,
                 ele.clone(),
-            ))
-            .await;
-        }
-        ResponseBody::default(Some(opt))
-    }
+            )
+        })
+        .collect();
+    sync_opt::sync_list(list).await;
+    ResponseBody::default(Some(opt))
 }
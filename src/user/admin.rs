@@ -1,20 +1,38 @@
+use std::{collections::BTreeSet, sync::Mutex};
+
+use rbatis::executor::RBatisTxExecutorGuard;
 use rbs::to_value;
-use rs_service_util::auth::gen_access_value;
+use rs_service_util::{auth::gen_access_value, time::get_current_time_fmt};
 use serde::{Deserialize, Serialize};
 
 use crate::{
+    access::AccessMapItem,
     entity::{
         access_entity::AccessEntity, role_access_entity::RoleAccessEntity, role_entity::RoleEntity,
         user_entity::UserEntity, user_role_entity::UserRoleEntity,
     },
+    rbac::rbac_plan::BUILTIN_NAME,
     response::MyError,
-    util::common::get_transaction_tx,
+    util::{
+        access_guard::route_access_list,
+        common::{get_tenant_ids, get_transaction_tx, RedisKeys, DEFAULT_TENANT_ID},
+        structs::Status,
+        sync_opt::{self, SyncOptData},
+    },
 };
 #[derive(Clone, Debug, Serialize, Deserialize)]
 struct IdRes {
     id: i32,
 }
 
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct NameRes {
+    name: String,
+}
+
+/// 本进程中已补齐接口权限的租户
+static SEEDED_TENANTS: Mutex<BTreeSet<i32>> = Mutex::new(BTreeSet::new());
+
 ///检测默认的管理员角色和用户是否存在
 pub async fn check_adm() -> Result<(), MyError> {
     log::info!("check adm start");
@@ -22,8 +40,8 @@ pub async fn check_adm() -> Result<(), MyError> {
     // check db user
     let db_adm_user: Option<IdRes> = tx
         .query_decode(
-            "select id from user where name='ADMIN' and phone='15717827650' ",
-            vec![],
+            "select id from user where name='ADMIN' and phone='15717827650' and tenant_id=? ",
+            vec![to_value!(DEFAULT_TENANT_ID)],
         )
         .await
         .expect("msg");
@@ -40,7 +58,10 @@ pub async fn check_adm() -> Result<(), MyError> {
 
     // check db role
     let db_role: Option<IdRes> = tx
-        .query_decode("select id from role where name='ADMIN'", vec![])
+        .query_decode(
+            "select id from role where name='ADMIN' and tenant_id=? and status=1",
+            vec![to_value!(DEFAULT_TENANT_ID)],
+        )
         .await
         .expect("msg");
     log::info!("db_role {db_role:?}");
@@ -56,8 +77,8 @@ pub async fn check_adm() -> Result<(), MyError> {
     // check db access
     let db_access: Option<IdRes> = tx
         .query_decode(
-            "select id from access where name='ADMIN'",
-            vec![],
+            "select id from access where name='ADMIN' and tenant_id=?",
+            vec![to_value!(DEFAULT_TENANT_ID)],
         )
         .await
         .expect("msg");
@@ -70,7 +91,10 @@ pub async fn check_adm() -> Result<(), MyError> {
             let res = AccessEntity::insert(&tx, &role).await.expect("msg");
             let permission = gen_access_value(res.last_insert_id.as_u64().unwrap_or(0));
             let _: () = tx
-                .query_decode("update access set value=?", vec![to_value!(permission)])
+                .query_decode(
+                    "update access set value=? where id=?",
+                    vec![to_value!(permission), to_value!(res.last_insert_id)],
+                )
                 .await
                 .expect("msg");
 
@@ -95,6 +119,7 @@ pub async fn check_adm() -> Result<(), MyError> {
             id: None,
             role_id: adm_role_id,
             access_id: adm_access_id,
+            tenant_id: DEFAULT_TENANT_ID,
         };
 
         let _res = RoleAccessEntity::insert(&tx, &new_relation)
@@ -119,6 +144,7 @@ pub async fn check_adm() -> Result<(), MyError> {
             id: None,
             role_id: adm_role_id,
             user_id: adm_user_id,
+            tenant_id: DEFAULT_TENANT_ID,
         };
 
         let _res = UserRoleEntity::insert(&tx, &new_relation)
@@ -126,7 +152,146 @@ pub async fn check_adm() -> Result<(), MyError> {
             .expect("msg");
     }
 
+    // 每个租户都需要接口权限, 租户内的角色只能绑定本租户的权限
+    let tenants = seed_tenant_ids(get_tenant_ids().await);
+    let mut seeded = vec![];
+    for tenant_id in tenants.iter() {
+        seeded.extend(seed_route_access(&tx, *tenant_id, adm_user_id).await);
+    }
+
     let _ = tx.commit().await;
+
+    sync_seeded_access(seeded).await;
+    SEEDED_TENANTS.lock().expect("lock").extend(tenants);
     log::info!("check adm end");
     Ok(())
 }
+
+/// 补齐租户下 `ROUTE_ACCESS` 中声明的权限名, 已存在的跳过
+///
+/// 返回新建的权限, 提交后写入缓存
+async fn seed_route_access(
+    tx: &RBatisTxExecutorGuard,
+    tenant_id: i32,
+    create_by: i32,
+) -> Vec<AccessEntity> {
+    let exist: Vec<NameRes> = tx
+        .query_decode(
+            "select name from access where tenant_id=? and status=1",
+            vec![to_value!(tenant_id)],
+        )
+        .await
+        .expect("msg");
+    let exist: Vec<String> = exist.into_iter().map(|val| val.name).collect();
+
+    let mut seeded = vec![];
+    for name in route_access_to_seed(&exist) {
+        let mut access = AccessEntity {
+            id: None,
+            create_time: get_current_time_fmt(),
+            update_time: get_current_time_fmt(),
+            name: name.to_string(),
+            create_by,
+            status: Status::ACTIVE as i8,
+            value: 0,
+            tenant_id,
+        };
+        let res = AccessEntity::insert(tx, &access).await.expect("msg");
+        access.id = Some(res.last_insert_id.as_i64().unwrap_or(0) as i32);
+        access.value = gen_access_value(res.last_insert_id.as_u64().unwrap_or(0));
+        AccessEntity::update_by_column(tx, &access, "id")
+            .await
+            .expect("msg");
+        log::info!("seed access {name} tenant {tenant_id}");
+        seeded.push(access);
+    }
+    seeded
+}
+
+/// 租户首次访问声明了权限的接口时补齐接口权限, 新租户的角色才能绑定这些权限
+pub async fn ensure_route_access(tenant_id: i32, create_by: i32) {
+    if SEEDED_TENANTS.lock().expect("lock").contains(&tenant_id) {
+        return;
+    }
+    let Ok(tx) = get_transaction_tx().await else {
+        return;
+    };
+    let seeded = seed_route_access(&tx, tenant_id, create_by).await;
+    if let Err(error) = tx.commit().await {
+        log::error!("补齐租户 {tenant_id} 接口权限失败 {error}");
+        return;
+    }
+    sync_seeded_access(seeded).await;
+    SEEDED_TENANTS.lock().expect("lock").insert(tenant_id);
+}
+
+async fn sync_seeded_access(seeded: Vec<AccessEntity>) {
+    sync_opt::sync_list(
+        seeded
+            .into_iter()
+            .map(|access| {
+                let tenant_id = access.tenant_id;
+                let item = AccessMapItem {
+                    id: access.id.unwrap_or_default(),
+                    name: access.name,
+                    value: access.value,
+                };
+                SyncOptData::default(
+                    RedisKeys::AccessMapIds,
+                    RedisKeys::AccessMap,
+                    tenant_id,
+                    item.id,
+                    item,
+                )
+            })
+            .collect(),
+    )
+    .await;
+}
+
+/// 需要补齐的接口权限名, 去重并排序; ADMIN 只存在于默认租户, 不补齐
+fn route_access_to_seed(exist: &[String]) -> Vec<&'static str> {
+    let mut names: Vec<&str> = route_access_list()
+        .iter()
+        .map(|val| val.access)
+        .filter(|name| *name != BUILTIN_NAME && !exist.iter().any(|val| val == name))
+        .collect();
+    names.sort();
+    names.dedup();
+    names
+}
+
+/// 启动时补齐的租户, 始终包含默认租户
+fn seed_tenant_ids(mut tenants: Vec<i32>) -> Vec<i32> {
+    tenants.push(DEFAULT_TENANT_ID);
+    tenants.sort();
+    tenants.dedup();
+    tenants
+}
+
+#[cfg(test)]
+mod test {
+    use super::{route_access_to_seed, seed_tenant_ids};
+    use crate::util::common::DEFAULT_TENANT_ID;
+
+    #[test]
+    fn test_seed_tenant_ids() {
+        assert_eq!(seed_tenant_ids(vec![]), vec![DEFAULT_TENANT_ID]);
+        assert_eq!(seed_tenant_ids(vec![3, 2, 3, 0]), vec![0, 2, 3]);
+    }
+
+    #[test]
+    fn test_route_access_to_seed() {
+        // 非默认租户没有任何权限时补齐全部接口权限
+        let all = route_access_to_seed(&[]);
+        assert!(all.contains(&"ROLE_DELETE"));
+        assert!(!all.contains(&"ADMIN"));
+        assert!(all.windows(2).all(|val| val[0] < val[1]));
+
+        // 已有同名权限的跳过
+        let exist = vec!["ROLE_DELETE".to_string(), "ADMIN".to_string()];
+        let rest = route_access_to_seed(&exist);
+        assert!(!rest.contains(&"ROLE_DELETE"));
+        assert_eq!(rest.len(), all.len() - 1);
+    }
+}
@@ -1,16 +1,30 @@
-use super::LoginData;
+use super::{AuthCheckData, AuthCheckRes, LoginData};
 use crate::{
-    access::AccessValueData,
+    access::{get_adm_access_value, AccessValueData},
+    cache::{cache, CacheStore},
+    entity::{access_entity::AccessEntity, user_entity::UserEntity},
+    policy::{
+        get_policies_by_access,
+        policy_eval::{eval_policies, PolicyAttrs, PolicyDecision},
+    },
     response::{MyError, ResponseBody},
     role::AccessData,
-    user::{check_user_by_user_id, user_role_service::sync_user_auth, RedisLoginData},
-    util::common::get_jwt_from_req,
-    RB, REDIS_KEY,
+    user::{
+        admin::ensure_route_access,
+        casbin_service::casbin_enforce,
+        check_user_by_user_id,
+        user_role_service::{sync_user_auth, user_role_ids_args, USER_ROLE_IDS_SQL},
+        RedisLoginData,
+    },
+    util::{
+        access_guard::route_access_list,
+        common::{get_jwt_from_req, get_tenant_id, RedisKeys},
+    },
+    RB,
 };
 use actix_web::{get, post, web, HttpRequest, Responder};
 use rbs::to_value;
-use redis::AsyncCommands;
-use rs_service_util::{jwt::gen_jwt_token, redis_conn, time::get_current_timestamp};
+use rs_service_util::{jwt::gen_jwt_token, time::get_current_timestamp};
 use serde::{Deserialize, Serialize};
 
 const LOGIN_EX_TIME: u64 = 60 * 60 * 24 * 10;
@@ -20,6 +34,8 @@ struct PasswordData {
     password: String,
     name: String,
     id: i32,
+    tenant_id: i32,
+    user_type: i16,
 }
 
 #[utoipa::path(
@@ -27,14 +43,18 @@ struct PasswordData {
   responses( (status = 200))
 )]
 #[get("/get_user_permission/{id}")]
-async fn get_user_permission(id: web::Path<i32>) -> Result<impl Responder, MyError> {
+async fn get_user_permission(
+    id: web::Path<i32>,
+    req: HttpRequest,
+) -> Result<impl Responder, MyError> {
+    let tenant_id = get_tenant_id(&req);
     let id = id.into_inner();
-    let check_res = check_user_by_user_id(id).await;
+    let check_res = check_user_by_user_id(id, tenant_id).await;
     if check_res.is_none() {
         return Err(MyError::UserNotExist);
     }
     let db_uer = check_res.unwrap();
-    let auth = sync_user_auth(db_uer.name.clone()).await?;
+    let auth = sync_user_auth(db_uer.id, tenant_id).await?;
 
     Ok(ResponseBody::default(Some(auth)))
 }
@@ -43,45 +63,53 @@ async fn get_user_permission(id: web::Path<i32>) -> Result<impl Responder, MyErr
     tag = "auth",
     responses( (status = 200) )
 )]
-#[post("/login")]
-async fn login(req_data: web::Json<LoginData>) -> Result<impl Responder, MyError> {
-    let key = format!("{}_{}", REDIS_KEY.to_string(), req_data.name.clone());
-    let mut conn = redis_conn!().await;
-    let is_login: Result<bool, redis::RedisError> = conn.exists(key.clone()).await;
-    let is_login = match is_login {
-        Err(e) => {
-            log::error!("{e:?}");
-            return Err(MyError::RedisError);
-        }
-        Ok(res) => res,
-    };
-
-    if is_login {
-        let user_info: Option<RedisLoginData> =
-            conn.get(key).await.map_err(|_| MyError::AuthError)?;
-
-        match user_info {
-            None => {
-                return Err(MyError::UserNotExist);
-            }
-            Some(info) => {
-                match check_user_pass_by_name(info.name.clone()).await {
-                    None => {
-                        return Err(MyError::UserNotExist);
-                    }
-                    Some(pass_data) => {
-                        if !pass_data.password.eq(&req_data.password) {
-                            return Err(MyError::PassWordError);
-                        }
-                    }
-                }
-                let jwt_token = gen_jwt_token(info.clone());
-                return Ok(ResponseBody::default(Some(jwt_token)));
-            }
-        }
+#[post("/check")]
+async fn check_permission(
+    req_data: web::Json<AuthCheckData>,
+    req: HttpRequest,
+) -> Result<impl Responder, MyError> {
+    let tenant_id = get_tenant_id(&req);
+    let jwt_user = get_jwt_from_req(&req);
+    if let (Some(obj), Some(act)) = (&req_data.obj, &req_data.act) {
+        let sub = req_data.sub.clone().unwrap_or(jwt_user.name);
+        return Ok(ResponseBody::default(Some(AuthCheckRes {
+            pass: casbin_enforce(&sub, obj, act, tenant_id).await,
+            policy_id: None,
+        })));
     }
 
-    let db_user = check_user_pass_by_name(req_data.name.clone()).await;
+    let access = req_data.access.as_ref().ok_or(MyError::AccessNotExist)?;
+    let res = check_user_access(
+        &jwt_user,
+        tenant_id,
+        access,
+        req_data.resource.clone().unwrap_or_default(),
+    )
+    .await?;
+
+    Ok(ResponseBody::default(Some(res)))
+}
+
+#[utoipa::path(
+    tag = "auth",
+    responses( (status = 200) )
+)]
+#[get("/route_access")]
+async fn get_route_access() -> impl Responder {
+    ResponseBody::default(Some(route_access_list()))
+}
+
+#[utoipa::path(
+    tag = "auth",
+    responses( (status = 200) )
+)]
+#[post("/login")]
+async fn login(
+    req_data: web::Json<LoginData>,
+    req: HttpRequest,
+) -> Result<impl Responder, MyError> {
+    let tenant_id = get_tenant_id(&req);
+    let db_user = check_user_pass_by_name(req_data.name.clone(), tenant_id).await;
 
     if db_user.is_none() {
         return Err(MyError::UserNotExist);
@@ -92,18 +120,30 @@ async fn login(req_data: web::Json<LoginData>) -> Result<impl Responder, MyError
     if !is_eq {
         return Err(MyError::PassWordError);
     }
+
+    let key = RedisKeys::Login.id_key(db_user.tenant_id, db_user.id);
+    // 登录缓存的结构变化后无法解析, 按未登录处理, 重新写入后覆盖
+    let user_info: Option<RedisLoginData> = cache()
+        .get(&key)
+        .await
+        .and_then(|json| serde_json::from_str(&json).ok());
+    if let Some(info) = user_info {
+        let jwt_token = gen_jwt_token(info);
+        return Ok(ResponseBody::default(Some(jwt_token)));
+    }
+
     let auth: u64 = get_user_access_val(db_user.id).await;
     let redis_data = RedisLoginData {
         auth,
         last_login_time: get_current_timestamp(),
         name: req_data.name.clone(),
         id: db_user.id.clone(),
+        tenant_id: db_user.tenant_id,
+        user_type: db_user.user_type,
     };
 
-    let _: () = conn
-        .set_ex(key.clone(), redis_data.clone(), LOGIN_EX_TIME)
-        .await
-        .map_err(|_| MyError::AuthError)?;
+    let json = serde_json::to_string(&redis_data).map_err(|_| MyError::AuthError)?;
+    cache().set_ex(&key, json, LOGIN_EX_TIME).await;
 
     let jwt_token = gen_jwt_token(redis_data);
     return Ok(ResponseBody::default(Some(jwt_token)));
@@ -116,15 +156,15 @@ async fn login(req_data: web::Json<LoginData>) -> Result<impl Responder, MyError
 #[post("/logout/{id}")]
 async fn logout(id: web::Path<i32>, req: HttpRequest) -> Result<impl Responder, MyError> {
     let user_id = id.into_inner();
-    let check_res = check_user_by_user_id(user_id).await;
+    let check_res = check_user_by_user_id(user_id, get_tenant_id(&req)).await;
     if check_res.is_none() {
         return Err(MyError::UserNotExist);
     }
-    let jwt_user = get_jwt_from_req(req);
+    let jwt_user = get_jwt_from_req(&req);
     if jwt_user.id != user_id {
         return Err(MyError::UserIsWrong);
     }
-    delete_user_from_redis(jwt_user.name).await;
+    delete_user_from_redis(jwt_user.id, jwt_user.tenant_id).await;
 
     Ok(ResponseBody::success("退出成功!"))
 }
@@ -135,13 +175,18 @@ pub async fn get_user_access_val(user_id: i32) -> u64 {
     let ex = RB.acquire().await.expect("get ex error");
     let access_ids: Option<Vec<AccessData>> = ex
         .query_decode(
-            "select role_access.access_id from role_access where role_id in (select user_role.role_id from user_role where user_id=?)",
-            vec![to_value!(user_id)],
+            &format!(
+                "select distinct role_access.access_id from role_access \
+                inner join role on role.id = role_access.role_id and role.status = 1 \
+                where role_access.role_id in ({USER_ROLE_IDS_SQL})"
+            ),
+            user_role_ids_args(user_id),
         )
         .await
         .expect("查询权限id错误");
 
-    if let Some(access_id_vec) = access_ids {
+    // 没有权限时跳过, 避免拼出 `in ()`
+    if let Some(access_id_vec) = access_ids.filter(|list| !list.is_empty()) {
         let ids: Vec<String> = access_id_vec
             .into_iter()
             .map(|val| val.access_id.to_string())
@@ -149,7 +194,7 @@ pub async fn get_user_access_val(user_id: i32) -> u64 {
         let ids = ids.join(",");
         let access_values: Option<Vec<AccessValueData>> = ex
             .query_decode(
-                &format!("select value from access where id in ({ids})"),
+                &format!("select value from access where status = 1 and id in ({ids})"),
                 vec![],
             )
             .await
@@ -165,21 +210,159 @@ pub async fn get_user_access_val(user_id: i32) -> u64 {
     vals
 }
 
-async fn check_user_pass_by_name(name: String) -> Option<PasswordData> {
+/// 校验用户是否拥有权限
+///
+/// 先用角色权限值判断, 再对权限上挂载的策略求值; 持有 ADMIN 权限直接通过
+pub async fn check_user_access(
+    login: &RedisLoginData,
+    tenant_id: i32,
+    access_name: &str,
+    resource: serde_json::Map<String, serde_json::Value>,
+) -> Result<AuthCheckRes, MyError> {
+    let auth = get_login_auth(login).await;
+    if is_adm_auth(auth).await {
+        return Ok(AuthCheckRes {
+            pass: true,
+            policy_id: None,
+        });
+    }
+
+    let ex = RB.acquire().await.expect("get ex error");
+    let access = AccessEntity::select_by_name(&ex, access_name, tenant_id)
+        .await
+        .expect("权限查询失败")
+        .ok_or(MyError::AccessNotExist)?;
+    drop(ex);
+    if auth & access.value == 0 {
+        return Ok(AuthCheckRes {
+            pass: false,
+            policy_id: None,
+        });
+    }
+
+    let policies = get_policies_by_access(access.id.expect("msg"), tenant_id).await;
+    if policies.is_empty() {
+        return Ok(AuthCheckRes {
+            pass: true,
+            policy_id: None,
+        });
+    }
+
+    let attrs = PolicyAttrs {
+        subject: get_subject_attrs(login).await?,
+        resource,
+    };
+    let res = match eval_policies(&policies, &attrs) {
+        PolicyDecision::Allow(policy_id) => AuthCheckRes {
+            pass: true,
+            policy_id,
+        },
+        PolicyDecision::Deny(policy_id) => AuthCheckRes {
+            pass: false,
+            policy_id,
+        },
+    };
+    Ok(res)
+}
+
+/// 接口权限校验, 只比较角色权限值, 不对策略求值; 持有 ADMIN 权限直接通过
+pub async fn has_route_access(login: &RedisLoginData, tenant_id: i32, access_name: &str) -> bool {
+    ensure_route_access(tenant_id, login.id).await;
+    let auth = get_login_auth(login).await;
+    if is_adm_auth(auth).await {
+        return true;
+    }
+
+    let ex = RB.acquire().await.expect("get ex error");
+    let access = AccessEntity::select_by_name(&ex, access_name, tenant_id)
+        .await
+        .expect("权限查询失败");
+    match access {
+        None => false,
+        Some(access) => auth & access.value != 0,
+    }
+}
+
+/// 绑定或解绑 ADMIN 权限、ADMIN 角色时, 调用者需持有 ADMIN 权限
+pub async fn check_adm_bind(
+    login: &RedisLoginData,
+    adm_id: Option<i32>,
+    add_ids: &[i32],
+    sub_ids: &[i32],
+) -> Result<(), MyError> {
+    if !is_adm_bind(adm_id, add_ids, sub_ids) {
+        return Ok(());
+    }
+    if !is_adm_auth(get_login_auth(login).await).await {
+        return Err(MyError::AdmBindForbidden);
+    }
+    Ok(())
+}
+
+fn is_adm_bind(adm_id: Option<i32>, add_ids: &[i32], sub_ids: &[i32]) -> bool {
+    adm_id.is_some_and(|id| add_ids.contains(&id) || sub_ids.contains(&id))
+}
+
+pub async fn is_adm_auth(auth: u64) -> bool {
+    let adm_value = get_adm_access_value().await;
+    adm_value != 0 && auth & adm_value != 0
+}
+
+/// 登录缓存中的权限值会随角色变更同步, token 中的可能已过期
+pub async fn get_login_auth(login: &RedisLoginData) -> u64 {
+    let key = RedisKeys::Login.id_key(login.tenant_id, login.id);
+    let cache_info: Option<RedisLoginData> = cache()
+        .get(&key)
+        .await
+        .and_then(|json| serde_json::from_str(&json).ok());
+    cache_info.map(|info| info.auth).unwrap_or(login.auth)
+}
+
+/// 策略中 subject 的属性, 即用户表字段 (不含密码)
+async fn get_subject_attrs(
+    login: &RedisLoginData,
+) -> Result<serde_json::Map<String, serde_json::Value>, MyError> {
+    let ex = RB.acquire().await.expect("get ex error");
+    let db_user: UserEntity = UserEntity::select_by_id(&ex, login.id, login.tenant_id)
+        .await
+        .expect("查询用户失败")
+        .ok_or(MyError::UserNotExist)?;
+    let mut attrs = match serde_json::to_value(db_user).expect("msg") {
+        serde_json::Value::Object(map) => map,
+        _ => serde_json::Map::new(),
+    };
+    attrs.remove("password");
+    Ok(attrs)
+}
+
+async fn check_user_pass_by_name(name: String, tenant_id: i32) -> Option<PasswordData> {
     let ex = RB.acquire().await.expect("msg");
 
     let db_user: Option<PasswordData> = ex
         .query_decode(
-            "select password, id, name from user where user.name=?",
-            vec![to_value!(name)],
+            "select password, id, name, tenant_id, user_type from user where user.name=? and user.tenant_id=?",
+            vec![to_value!(name), to_value!(tenant_id)],
         )
         .await
         .expect("获取用户失败");
     db_user
 }
 
-async fn delete_user_from_redis(user_name: String) {
-    let key = format!("{}_{}", REDIS_KEY.to_string(), user_name);
-    let mut conn = redis_conn!().await;
-    let _: () = conn.del(key).await.expect("msg");
+async fn delete_user_from_redis(user_id: i32, tenant_id: i32) {
+    let key = RedisKeys::Login.id_key(tenant_id, user_id);
+    cache().del(&key).await;
+}
+
+#[cfg(test)]
+mod test {
+    use super::is_adm_bind;
+
+    #[test]
+    fn test_is_adm_bind() {
+        assert!(is_adm_bind(Some(1), &[1, 2], &[]));
+        assert!(is_adm_bind(Some(1), &[2], &[1]));
+        assert!(!is_adm_bind(Some(1), &[2], &[3]));
+        // 未初始化 ADMIN 时不限制
+        assert!(!is_adm_bind(None, &[1], &[1]));
+    }
 }
@@ -0,0 +1,133 @@
+use std::collections::{HashMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::{
+    user_role_service::{get_inherited_role_ids, get_user_role_ids},
+    OptionData,
+};
+use crate::{
+    entity::{access_entity::AccessEntity, role_access_entity::RoleAccessEntity},
+    util::{common::diff_ids, structs::Status},
+    RB,
+};
+
+/// 模拟绑定后单个用户的权限变化
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct SimUserChange {
+    pub user: OptionData,
+    pub gain: Vec<OptionData>,
+    pub lose: Vec<OptionData>,
+}
+
+/// 模拟绑定的结果, 不写入数据库和缓存
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct BindSimRes {
+    pub add_ids: Vec<i32>,
+    pub sub_ids: Vec<i32>,
+    pub users: Vec<SimUserChange>, // 只包含权限有变化的用户
+}
+
+/// 角色 -> 权限id
+pub type RoleAccessMap = HashMap<i32, HashSet<i32>>;
+
+/// 与计算权限值一致, 忽略已停用的角色和权限
+pub async fn get_role_access_map(role_ids: &[i32]) -> RoleAccessMap {
+    let mut map: RoleAccessMap = HashMap::new();
+    if role_ids.is_empty() {
+        return map;
+    }
+    let ids: Vec<String> = role_ids.iter().map(|id| id.to_string()).collect();
+    let ex = RB.acquire().await.expect("msg");
+    let list: Vec<RoleAccessEntity> = ex
+        .query_decode(
+            &format!(
+                "select role_access.* from role_access \
+                inner join role on role.id = role_access.role_id and role.status = 1 \
+                inner join access on access.id = role_access.access_id and access.status = 1 \
+                where role_access.role_id in ({})",
+                ids.join(",")
+            ),
+            vec![],
+        )
+        .await
+        .expect("查询角色权限失败");
+    for val in list {
+        map.entry(val.role_id).or_default().insert(val.access_id);
+    }
+    map
+}
+
+/// 一组角色拥有的权限id
+pub fn collect_access(role_ids: &[i32], map: &RoleAccessMap) -> Vec<i32> {
+    let mut ids: Vec<i32> = role_ids
+        .iter()
+        .filter_map(|id| map.get(id))
+        .flatten()
+        .copied()
+        .collect::<HashSet<i32>>()
+        .into_iter()
+        .collect();
+    ids.sort();
+    ids
+}
+
+/// 直接绑定的角色改为 role_ids 后, 用户的有效角色 (继承的角色不受影响)
+pub async fn simulate_user_roles(user_id: i32, role_ids: &[i32]) -> (Vec<i32>, Vec<i32>) {
+    let current = get_user_role_ids(user_id).await;
+    let mut next = get_inherited_role_ids(user_id).await;
+    for id in role_ids {
+        if !next.contains(id) {
+            next.push(*id);
+        }
+    }
+    (current, next)
+}
+
+/// 按模拟前后的权限id生成变化, 没有变化时返回 None
+pub async fn gen_user_change(
+    user: OptionData,
+    current_access: &[i32],
+    next_access: &[i32],
+) -> Option<SimUserChange> {
+    let (gain_ids, lose_ids) = diff_ids(next_access, current_access);
+    if gain_ids.is_empty() && lose_ids.is_empty() {
+        return None;
+    }
+    Some(SimUserChange {
+        user,
+        gain: get_access_options(&gain_ids).await,
+        lose: get_access_options(&lose_ids).await,
+    })
+}
+
+async fn get_access_options(ids: &[i32]) -> Vec<OptionData> {
+    if ids.is_empty() {
+        return vec![];
+    }
+    let ex = RB.acquire().await.expect("msg");
+    let list: Vec<AccessEntity> = AccessEntity::select_in_column(&ex, "id", ids)
+        .await
+        .expect("权限查询失败");
+    list.into_iter()
+        .filter(|val| val.status == Status::ACTIVE as i8)
+        .map(|val| OptionData::default(&val.name, val.id.expect("msg")))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::{HashMap, HashSet};
+
+    use super::collect_access;
+
+    #[test]
+    fn test_collect_access() {
+        let mut map = HashMap::new();
+        map.insert(1, HashSet::from([10, 11]));
+        map.insert(2, HashSet::from([11, 12]));
+        assert_eq!(collect_access(&[1, 2, 3], &map), vec![10, 11, 12]);
+        assert!(collect_access(&[3], &map).is_empty());
+    }
+}
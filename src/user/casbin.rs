@@ -0,0 +1,176 @@
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+
+use crate::util::access_guard::RouteAccess;
+
+/// Casbin RBAC 模型, obj 可以是权限名或接口路径 (`{id}` 形式的路径参数)
+pub const CASBIN_MODEL: &str = "[request_definition]
+r = sub, obj, act
+
+[policy_definition]
+p = sub, obj, act
+
+[role_definition]
+g = _, _
+
+[policy_effect]
+e = some(where (p.eft == allow))
+
+[matchers]
+m = g(r.sub, p.sub) && (p.obj == \"*\" || r.obj == p.obj || keyMatch4(r.obj, p.obj)) && (p.act == \"*\" || r.act == p.act)
+";
+
+/// 角色在策略中的前缀, 避免与同名用户冲突
+pub const ROLE_PREFIX: &str = "role:";
+
+const ADMIN_ACCESS: &str = "ADMIN";
+
+/// 角色名和权限名
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RoleAccessName {
+    pub role_name: String,
+    pub access_name: String,
+}
+
+/// 用户名和角色名
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct UserRoleName {
+    pub user_name: String,
+    pub role_name: String,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct CasbinPolicy {
+    pub p: Vec<[String; 3]>,
+    pub g: Vec<[String; 2]>,
+}
+
+impl CasbinPolicy {
+    /// 角色的每个权限生成 `p, role:角色, 权限, *`, 并为声明了该权限的接口生成 `p, role:角色, 路径, 方法`
+    ///
+    /// 持有 ADMIN 的角色生成 `p, role:角色, *, *`
+    pub fn build(
+        role_access: &[RoleAccessName],
+        user_roles: &[UserRoleName],
+        routes: &[RouteAccess],
+    ) -> Self {
+        let mut policy = CasbinPolicy::default();
+        for val in role_access {
+            let sub = format!("{ROLE_PREFIX}{}", val.role_name);
+            if val.access_name == ADMIN_ACCESS {
+                policy.push_p(&sub, "*", "*");
+                continue;
+            }
+            policy.push_p(&sub, &val.access_name, "*");
+            for route in routes
+                .iter()
+                .filter(|route| route.access == val.access_name)
+            {
+                policy.push_p(&sub, route.path, route.method);
+            }
+        }
+        for val in user_roles {
+            let line = [
+                val.user_name.clone(),
+                format!("{ROLE_PREFIX}{}", val.role_name),
+            ];
+            if !policy.g.contains(&line) {
+                policy.g.push(line);
+            }
+        }
+        policy
+    }
+
+    fn push_p(&mut self, sub: &str, obj: &str, act: &str) {
+        let line = [sub.to_string(), obj.to_string(), act.to_string()];
+        if !self.p.contains(&line) {
+            self.p.push(line);
+        }
+    }
+
+    /// Casbin csv 格式的策略
+    pub fn to_csv(&self) -> String {
+        let p = self.p.iter().map(|line| format!("p, {}", line.join(", ")));
+        let g = self.g.iter().map(|line| format!("g, {}", line.join(", ")));
+        p.chain(g).collect::<Vec<String>>().join("\n")
+    }
+
+    /// 按 `CASBIN_MODEL` 求值
+    pub fn enforce(&self, sub: &str, obj: &str, act: &str) -> bool {
+        let mut subjects: HashSet<&str> = HashSet::from([sub]);
+        for [user, role] in self.g.iter() {
+            if user == sub {
+                subjects.insert(role);
+            }
+        }
+        self.p.iter().any(|[p_sub, p_obj, p_act]| {
+            subjects.contains(p_sub.as_str())
+                && (p_obj == "*" || p_obj == obj || key_match(obj, p_obj))
+                && (p_act == "*" || p_act == act)
+        })
+    }
+}
+
+/// 同 Casbin 的 keyMatch4, pattern 中的 `{name}` 匹配一段路径
+fn key_match(path: &str, pattern: &str) -> bool {
+    let path: Vec<&str> = path.split('/').collect();
+    let pattern: Vec<&str> = pattern.split('/').collect();
+    if path.len() != pattern.len() {
+        return false;
+    }
+    path.iter().zip(pattern.iter()).all(|(seg, pat)| {
+        (pat.starts_with('{') && pat.ends_with('}') && !seg.is_empty()) || seg == pat
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::{CasbinPolicy, RoleAccessName, UserRoleName};
+    use crate::util::access_guard::RouteAccess;
+
+    fn role_access(role_name: &str, access_name: &str) -> RoleAccessName {
+        RoleAccessName {
+            role_name: role_name.to_string(),
+            access_name: access_name.to_string(),
+        }
+    }
+
+    fn user_role(user_name: &str, role_name: &str) -> UserRoleName {
+        UserRoleName {
+            user_name: user_name.to_string(),
+            role_name: role_name.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_casbin_policy() {
+        let routes = [
+            RouteAccess::new("DELETE", "/api/role/{id}", "ROLE_DELETE"),
+            RouteAccess::new("POST", "/api/role/create_role", "ROLE_CREATE"),
+        ];
+        let policy = CasbinPolicy::build(
+            &[
+                role_access("ops", "ROLE_DELETE"),
+                role_access("root", "ADMIN"),
+            ],
+            &[user_role("alice", "ops"), user_role("bob", "root")],
+            &routes,
+        );
+        assert_eq!(
+            policy.to_csv(),
+            "p, role:ops, ROLE_DELETE, *\n\
+            p, role:ops, /api/role/{id}, DELETE\n\
+            p, role:root, *, *\n\
+            g, alice, role:ops\n\
+            g, bob, role:root"
+        );
+
+        assert!(policy.enforce("alice", "ROLE_DELETE", "read"));
+        assert!(policy.enforce("alice", "/api/role/5", "DELETE"));
+        assert!(!policy.enforce("alice", "/api/role/5/users", "DELETE"));
+        assert!(!policy.enforce("alice", "/api/role/create_role", "POST"));
+        assert!(policy.enforce("bob", "/api/role/create_role", "POST"));
+        assert!(!policy.enforce("carol", "ROLE_DELETE", "*"));
+    }
+}
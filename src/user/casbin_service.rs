@@ -0,0 +1,64 @@
+use super::{
+    casbin::{CasbinPolicy, RoleAccessName, UserRoleName, CASBIN_MODEL},
+    user_role_service::ALL_USER_ROLE_SQL,
+    CasbinExportRes,
+};
+use crate::{
+    response::ResponseBody,
+    util::{access_guard::route_access_list, common::get_tenant_id},
+    RB,
+};
+use actix_web::{get, HttpRequest, Responder};
+use rbs::to_value;
+
+const ROLE_ACCESS_NAME_SQL: &str =
+    "select role.name as role_name, access.name as access_name from role_access \
+    inner join role on role.id = role_access.role_id and role.status = 1 \
+    inner join access on access.id = role_access.access_id and access.status = 1 \
+    where role_access.tenant_id = ?";
+
+#[utoipa::path(
+    tag = "auth",
+    responses( (status = 200) )
+)]
+#[get("/casbin")]
+pub async fn get_casbin_policy(req: HttpRequest) -> impl Responder {
+    let tenant_id = get_tenant_id(&req);
+    let policy = load_casbin_policy(tenant_id, None).await;
+    ResponseBody::default(Some(CasbinExportRes {
+        model: CASBIN_MODEL.to_string(),
+        policy: policy.to_csv(),
+    }))
+}
+
+/// 按 Casbin 的 `(sub, obj, act)` 校验, sub 为用户名
+pub async fn casbin_enforce(sub: &str, obj: &str, act: &str, tenant_id: i32) -> bool {
+    load_casbin_policy(tenant_id, Some(sub))
+        .await
+        .enforce(sub, obj, act)
+}
+
+/// 租户下的策略, user_name 存在时 `g` 只包含该用户
+async fn load_casbin_policy(tenant_id: i32, user_name: Option<&str>) -> CasbinPolicy {
+    let ex = RB.acquire().await.expect("get ex error");
+    let role_access: Vec<RoleAccessName> = ex
+        .query_decode(ROLE_ACCESS_NAME_SQL, vec![to_value!(tenant_id)])
+        .await
+        .expect("查询角色权限失败");
+
+    let mut sql = format!(
+        "select user.name as user_name, role.name as role_name from ({ALL_USER_ROLE_SQL}) as t \
+        inner join user on user.id = t.user_id and user.status = 1 \
+        inner join role on role.id = t.role_id and role.status = 1 \
+        where t.tenant_id = ?"
+    );
+    let mut args = vec![to_value!(tenant_id)];
+    if let Some(name) = user_name {
+        sql.push_str(" and user.name = ?");
+        args.push(to_value!(name));
+    }
+    let user_roles: Vec<UserRoleName> =
+        ex.query_decode(&sql, args).await.expect("查询用户角色失败");
+
+    CasbinPolicy::build(&role_access, &user_roles, &route_access_list())
+}
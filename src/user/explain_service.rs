@@ -0,0 +1,264 @@
+use super::{
+    check_user_by_user_id, AccessExplainData, AccessExplainRes, AccessGrantPath, GrantBinding,
+    OptionData,
+};
+use crate::{
+    access::check_access_by_id,
+    entity::access_entity::AccessEntity,
+    response::{MyError, ResponseBody},
+    util::common::{get_tenant_id, DEFAULT_TENANT_ID},
+    RB,
+};
+use actix_web::{post, web, HttpRequest, Responder};
+use rbs::to_value;
+use serde::{Deserialize, Serialize};
+
+/// 授权链路查询结果, 用户组和部门字段只在对应来源的查询中存在
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+struct GrantRow {
+    role_id: i32,
+    role_name: String,
+    access_id: i32,
+    access_name: String,
+    group_id: Option<i32>,
+    group_name: Option<String>,
+    org_id: Option<i32>,
+    org_name: Option<String>,
+    member_org_id: Option<i32>,
+    member_org_name: Option<String>,
+}
+
+const ROLE_ACCESS_JOIN: &str = "inner join role on role.id = {role_col} and role.status = 1 \
+    inner join role_access on role_access.role_id = role.id \
+    inner join access on access.id = role_access.access_id and access.status = 1";
+
+const GRANT_FIELDS: &str =
+    "role.id as role_id, role.name as role_name, access.id as access_id, access.name as access_name";
+
+#[utoipa::path(
+    tag = "auth",
+    responses( (status = 200) )
+)]
+#[post("/explain")]
+pub async fn explain_access(
+    req_data: web::Json<AccessExplainData>,
+    req: HttpRequest,
+) -> Result<impl Responder, MyError> {
+    let tenant_id = get_tenant_id(&req);
+    let user = check_user_by_user_id(req_data.user_id, tenant_id)
+        .await
+        .ok_or(MyError::UserNotExist)?;
+    let access = check_access_by_id(req_data.access_id, tenant_id)
+        .await
+        .ok_or(MyError::AccessNotExist)?;
+
+    // 持有 ADMIN 权限等同于拥有全部权限, 一并列出
+    let ex = RB.acquire().await.expect("msg");
+    let mut access_ids = vec![req_data.access_id];
+    let adm_access = AccessEntity::select_by_name(&ex, "ADMIN", DEFAULT_TENANT_ID)
+        .await
+        .expect("权限查询失败");
+    if let Some(adm_id) = adm_access.and_then(|val| val.id) {
+        if adm_id != req_data.access_id {
+            access_ids.push(adm_id);
+        }
+    }
+    drop(ex);
+
+    let rows = get_grant_rows(req_data.user_id, &access_ids).await;
+    let (paths, revoke) = build_grant_paths(req_data.user_id, req_data.access_id, rows);
+
+    Ok(ResponseBody::default(Some(AccessExplainRes {
+        user: OptionData::default(&user.name, req_data.user_id),
+        access: OptionData::default(&access.name, req_data.access_id),
+        granted: !paths.is_empty(),
+        paths,
+        revoke,
+    })))
+}
+
+/// 分别查询直接绑定、用户组、部门三种来源的授权链路
+async fn get_grant_rows(user_id: i32, access_ids: &[i32]) -> Vec<GrantRow> {
+    let ids: Vec<String> = access_ids.iter().map(|id| id.to_string()).collect();
+    let ids = ids.join(",");
+    let sqls = [
+        format!(
+            "select {GRANT_FIELDS} from user_role {} where user_role.user_id = ? and access.id in ({ids})",
+            ROLE_ACCESS_JOIN.replace("{role_col}", "user_role.role_id")
+        ),
+        format!(
+            "select {GRANT_FIELDS}, user_group.id as group_id, user_group.name as group_name from user_group_member \
+            inner join user_group on user_group.id = user_group_member.group_id and user_group.status = 1 \
+            inner join group_role on group_role.group_id = user_group.id {} \
+            where user_group_member.user_id = ? and access.id in ({ids})",
+            ROLE_ACCESS_JOIN.replace("{role_col}", "group_role.role_id")
+        ),
+        format!(
+            "select {GRANT_FIELDS}, bind_org.id as org_id, bind_org.name as org_name, member_org.id as member_org_id, member_org.name as member_org_name from user_org \
+            inner join org_unit member_org on member_org.id = user_org.org_id and member_org.status = 1 \
+            inner join org_role on member_org.path like concat('%/', org_role.org_id, '/%') \
+            inner join org_unit bind_org on bind_org.id = org_role.org_id {} \
+            where user_org.user_id = ? and access.id in ({ids})",
+            ROLE_ACCESS_JOIN.replace("{role_col}", "org_role.role_id")
+        ),
+    ];
+
+    let ex = RB.acquire().await.expect("get ex error");
+    let mut rows: Vec<GrantRow> = vec![];
+    for sql in sqls.iter() {
+        let list: Vec<GrantRow> = ex
+            .query_decode(sql, vec![to_value!(user_id)])
+            .await
+            .expect("查询授权链路失败");
+        rows.extend(list);
+    }
+    rows
+}
+
+/// 组装授权链路, 并给出撤销所需的解绑项
+///
+/// 解绑项取每条链路上靠近用户的一环 (用户角色 / 用户组成员 / 部门成员), 只影响该用户
+fn build_grant_paths(
+    user_id: i32,
+    access_id: i32,
+    rows: Vec<GrantRow>,
+) -> (Vec<AccessGrantPath>, Vec<GrantBinding>) {
+    let mut paths: Vec<AccessGrantPath> = vec![];
+    let mut revoke: Vec<GrantBinding> = vec![];
+
+    for row in rows {
+        let role_access = GrantBinding::RoleAccess {
+            role_id: row.role_id,
+            access_id: row.access_id,
+        };
+        let (bindings, user_side) = match (row.group_id, row.org_id, row.member_org_id) {
+            (Some(group_id), _, _) => {
+                let member = GrantBinding::GroupMember { group_id, user_id };
+                let group_role = GrantBinding::GroupRole {
+                    group_id,
+                    role_id: row.role_id,
+                };
+                (vec![member.clone(), group_role, role_access], member)
+            }
+            (None, Some(org_id), Some(member_org_id)) => {
+                let member = GrantBinding::OrgMember {
+                    org_id: member_org_id,
+                    user_id,
+                };
+                let org_role = GrantBinding::OrgRole {
+                    org_id,
+                    role_id: row.role_id,
+                };
+                (vec![member.clone(), org_role, role_access], member)
+            }
+            _ => {
+                let user_role = GrantBinding::UserRole {
+                    user_id,
+                    role_id: row.role_id,
+                };
+                (vec![user_role.clone(), role_access], user_role)
+            }
+        };
+        if !revoke.contains(&user_side) {
+            revoke.push(user_side);
+        }
+
+        let group = match (row.group_id, row.group_name) {
+            (Some(id), Some(name)) => Some(OptionData { id, name }),
+            _ => None,
+        };
+        let org = match (row.org_id, row.org_name) {
+            (Some(id), Some(name)) => Some(OptionData { id, name }),
+            _ => None,
+        };
+        let member_org = match (row.member_org_id, row.member_org_name) {
+            (Some(id), Some(name)) => Some(OptionData { id, name }),
+            _ => None,
+        };
+        paths.push(AccessGrantPath {
+            role: OptionData {
+                id: row.role_id,
+                name: row.role_name,
+            },
+            access: OptionData {
+                id: row.access_id,
+                name: row.access_name,
+            },
+            group,
+            org,
+            member_org,
+            via_admin: row.access_id != access_id,
+            bindings,
+        });
+    }
+
+    (paths, revoke)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{build_grant_paths, GrantRow};
+    use crate::user::GrantBinding;
+
+    fn row(role_id: i32, access_id: i32) -> GrantRow {
+        GrantRow {
+            role_id,
+            role_name: format!("role_{role_id}"),
+            access_id,
+            access_name: format!("access_{access_id}"),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_build_grant_paths() {
+        let direct = row(1, 10);
+        let group = GrantRow {
+            group_id: Some(3),
+            group_name: Some("group_3".to_string()),
+            ..row(2, 10)
+        };
+        let org = GrantRow {
+            org_id: Some(4),
+            org_name: Some("org_4".to_string()),
+            member_org_id: Some(5),
+            member_org_name: Some("org_5".to_string()),
+            ..row(2, 1)
+        };
+        let same_group = GrantRow {
+            group_id: Some(3),
+            group_name: Some("group_3".to_string()),
+            ..row(6, 10)
+        };
+
+        let (paths, revoke) = build_grant_paths(7, 10, vec![direct, group, org, same_group]);
+        assert_eq!(paths.len(), 4);
+        assert!(!paths[0].via_admin);
+        assert!(paths[2].via_admin);
+        assert_eq!(
+            paths[2].bindings[0],
+            GrantBinding::OrgMember {
+                org_id: 5,
+                user_id: 7
+            }
+        );
+        // 同一用户组的两条链路只需退出一次用户组
+        assert_eq!(
+            revoke,
+            vec![
+                GrantBinding::UserRole {
+                    user_id: 7,
+                    role_id: 1
+                },
+                GrantBinding::GroupMember {
+                    group_id: 3,
+                    user_id: 7
+                },
+                GrantBinding::OrgMember {
+                    org_id: 5,
+                    user_id: 7
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,159 @@
+use super::{
+    check_user_by_user_id, user_role_service::get_user_role_ids, MeRes, OptionData, UserProfile,
+};
+use crate::{
+    access::AccessMapItem,
+    cache::{cache, smembers_ids, CacheStore},
+    entity::{access_entity::AccessEntity, role_entity::RoleEntity},
+    response::{MyError, ResponseBody},
+    user::auth_service::get_login_auth,
+    util::{
+        common::{get_jwt_from_req, hmget_by_ids, RedisKeys},
+        structs::Status,
+    },
+    RB,
+};
+use actix_web::{get, HttpRequest, Responder};
+use rbs::to_value;
+use serde::Deserialize;
+
+/// 当前用户的资料、有效角色、权限名称及权限值, 优先读取缓存
+#[utoipa::path(
+    tag = "auth",
+    responses( (status = 200) )
+)]
+#[get("/me")]
+pub async fn get_me(req: HttpRequest) -> Result<impl Responder, MyError> {
+    let login = get_jwt_from_req(&req);
+    let tenant_id = login.tenant_id;
+    let user = check_user_by_user_id(login.id, tenant_id)
+        .await
+        .ok_or(MyError::UserNotExist)?;
+
+    let role_ids = get_cached_user_role_ids(login.id, tenant_id).await;
+    let roles = get_role_options(&role_ids, tenant_id).await;
+    // 只取仍然有效的角色的权限
+    let role_ids: Vec<i32> = roles.iter().map(|val| val.id).collect();
+    let access_ids = get_cached_role_access_ids(&role_ids, tenant_id).await?;
+    let access = get_access_options(&access_ids, tenant_id).await;
+
+    Ok(ResponseBody::default(Some(MeRes {
+        user: UserProfile::from(user),
+        roles,
+        access,
+        auth: get_login_auth(&login).await,
+    })))
+}
+
+async fn get_cached_user_role_ids(user_id: i32, tenant_id: i32) -> Vec<i32> {
+    let key = RedisKeys::UserRoles.id_key(tenant_id, user_id);
+    if cache().exists(&key).await {
+        return smembers_ids(&key).await;
+    }
+    get_user_role_ids(user_id).await
+}
+
+async fn get_cached_role_access_ids(role_ids: &[i32], tenant_id: i32) -> Result<Vec<i32>, MyError> {
+    let mut access_ids: Vec<i32> = vec![];
+    let mut missing: Vec<i32> = vec![];
+    for role_id in role_ids {
+        let key = RedisKeys::RoleAccess.id_key(tenant_id, *role_id);
+        if cache().exists(&key).await {
+            access_ids.extend(smembers_ids(&key).await);
+        } else {
+            missing.push(*role_id);
+        }
+    }
+    if !missing.is_empty() {
+        let ex = RB.acquire().await.map_err(|error| {
+            log::error!(" {} {error}", MyError::QueryRoleAccessError);
+            MyError::QueryRoleAccessError
+        })?;
+        let list: Vec<AccessIdRes> = ex
+            .query_decode(&role_access_sql(&missing), vec![to_value!(tenant_id)])
+            .await
+            .map_err(|error| {
+                log::error!(" {} {error}", MyError::QueryRoleAccessError);
+                MyError::QueryRoleAccessError
+            })?;
+        access_ids.extend(list.into_iter().map(|val| val.access_id));
+    }
+    access_ids.sort();
+    access_ids.dedup();
+    Ok(access_ids)
+}
+
+#[derive(Deserialize)]
+struct AccessIdRes {
+    access_id: i32,
+}
+
+/// 缓存未命中时从数据库读取角色的有效权限, 限定在当前租户; 只拼接整数id
+fn role_access_sql(role_ids: &[i32]) -> String {
+    let ids: Vec<String> = role_ids.iter().map(|id| id.to_string()).collect();
+    format!(
+        "select distinct role_access.access_id from role_access \
+        inner join access on access.id = role_access.access_id and access.status = 1 \
+        where role_access.role_id in ({}) and role_access.tenant_id = ?",
+        ids.join(",")
+    )
+}
+
+async fn get_role_options(role_ids: &[i32], tenant_id: i32) -> Vec<OptionData> {
+    let ex = RB.acquire().await.expect("msg");
+    let mut roles: Vec<OptionData> = vec![];
+    let cache_list = hmget_by_ids(RedisKeys::RoleInfo, tenant_id, role_ids).await;
+    for (id, cache_info) in role_ids.iter().zip(cache_list) {
+        match cache_info.and_then(|info| serde_json::from_str::<OptionData>(&info).ok()) {
+            Some(role) => roles.push(role),
+            None => {
+                let db_role = RoleEntity::select_by_id(&ex, *id, tenant_id)
+                    .await
+                    .expect("角色查询失败");
+                if let Some(role) = db_role {
+                    roles.push(OptionData::default(&role.name, *id));
+                }
+            }
+        }
+    }
+    roles
+}
+
+/// ADMIN 权限属于默认租户, 缓存中找不到时按id从数据库补充
+async fn get_access_options(access_ids: &[i32], tenant_id: i32) -> Vec<OptionData> {
+    let mut access: Vec<OptionData> = vec![];
+    let mut missing: Vec<i32> = vec![];
+    let cache_list = hmget_by_ids(RedisKeys::AccessMap, tenant_id, access_ids).await;
+    for (id, cache_info) in access_ids.iter().zip(cache_list) {
+        match cache_info.and_then(|info| serde_json::from_str::<AccessMapItem>(&info).ok()) {
+            Some(item) => access.push(OptionData::default(&item.name, item.id)),
+            None => missing.push(*id),
+        }
+    }
+    if !missing.is_empty() {
+        let ex = RB.acquire().await.expect("msg");
+        let list: Vec<AccessEntity> = AccessEntity::select_in_column(&ex, "id", &missing)
+            .await
+            .expect("权限查询失败");
+        access.extend(
+            list.into_iter()
+                .filter(|val| val.status == Status::ACTIVE as i8)
+                .map(|val| OptionData::default(&val.name, val.id.unwrap_or_default())),
+        );
+    }
+    access
+}
+
+#[cfg(test)]
+mod test {
+    use super::role_access_sql;
+
+    #[test]
+    fn test_role_access_sql() {
+        let sql = role_access_sql(&[3, 5]);
+        assert!(sql.contains("role_access.role_id in (3,5)"));
+        // 只取当前租户的绑定和启用的权限
+        assert!(sql.contains("role_access.tenant_id = ?"));
+        assert!(sql.contains("access.status = 1"));
+    }
+}
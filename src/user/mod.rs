@@ -1,14 +1,23 @@
-use crate::{entity::user_entity::UserEntity, RB};
+use crate::{
+    entity::user_entity::UserEntity,
+    util::{access_guard::RouteAccess, common::DEFAULT_TENANT_ID, structs::UserType},
+    RB,
+};
 use redis_macros::{FromRedisValue, ToRedisArgs};
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 use utoipa_actix_web::service_config::ServiceConfig;
 
+mod casbin_service;
+mod explain_service;
+mod me_service;
 mod obs;
 mod user_service;
 
 pub mod admin;
 pub mod auth_service;
+pub mod bind_simulate;
+pub mod casbin;
 pub mod user_role_service;
 
 pub fn configure() -> impl FnOnce(&mut ServiceConfig) {
@@ -16,6 +25,7 @@ pub fn configure() -> impl FnOnce(&mut ServiceConfig) {
         config.service(user_service::create_user);
         config.service(user_service::get_user_list);
         config.service(user_service::bind_role);
+        config.service(user_service::bind_role_dry_run);
         config.service(user_service::get_user_option);
 
         config.service(user_service::update_user_by_id);
@@ -25,11 +35,31 @@ pub fn configure() -> impl FnOnce(&mut ServiceConfig) {
     }
 }
 
+/// 接口所需权限, 由 `access_guard` 中间件校验
+pub const ROUTE_ACCESS: &[RouteAccess] = &[
+    RouteAccess::new("POST", "/api/user/create_user", "USER_CREATE"),
+    RouteAccess::new("POST", "/api/user/get_user_list", "USER_LIST"),
+    RouteAccess::new("GET", "/api/user/get_user_option", "USER_LIST"),
+    RouteAccess::new("GET", "/api/user/{id}", "USER_VIEW"),
+    RouteAccess::new("GET", "/api/user/user_binds/{id}", "USER_VIEW"),
+    RouteAccess::new("POST", "/api/user/update_user/{id}", "USER_UPDATE"),
+    RouteAccess::new("DELETE", "/api/user/delete_user/{id}", "USER_DELETE"),
+    RouteAccess::new("POST", "/api/user/bind_role", "USER_BIND_ROLE"),
+    RouteAccess::new("POST", "/api/user/bind_role_dry_run", "USER_BIND_ROLE"),
+    RouteAccess::new("POST", "/api/auth/explain", "USER_VIEW"),
+    RouteAccess::new("GET", "/api/auth/casbin", "RBAC_EXPORT"),
+];
+
 pub fn auth_configure() -> impl FnOnce(&mut ServiceConfig) {
     |config: &mut ServiceConfig| {
         config.service(auth_service::login);
         config.service(auth_service::logout);
         config.service(auth_service::get_user_permission);
+        config.service(auth_service::check_permission);
+        config.service(auth_service::get_route_access);
+        config.service(explain_service::explain_access);
+        config.service(casbin_service::get_casbin_policy);
+        config.service(me_service::get_me);
     }
 }
 
@@ -76,6 +106,117 @@ pub struct RedisLoginData {
     pub last_login_time: i64,
     pub name: String,
     pub id: i32,
+    #[serde(default)]
+    pub tenant_id: i32,
+    #[serde(default)]
+    pub user_type: i16,
+}
+
+impl RedisLoginData {
+    /// 默认租户下的管理员为超级管理员, 可以跨租户操作
+    pub fn is_super_admin(&self) -> bool {
+        self.tenant_id == DEFAULT_TENANT_ID && self.user_type == UserType::ADMIN as i16
+    }
+}
+
+/// 权限校验, resource 为参与策略求值的资源属性
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct AuthCheckData {
+    pub access: Option<String>,
+    #[schema(value_type = Option<Object>)]
+    pub resource: Option<serde_json::Map<String, serde_json::Value>>,
+    /// Casbin 风格的校验, obj 和 act 同时存在时生效, sub 默认为当前用户
+    pub sub: Option<String>,
+    pub obj: Option<String>,
+    pub act: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct AuthCheckRes {
+    pub pass: bool,
+    pub policy_id: Option<i32>, // 命中的策略id
+}
+
+/// 用户资料, 不含密码
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct UserProfile {
+    pub id: i32,
+    pub name: String,
+    pub phone: String,
+    pub picture: Option<String>,
+    pub introduce: Option<String>,
+    pub user_type: i16,
+    pub tenant_id: i32,
+    pub create_time: String,
+}
+
+impl From<UserEntity> for UserProfile {
+    fn from(val: UserEntity) -> Self {
+        Self {
+            id: val.id.unwrap_or_default(),
+            name: val.name,
+            phone: val.phone,
+            picture: val.picture,
+            introduce: val.introduce,
+            user_type: val.user_type,
+            tenant_id: val.tenant_id,
+            create_time: val.create_time,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MeRes {
+    pub user: UserProfile,
+    pub roles: Vec<OptionData>,
+    pub access: Vec<OptionData>, // 权限名称
+    pub auth: u64,               // 权限值
+}
+
+/// Casbin 模型及 csv 格式的策略
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CasbinExportRes {
+    pub model: String,
+    pub policy: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct AccessExplainData {
+    pub user_id: i32,
+    pub access_id: i32,
+}
+
+/// 授权链路上的一条绑定关系
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum GrantBinding {
+    UserRole { user_id: i32, role_id: i32 },
+    RoleAccess { role_id: i32, access_id: i32 },
+    GroupMember { group_id: i32, user_id: i32 },
+    GroupRole { group_id: i32, role_id: i32 },
+    OrgMember { org_id: i32, user_id: i32 },
+    OrgRole { org_id: i32, role_id: i32 },
+}
+
+/// 一条授权链路, 例如 用户 -> 用户组 -> 角色 -> 权限
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct AccessGrantPath {
+    pub role: OptionData,
+    pub access: OptionData,
+    pub group: Option<OptionData>,
+    pub org: Option<OptionData>,        // 绑定角色的部门
+    pub member_org: Option<OptionData>, // 用户所在的部门, 可能是 org 的子部门
+    pub via_admin: bool,                // 通过 ADMIN 权限获得
+    pub bindings: Vec<GrantBinding>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct AccessExplainRes {
+    pub user: OptionData,
+    pub access: OptionData,
+    pub granted: bool,
+    pub paths: Vec<AccessGrantPath>,
+    pub revoke: Vec<GrantBinding>, // 只影响该用户的最少解绑项
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
@@ -110,10 +251,10 @@ impl OptionData {
     }
 }
 
-pub async fn check_user_by_user_id(user_id: i32) -> Option<UserEntity> {
+pub async fn check_user_by_user_id(user_id: i32, tenant_id: i32) -> Option<UserEntity> {
     // check in redis
     let ex_db = RB.acquire().await.expect("msg");
-    let db_user: Option<UserEntity> = UserEntity::select_by_id(&ex_db, user_id.clone().into())
+    let db_user: Option<UserEntity> = UserEntity::select_by_id(&ex_db, user_id, tenant_id)
         .await
         .expect("查询用户失败");
 
@@ -1,11 +1,10 @@
 use crate::{
+    cache::{cache, CacheStore},
     response::{MyError, ResponseBody},
     REDIS_KEY,
 };
 use actix_web::{get, Responder};
-use redis::AsyncCommands;
 use reqwest::header;
-use rs_service_util::redis_conn;
 use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize)]
@@ -27,11 +26,12 @@ struct Credential {
 )]
 #[get("/get_keys")]
 pub async fn get_keys() -> Result<impl Responder, MyError> {
-    let mut conn = redis_conn!().await;
     let key = format!("{}_obs_ak_sk", REDIS_KEY.to_string());
-    let cache_token: Option<String> = conn.get(&key).await.expect("get cache_token error");
-    if let Some(token) = cache_token {
-        let res: AkSkRes = serde_json::from_str(&token).expect("token perse error");
+    let cache_token: Option<AkSkRes> = cache()
+        .get(&key)
+        .await
+        .and_then(|token| serde_json::from_str(&token).ok());
+    if let Some(res) = cache_token {
         return Ok(ResponseBody::default(Some(res)));
     }
 
@@ -81,10 +81,7 @@ pub async fn get_keys() -> Result<impl Responder, MyError> {
             let json = res.text().await.map_err(|_| MyError::ObsAkSkError)?;
             let res: AkSkRes = serde_json::from_str(&json).expect("token perse error");
 
-            let _ = conn
-                .set_ex(key, json, 15 * 60)
-                .await
-                .map_err(|_| MyError::CacheObsAkSkError)?;
+            cache().set_ex(&key, json, 15 * 60).await;
 
             return Ok(ResponseBody::default(Some(res)));
         }
@@ -1,28 +1,77 @@
-use redis::AsyncCommands;
-use rs_service_util::{redis_conn, RedisLoginData};
+use rbs::to_value;
+use serde::{Deserialize, Serialize};
 
+use crate::cache::{cache, CacheStore};
 use crate::entity::user_role_entity::UserRoleEntity;
 use crate::response::MyError;
 use crate::role::check_role_by_id;
 use crate::user::auth_service::get_user_access_val;
-use crate::util::common::RedisKeys;
-use crate::REDIS_KEY;
+use crate::user::{OptionData, RedisLoginData};
+use crate::util::common::{cache_ids_exist, diff_ids, RedisKeys};
+use crate::util::sync_opt::replace_set;
+use crate::RB;
+
+macro_rules! inherited_role_ids_sql {
+    () => {
+        "select group_role.role_id from user_group_member \
+        inner join user_group on user_group.id = user_group_member.group_id and user_group.status = 1 \
+        inner join group_role on group_role.group_id = user_group_member.group_id \
+        where user_group_member.user_id=? \
+        union select org_role.role_id from user_org \
+        inner join org_unit on org_unit.id = user_org.org_id and org_unit.status = 1 \
+        inner join org_role on org_unit.path like concat('%/', org_role.org_id, '/%') \
+        where user_org.user_id=?"
+    };
+}
+
+/// 用户继承的角色id: 所在用户组绑定 + 所在部门及其上级部门绑定, 2 个参数均为用户id
+pub const INHERITED_ROLE_IDS_SQL: &str = inherited_role_ids_sql!();
+
+/// 用户的所有有效角色id: 直接绑定 + 继承, 参数由 `user_role_ids_args` 生成
+pub const USER_ROLE_IDS_SQL: &str = concat!(
+    "select user_role.role_id from user_role where user_role.user_id=? union ",
+    inherited_role_ids_sql!()
+);
+
+/// 有效角色包含指定角色的用户, 3 个参数均为角色id
+pub const ROLE_USERS_SQL: &str =
+    "select user.id, user.name from user where user.status = 1 and user.id in (\
+    select user_role.user_id from user_role where user_role.role_id=? \
+    union select user_group_member.user_id from user_group_member \
+    inner join user_group on user_group.id = user_group_member.group_id and user_group.status = 1 \
+    inner join group_role on group_role.group_id = user_group_member.group_id \
+    where group_role.role_id=? \
+    union select user_org.user_id from user_org \
+    inner join org_unit on org_unit.id = user_org.org_id and org_unit.status = 1 \
+    inner join org_role on org_unit.path like concat('%/', org_role.org_id, '/%') \
+    where org_role.role_id=?)";
+
+/// 全部用户的有效角色关系, 字段同 user_role 表
+pub const ALL_USER_ROLE_SQL: &str = "select user_role.user_id, user_role.role_id, user_role.tenant_id from user_role \
+    union select user_group_member.user_id, group_role.role_id, group_role.tenant_id from user_group_member \
+    inner join user_group on user_group.id = user_group_member.group_id and user_group.status = 1 \
+    inner join group_role on group_role.group_id = user_group_member.group_id \
+    union select user_org.user_id, org_role.role_id, org_role.tenant_id from user_org \
+    inner join org_unit on org_unit.id = user_org.org_id and org_unit.status = 1 \
+    inner join org_role on org_unit.path like concat('%/', org_role.org_id, '/%')";
+
+pub fn user_role_ids_args(user_id: i32) -> Vec<rbs::Value> {
+    vec![to_value!(user_id); 3]
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct RoleIdRes {
+    role_id: i32,
+}
 
 ///检查角色是否存在于cache & db
-pub async fn check_role_exists(role_ids: &Vec<i32>) -> Option<bool> {
+pub async fn check_role_exists(role_ids: &Vec<i32>, tenant_id: i32) -> Option<bool> {
     //  check in cache
-    let mut conn = redis_conn!().await;
-    for id in role_ids {
-        let in_ids_cache: bool = conn
-            .sismember(RedisKeys::RoleIds.to_string(), id)
-            .await
-            .expect("msg");
-        let in_info_cache: bool = conn
-            .hexists(RedisKeys::RoleInfo.to_string(), id)
-            .await
-            .expect("msg");
-        if !in_ids_cache && !in_info_cache {
-            let db_role = check_role_by_id(id.clone()).await;
+    let in_cache =
+        cache_ids_exist(RedisKeys::RoleIds, RedisKeys::RoleInfo, tenant_id, role_ids).await;
+    for (id, in_cache) in role_ids.iter().zip(in_cache) {
+        if !in_cache {
+            let db_role = check_role_by_id(id.clone(), tenant_id).await;
             if db_role.is_none() {
                 return None;
             }
@@ -32,85 +81,96 @@ pub async fn check_role_exists(role_ids: &Vec<i32>) -> Option<bool> {
     Some(true)
 }
 
-/// role_ids    cache_id
+/// role_ids    bind_ids
 ///
 /// [1,2]       [1,2,3,4]    remove 3,4
 ///
 /// [1,2 ,5]    [1,2,3,4]    remove 3,4 add 5
 ///
-///
+/// user_roles 缓存包含用户组和部门继承的角色, 这里只对比直接绑定的角色
 pub async fn check_user_role_bind(user_id: &i32, role_ids: &Vec<i32>) -> (Vec<i32>, Vec<i32>) {
-    let mut conn = redis_conn!().await;
-    let key = format!("{}_{}", RedisKeys::UserRoles.to_string(), user_id);
-    let cache_ids: Vec<i32> = conn.smembers(key).await.expect("msg");
-    log::info!("cache_user bind role ids {cache_ids:?}");
-    if cache_ids.is_empty() {
-        return (role_ids.clone(), vec![]);
-    }
-    // 查看交集
-    let mut add_ids: Vec<i32> = vec![];
-    let mut sub_ids: Vec<i32> = vec![];
-
-    role_ids.iter().for_each(|id| {
-        // 新加的角色不存在 过滤出来
-        let is_contain = cache_ids.iter().find(|c_id| **c_id == *id);
-        if is_contain.is_none() {
-            add_ids.push(*id);
-        }
-    });
+    let ex = RB.acquire().await.expect("msg");
+    let binds: Vec<UserRoleEntity> = UserRoleEntity::select_by_column(&ex, "user_id", *user_id)
+        .await
+        .expect("查询用户角色失败");
+    let bind_ids: Vec<i32> = binds.into_iter().map(|val| val.role_id).collect();
+    log::info!("user bind role ids {bind_ids:?}");
+    diff_ids(role_ids, &bind_ids)
+}
 
-    cache_ids.iter().for_each(|id| {
-        // 新加的角色不存在 过滤出来
-        let is_contain = role_ids.iter().find(|c_id| **c_id == *id);
-        if is_contain.is_none() {
-            sub_ids.push(*id);
-        }
-    });
+/// 查询用户继承的角色id
+pub async fn get_inherited_role_ids(user_id: i32) -> Vec<i32> {
+    let ex = RB.acquire().await.expect("msg");
+    let list: Vec<RoleIdRes> = ex
+        .query_decode(INHERITED_ROLE_IDS_SQL, vec![to_value!(user_id); 2])
+        .await
+        .expect("查询用户角色失败");
+    list.into_iter().map(|val| val.role_id).collect()
+}
+
+/// 查询拥有该角色的所有用户, 包括通过用户组和部门继承的
+pub async fn get_role_users(role_id: i32) -> Vec<OptionData> {
+    let ex = RB.acquire().await.expect("msg");
+    ex.query_decode(ROLE_USERS_SQL, vec![to_value!(role_id); 3])
+        .await
+        .expect("查询角色用户失败")
+}
 
-    (add_ids, sub_ids)
+/// 查询用户的所有有效角色id
+pub async fn get_user_role_ids(user_id: i32) -> Vec<i32> {
+    let ex = RB.acquire().await.expect("msg");
+    let list: Vec<RoleIdRes> = ex
+        .query_decode(USER_ROLE_IDS_SQL, user_role_ids_args(user_id))
+        .await
+        .expect("查询用户角色失败");
+    list.into_iter().map(|val| val.role_id).collect()
+}
+
+/// 角色来源(直接绑定/用户组/部门)变化后, 重建用户的角色缓存并刷新登录权限值
+pub async fn refresh_user_roles(user_id: i32, tenant_id: i32) -> Result<u64, MyError> {
+    let role_ids = get_user_role_ids(user_id).await;
+    replace_set(RedisKeys::UserRoles.id_key(tenant_id, user_id), role_ids).await;
+    sync_user_auth(user_id, tenant_id).await
 }
 
-pub async fn bind_user_role(user_id: &i32, role_ids: &Vec<i32>) -> Vec<UserRoleEntity> {
-    let mut conn = redis_conn!().await;
-    let key = format!("{}_{}", RedisKeys::UserRoles.to_string(), user_id);
+/// 待写入的用户角色关系, 缓存在事务提交后由 `refresh_user_roles` 重建
+pub fn user_role_tabs(user_id: &i32, role_ids: &[i32], tenant_id: i32) -> Vec<UserRoleEntity> {
     let mut tabs: Vec<UserRoleEntity> = vec![];
     for id in role_ids {
-        let _: () = conn.sadd(key.clone(), id).await.expect("msg");
         tabs.push(UserRoleEntity {
             id: None,
             user_id: *user_id,
             role_id: *id,
+            tenant_id,
         });
     }
 
     tabs
 }
 
-pub async fn unbind_role_from_cache(user_id: &i32, role_ids: &Vec<i32>) {
-    let mut conn = redis_conn!().await;
-    let key = format!("{}_{}", RedisKeys::UserRoles.to_string(), user_id);
-    let _: () = conn
-        .srem(key.clone(), role_ids.to_vec())
-        .await
-        .expect("msg");
-}
-
-pub async fn sync_user_auth(name: String) -> Result<u64, MyError> {
-    let key = format!("{}_{}", REDIS_KEY.to_string(), name);
-    let mut conn = redis_conn!().await;
-    let cache_info: Option<String> = conn.get(&key).await.expect("msg");
+pub async fn sync_user_auth(user_id: i32, tenant_id: i32) -> Result<u64, MyError> {
+    let key = RedisKeys::Login.id_key(tenant_id, user_id);
+    let cache_info: Option<String> = cache().get(&key).await;
 
     log::info!("key {key}");
     log::info!("cache_info {cache_info:#?}");
 
     if let Some(info) = cache_info {
-        let mut login_info: RedisLoginData = serde_json::from_str(&info).expect("msg");
+        // 登录缓存的结构变化后无法解析, 删除后由用户重新登录写入
+        let Ok(mut login_info) = serde_json::from_str::<RedisLoginData>(&info) else {
+            log::warn!("登录缓存 {key} 解析失败, 已删除");
+            cache().del(&key).await;
+            return Ok(0);
+        };
         let new_auth = get_user_access_val(login_info.id).await;
         login_info.auth = new_auth;
 
-        let ttl: u64 = conn.ttl(&key).await.expect("msg");
-        let json = serde_json::to_string(&login_info).unwrap();
-        let _: () = conn.set_ex(key, json, ttl).await.expect("msg");
+        // 保持原有的过期时间, 期间已过期则不再写入
+        let ttl = cache().ttl(&key).await;
+        if ttl > 0 {
+            let json = serde_json::to_string(&login_info).unwrap();
+            cache().set_ex(&key, json, ttl as u64).await;
+        }
         return Ok(new_auth);
     }
     Ok(0)
@@ -1,10 +1,19 @@
 use super::{BindRoleData, UserCreateData, UserListQuery, UserUpdateData};
+use crate::cache::{cache, smembers_ids, CacheOp, CacheStore};
+use crate::constraint::check_role_change;
 use crate::entity::role_entity::RoleEntity;
 use crate::response::MyError;
+use crate::role::get_adm_role_id;
+use crate::user::auth_service::{check_adm_bind, get_login_auth, is_adm_auth};
+use crate::user::bind_simulate::{
+    collect_access, gen_user_change, get_role_access_map, simulate_user_roles, BindSimRes,
+};
 use crate::user::user_role_service::{
-    bind_user_role, check_role_exists, check_user_role_bind, sync_user_auth, unbind_role_from_cache,
+    check_role_exists, check_user_role_bind, refresh_user_roles, user_role_ids_args,
+    user_role_tabs, USER_ROLE_IDS_SQL,
 };
-use crate::util::common::{rds_str_to_list, RedisKeys};
+use crate::util::common::{get_jwt_from_req, get_tenant_id, rds_str_to_list, RedisKeys};
+use crate::util::data_scope::{append_scope_filter, get_user_data_scope, scope_table};
 use crate::util::sync_opt::DelOptData;
 use crate::{
     entity::{user_entity::UserEntity, user_role_entity::UserRoleEntity},
@@ -12,27 +21,32 @@ use crate::{
     user::{check_user_by_user_id, OptionData},
     util::{
         common::{check_phone, get_transaction_tx},
-        structs::Status,
+        outbox::add_events,
+        structs::{OutboxEvent, Status, UserType},
         sync_opt::{self, SyncOptData},
     },
     RB,
 };
-use actix_web::{delete, get, post, web, Responder};
+use actix_web::{delete, get, post, web, HttpRequest, Responder};
 use rbs::to_value;
-use redis::AsyncCommands;
-use rs_service_util::redis_conn;
 use rs_service_util::sql_tool::{SqlTool, SqlToolPageData};
 use rs_service_util::time::get_current_time_fmt;
+use std::collections::HashMap;
 #[utoipa::path(
     tag = "user",
     responses( (status = 200) )
 )]
 #[post("/create_user")]
-pub async fn create_user(req_data: web::Json<UserCreateData>) -> Result<impl Responder, MyError> {
+pub async fn create_user(
+    req_data: web::Json<UserCreateData>,
+    req: HttpRequest,
+) -> Result<impl Responder, MyError> {
+    let tenant_id = get_tenant_id(&req);
     let phone_check_res = check_phone(&req_data.phone);
     if !phone_check_res {
         return Err(MyError::PhoneIsError);
     }
+    check_user_type(&req, None, req_data.user_type).await?;
 
     let insert_user = UserEntity {
         id: None,
@@ -45,6 +59,7 @@ pub async fn create_user(req_data: web::Json<UserCreateData>) -> Result<impl Res
         introduce: req_data.introduce.clone(),
         user_type: req_data.user_type,
         status: Status::ACTIVE as i16,
+        tenant_id,
     };
 
     let tx = get_transaction_tx().await.unwrap();
@@ -64,6 +79,7 @@ pub async fn create_user(req_data: web::Json<UserCreateData>) -> Result<impl Res
             sync_opt::sync(SyncOptData::default(
                 RedisKeys::UserIds,
                 RedisKeys::UserInfo,
+                tenant_id,
                 opt.id,
                 opt,
             ))
@@ -79,15 +95,23 @@ pub async fn create_user(req_data: web::Json<UserCreateData>) -> Result<impl Res
     responses( (status = 200) )
 )]
 #[post("/get_user_list")]
-pub async fn get_user_list(req_data: web::Json<UserListQuery>) -> impl Responder {
+pub async fn get_user_list(req_data: web::Json<UserListQuery>, req: HttpRequest) -> impl Responder {
+    let tenant_id = get_tenant_id(&req);
+    let jwt_user = get_jwt_from_req(&req);
+    let scope = get_user_data_scope(&jwt_user).await;
     let ex_db = RB.acquire().await.expect("msg");
-    let mut tool = SqlTool::init("select * from user", "order by create_time desc");
+    let table = scope_table("user", &scope, "id", &jwt_user);
+    let mut tool = SqlTool::init(
+        &format!("select * from {table}"),
+        "order by create_time desc",
+    );
     if let Some(name) = &req_data.name {
         tool.append_sql_filed("name", to_value!(name));
     }
     if let Some(user_type) = req_data.user_type {
         tool.append_sql_filed("user_type", to_value!(user_type));
     }
+    append_scope_filter(&mut tool, &scope, "id", &jwt_user, tenant_id);
 
     tool.append_sql_filed("status", to_value!(1));
     let page_sql = tool.gen_page_sql(req_data.page_no, req_data.take);
@@ -98,7 +122,7 @@ pub async fn get_user_list(req_data: web::Json<UserListQuery>) -> impl Responder
         .expect("msg");
     let conf: SqlToolPageData<UserEntity> = SqlToolPageData {
         ex_db,
-        table: "user".to_string(),
+        table,
         records: db_res,
         page_no: req_data.page_no as u64,
         page_size: req_data.take as u64,
@@ -114,10 +138,14 @@ pub async fn get_user_list(req_data: web::Json<UserListQuery>) -> impl Responder
     responses( (status = 200) )
 )]
 #[get("/{id}")]
-pub async fn get_user_by_id(id: web::Path<i32>) -> Result<impl Responder, MyError> {
+pub async fn get_user_by_id(
+    id: web::Path<i32>,
+    req: HttpRequest,
+) -> Result<impl Responder, MyError> {
+    let tenant_id = get_tenant_id(&req);
     let ex_db = RB.acquire().await.expect("msg");
     let user_id = id.into_inner();
-    let db_res: Option<UserEntity> = UserEntity::select_by_id(&ex_db, user_id)
+    let db_res: Option<UserEntity> = UserEntity::select_by_id(&ex_db, user_id, tenant_id)
         .await
         .expect("查询用户失败");
 
@@ -133,7 +161,9 @@ pub async fn get_user_by_id(id: web::Path<i32>) -> Result<impl Responder, MyErro
 pub async fn update_user_by_id(
     id: web::Path<i32>,
     req_data: web::Json<UserUpdateData>,
+    req: HttpRequest,
 ) -> Result<impl Responder, MyError> {
+    let tenant_id = get_tenant_id(&req);
     if let Some(new_phone) = &req_data.phone {
         let phone_check_res = check_phone(new_phone);
         if !phone_check_res {
@@ -144,7 +174,7 @@ pub async fn update_user_by_id(
     let tx = get_transaction_tx().await.unwrap();
 
     let user_id = id.into_inner();
-    let db_res: Option<UserEntity> = UserEntity::select_by_id(&tx, user_id)
+    let db_res: Option<UserEntity> = UserEntity::select_by_id(&tx, user_id, tenant_id)
         .await
         .expect("查询用户失败");
 
@@ -154,6 +184,9 @@ pub async fn update_user_by_id(
         }
         Some(mut db_user) => {
             log::debug!("db_user {db_user:?}");
+            if let Some(user_type) = req_data.user_type {
+                check_user_type(&req, Some(db_user.user_type), user_type).await?;
+            }
             db_user.update_time = get_current_time_fmt();
             db_user.introduce = req_data.introduce.clone();
             db_user.name = req_data.name.clone().unwrap_or(db_user.name);
@@ -175,30 +208,42 @@ pub async fn update_user_by_id(
                 return Err(MyError::UpdateUserError);
             }
             let opt = OptionData::default(&db_user.name, db_user.id.clone().expect("msg"));
-            let mut conn = redis_conn!().await;
-            let _: () = conn
-                .hset(
-                    RedisKeys::UserInfo.to_string(),
+            cache()
+                .exec(vec![CacheOp::HSet(
+                    RedisKeys::UserInfo.key(tenant_id),
+                    db_user.id.clone().expect("msg").to_string(),
                     serde_json::to_string(&opt).expect("msg"),
-                    db_user.id.clone().expect("msg"),
-                )
-                .await
-                .expect("msg");
+                )])
+                .await;
         }
     }
     Ok(ResponseBody::success("更新用户成功"))
 }
 
+/// 管理员类型决定是否为超级管理员, 只有持有 ADMIN 权限的调用者可以设置或取消
+async fn check_user_type(req: &HttpRequest, old: Option<i16>, new: i16) -> Result<(), MyError> {
+    let admin = UserType::ADMIN as i16;
+    if old == Some(new) || (old != Some(admin) && new != admin) {
+        return Ok(());
+    }
+    let login = get_jwt_from_req(req);
+    if !is_adm_auth(get_login_auth(&login).await).await {
+        return Err(MyError::UserTypeForbidden);
+    }
+    Ok(())
+}
+
 #[utoipa::path(
     tag = "user",
     params(("id", description = "user id") ),
     responses( (status = 200) )
 )]
 #[delete("/delete_user/{id}")]
-pub async fn delete_user(id: web::Path<i32>) -> Result<impl Responder, MyError> {
+pub async fn delete_user(id: web::Path<i32>, req: HttpRequest) -> Result<impl Responder, MyError> {
+    let tenant_id = get_tenant_id(&req);
     let tx = get_transaction_tx().await.unwrap();
     let user_id = id.into_inner();
-    let db_res: Option<UserEntity> = UserEntity::select_by_id(&tx, user_id)
+    let db_res: Option<UserEntity> = UserEntity::select_by_id(&tx, user_id, tenant_id)
         .await
         .expect("查询用户失败");
 
@@ -224,8 +269,9 @@ pub async fn delete_user(id: web::Path<i32>) -> Result<impl Responder, MyError>
     }
 
     sync_opt::del(DelOptData::default(
-        RedisKeys::RoleIds,
-        RedisKeys::RoleInfo,
+        RedisKeys::UserIds,
+        RedisKeys::UserInfo,
+        tenant_id,
         vec![user_id],
     ))
     .await;
@@ -238,9 +284,10 @@ pub async fn delete_user(id: web::Path<i32>) -> Result<impl Responder, MyError>
     responses( (status = 200) )
   )]
 #[get("/user_binds/{id}")]
-pub async fn get_role_binds(parma: web::Path<i32>) -> impl Responder {
+pub async fn get_role_binds(parma: web::Path<i32>, req: HttpRequest) -> impl Responder {
+    let tenant_id = get_tenant_id(&req);
     let id = parma.into_inner();
-    let db_user = check_user_by_user_id(id).await;
+    let db_user = check_user_by_user_id(id, tenant_id).await;
     if db_user.is_none() {
         return ResponseBody {
             code: 500,
@@ -248,18 +295,24 @@ pub async fn get_role_binds(parma: web::Path<i32>) -> impl Responder {
             data: None,
         };
     }
-    let mut conn = redis_conn!().await;
-    let key: String = format!("{}_{}", RedisKeys::UserRoles.to_string(), id);
-    let cache_ids: Vec<i32> = conn.smembers(key).await.expect("msg");
+    let key: String = RedisKeys::UserRoles.id_key(tenant_id, id);
+    let cache_ids: Vec<i32> = smembers_ids(&key).await;
 
     let ex = RB.acquire().await.expect("msg");
 
     let roles = if cache_ids.is_empty() {
-        let roles:Vec<RoleEntity> = ex.query_decode("select role.* from user_role left join role on user_role.role_id = role.id where user_id=? and role.status = 1;",vec![to_value!(id)]).await.expect("获取用户绑定角色失败");
-        let key = format!("{}_{}", RedisKeys::UserRoles.to_string(), id);
-        for ele in roles.iter() {
-            let _: () = conn.sadd(key.clone(), ele.id.unwrap()).await.expect("msg");
-        }
+        // 缓存为空时按直接绑定、用户组和部门继承的角色重建
+        let roles: Vec<RoleEntity> = ex
+            .query_decode(
+                &format!(
+                    "select role.* from role where id in ({USER_ROLE_IDS_SQL}) and role.status = 1"
+                ),
+                user_role_ids_args(id),
+            )
+            .await
+            .expect("获取用户绑定角色失败");
+        let role_ids: Vec<i32> = roles.iter().filter_map(|ele| ele.id).collect();
+        sync_opt::replace_set(RedisKeys::UserRoles.id_key(tenant_id, id), role_ids).await;
         roles
     } else {
         let roles = RoleEntity::select_in_column(&ex, "id", &cache_ids)
@@ -277,9 +330,13 @@ pub async fn get_role_binds(parma: web::Path<i32>) -> impl Responder {
     responses( (status = 200) )
   )]
 #[post("/bind_role")]
-pub async fn bind_role(req_data: web::Json<BindRoleData>) -> Result<impl Responder, MyError> {
-    let check_res = check_role_exists(&req_data.role_id).await;
-    let db_user = check_user_by_user_id(req_data.user_id).await;
+pub async fn bind_role(
+    req_data: web::Json<BindRoleData>,
+    req: HttpRequest,
+) -> Result<impl Responder, MyError> {
+    let tenant_id = get_tenant_id(&req);
+    let check_res = check_role_exists(&req_data.role_id, tenant_id).await;
+    let db_user = check_user_by_user_id(req_data.user_id, tenant_id).await;
     if check_res.is_none() {
         return Err(MyError::RoleNotExist);
     }
@@ -287,49 +344,54 @@ pub async fn bind_role(req_data: web::Json<BindRoleData>) -> Result<impl Respond
         return Err(MyError::UserNotExist);
     }
 
+    let (_, next_roles) = simulate_user_roles(req_data.user_id, &req_data.role_id).await;
+    check_role_change(HashMap::from([(req_data.user_id, next_roles)]), tenant_id).await?;
+
     let (add_ids, sub_ids) = check_user_role_bind(&req_data.user_id, &req_data.role_id).await;
+    check_adm_bind(
+        &get_jwt_from_req(&req),
+        get_adm_role_id().await,
+        &add_ids,
+        &sub_ids,
+    )
+    .await?;
 
     log::debug!("add_ids {add_ids:?}");
     log::debug!("sub_ids {sub_ids:?}");
 
-    if !sub_ids.is_empty() {
-        let tx = RB.acquire_begin().await.expect("msg");
-        unbind_role_from_cache(&req_data.user_id, &sub_ids).await;
+    let clear_all = sub_ids.is_empty() && req_data.role_id.is_empty();
+    let add_tabs: Vec<UserRoleEntity> = user_role_tabs(&req_data.user_id, &add_ids, tenant_id);
+    log::debug!("add_tabs {add_tabs:#?}");
 
-        for id in sub_ids {
-            let sub_res: Result<Option<()>, rbs::Error> = tx
-                .query_decode(
+    if clear_all || !sub_ids.is_empty() || !add_tabs.is_empty() {
+        // 删除、新增和变更事件在同一事务中提交, 失败时整体回滚
+        let tx = RB.acquire_begin().await.expect("msg");
+        let mut bind_res =
+            add_events(&tx, OutboxEvent::UserRoles, &[req_data.user_id], tenant_id).await;
+        if bind_res.is_ok() && clear_all {
+            bind_res = UserRoleEntity::delete_by_column(&tx, "user_id", req_data.user_id)
+                .await
+                .map(|_| ());
+        }
+        for id in sub_ids.iter() {
+            if bind_res.is_err() {
+                break;
+            }
+            bind_res = tx
+                .query_decode::<Option<()>>(
                     "delete from user_role where role_id=? and user_id = ?",
                     vec![to_value!(id), to_value!(req_data.user_id)],
                 )
-                .await;
-            if let Err(rbs::Error::E(error)) = sub_res {
-                log::error!("删除用户角色失败, {error}");
-                tx.rollback().await.expect("msg");
-                return Err(MyError::DelUserRoleError);
-            }
+                .await
+                .map(|_| ());
         }
-        tx.commit().await.expect("msg");
-    } else {
-        if req_data.role_id.is_empty() {
-            let tx = RB.acquire_begin().await.expect("msg");
-            let sub_res = UserRoleEntity::delete_by_column(&tx, "user_id", req_data.user_id).await;
-            tx.commit().await.expect("msg");
-            if let Err(rbs::Error::E(error)) = sub_res {
-                log::error!("删除用户角色失败, {error}");
-                tx.rollback().await.expect("msg");
-                return Err(MyError::DelUserRoleError);
-            }
+        if bind_res.is_ok() && !add_tabs.is_empty() {
+            bind_res = UserRoleEntity::insert_batch(&tx, &add_tabs, add_tabs.len() as u64)
+                .await
+                .map(|_| ());
         }
-    }
-
-    if !add_ids.is_empty() {
-        let add_tabs: Vec<UserRoleEntity> = bind_user_role(&req_data.user_id, &add_ids).await;
-        log::debug!("add_tabs {add_tabs:#?}");
-        let tx = RB.acquire_begin().await.expect("msg");
-        let add_res = UserRoleEntity::insert_batch(&tx, &add_tabs, add_tabs.len() as u64).await;
 
-        if let Err(rbs::Error::E(error)) = add_res {
+        if let Err(rbs::Error::E(error)) = bind_res {
             log::error!("绑定用户角色失败, {error}");
             tx.rollback().await.expect("msg");
             return Err(MyError::BindUserRoleError);
@@ -337,7 +399,8 @@ pub async fn bind_role(req_data: web::Json<BindRoleData>) -> Result<impl Respond
         tx.commit().await.expect("msg");
     }
 
-    sync_user_auth(db_user.unwrap().name).await?;
+    // 提交后按数据库重建角色缓存和权限值
+    refresh_user_roles(req_data.user_id, tenant_id).await?;
 
     Ok(ResponseBody::success("绑定成功"))
 }
@@ -346,36 +409,77 @@ pub async fn bind_role(req_data: web::Json<BindRoleData>) -> Result<impl Respond
     tag = "user",
     responses( (status = 200) )
   )]
-#[get("/get_user_option")]
-pub async fn get_user_option() -> Result<impl Responder, MyError> {
-    let mut conn = redis_conn!().await;
-    let ids: Vec<i32> = conn
-        .smembers(RedisKeys::UserIds.to_string())
+#[post("/bind_role_dry_run")]
+pub async fn bind_role_dry_run(
+    req_data: web::Json<BindRoleData>,
+    req: HttpRequest,
+) -> Result<impl Responder, MyError> {
+    let tenant_id = get_tenant_id(&req);
+    if check_role_exists(&req_data.role_id, tenant_id)
         .await
-        .expect("msg");
-    if !ids.is_empty() {
-        let res: Vec<OptionData> = rds_str_to_list(ids, RedisKeys::UserInfo, |val| {
-            let user_data: OptionData = serde_json::from_str(&val).expect("msg");
-            user_data
-        })
-        .await;
+        .is_none()
+    {
+        return Err(MyError::RoleNotExist);
+    }
+    let db_user = check_user_by_user_id(req_data.user_id, tenant_id)
+        .await
+        .ok_or(MyError::UserNotExist)?;
+
+    let (add_ids, sub_ids) = check_user_role_bind(&req_data.user_id, &req_data.role_id).await;
+    let (current_roles, next_roles) =
+        simulate_user_roles(req_data.user_id, &req_data.role_id).await;
+    let all_roles: Vec<i32> = current_roles.iter().chain(&next_roles).copied().collect();
+    let map = get_role_access_map(&all_roles).await;
+    let change = gen_user_change(
+        OptionData::default(&db_user.name, req_data.user_id),
+        &collect_access(&current_roles, &map),
+        &collect_access(&next_roles, &map),
+    )
+    .await;
+
+    Ok(ResponseBody::default(Some(BindSimRes {
+        add_ids,
+        sub_ids,
+        users: change.into_iter().collect(),
+    })))
+}
+
+#[utoipa::path(
+    tag = "user",
+    responses( (status = 200) )
+  )]
+#[get("/get_user_option")]
+pub async fn get_user_option(req: HttpRequest) -> Result<impl Responder, MyError> {
+    let tenant_id = get_tenant_id(&req);
+    let ids: Vec<i32> = smembers_ids(&RedisKeys::UserIds.key(tenant_id)).await;
+    let cache_res: Option<Vec<OptionData>> =
+        rds_str_to_list(ids, RedisKeys::UserInfo, tenant_id).await;
+    if let Some(res) = cache_res {
         return Ok(ResponseBody::default(Some(res)));
-    } else {
-        let ex_db = RB.acquire().await.expect("get ex err");
-        let opt: Vec<OptionData> = ex_db
-            .query_decode("select id, name from user where status = 1", vec![])
-            .await
-            .expect("select db err");
+    }
 
-        for ele in opt.iter() {
-            sync_opt::sync(SyncOptData::default(
+    // 缓存为空或无法解析时从数据库重建
+    let ex_db = RB.acquire().await.expect("get ex err");
+    let opt: Vec<OptionData> = ex_db
+        .query_decode(
+            "select id, name from user where status = 1 and tenant_id = ?",
+            vec![to_value!(tenant_id)],
+        )
+        .await
+        .expect("select db err");
+
+    let list = opt
+        .iter()
+        .map(|ele| {
+            SyncOptData::default(
                 RedisKeys::UserIds,
                 RedisKeys::UserInfo,
+                tenant_id,
                 ele.id,
                 ele.clone(),
-            ))
-            .await;
-        }
-        Ok(ResponseBody::default(Some(opt)))
-    }
+            )
+        })
+        .collect();
+    sync_opt::sync_list(list).await;
+    Ok(ResponseBody::default(Some(opt)))
 }
@@ -0,0 +1,169 @@
+use actix_web::{
+    body::MessageBody,
+    dev::{ServiceRequest, ServiceResponse},
+    middleware::Next,
+    Error,
+};
+use serde::Serialize;
+use utoipa::openapi::{extensions::ExtensionsBuilder, path::Operation, OpenApi, PathItem};
+
+use super::common::{get_jwt_from_req, get_tenant_id};
+use crate::{
+    access, cache_check, constraint, cron, group, menu, operation, org, policy, rbac,
+    response::MyError, role, user, user::auth_service::has_route_access,
+};
+
+/// 接口所需的权限
+///
+/// path 为完整的路由模板, 与 `match_pattern` 一致, 例如 `/api/role/{id}`
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct RouteAccess {
+    pub method: &'static str,
+    pub path: &'static str,
+    pub access: &'static str,
+}
+
+impl RouteAccess {
+    pub const fn new(method: &'static str, path: &'static str, access: &'static str) -> Self {
+        Self {
+            method,
+            path,
+            access,
+        }
+    }
+}
+
+/// 所有声明了权限的接口
+pub fn route_access_list() -> Vec<RouteAccess> {
+    [
+        user::ROUTE_ACCESS,
+        role::ROUTE_ACCESS,
+        access::ROUTE_ACCESS,
+        rbac::ROUTE_ACCESS,
+        policy::ROUTE_ACCESS,
+        org::ROUTE_ACCESS,
+        group::ROUTE_ACCESS,
+        constraint::ROUTE_ACCESS,
+        menu::ROUTE_ACCESS,
+        operation::ROUTE_ACCESS,
+        cron::ROUTE_ACCESS,
+        cache_check::ROUTE_ACCESS,
+    ]
+    .concat()
+}
+
+pub fn find_route_access(method: &str, path: &str) -> Option<RouteAccess> {
+    route_access_list()
+        .into_iter()
+        .find(|route| route.method == method && route.path == path)
+}
+
+/// 在文档中为声明了权限的接口加上 `x-required-access` 扩展
+pub fn add_route_access_doc(api: &mut OpenApi) {
+    for route in route_access_list() {
+        let operation = api
+            .paths
+            .paths
+            .get_mut(route.path)
+            .and_then(|item| path_operation(item, route.method));
+        match operation {
+            None => log::warn!("文档中找不到接口 {} {}", route.method, route.path),
+            Some(operation) => {
+                let ext = ExtensionsBuilder::new()
+                    .add("x-required-access", route.access)
+                    .build();
+                match operation.extensions.as_mut() {
+                    Some(extensions) => extensions.merge(ext),
+                    None => operation.extensions = Some(ext),
+                }
+            }
+        }
+    }
+}
+
+fn path_operation<'a>(item: &'a mut PathItem, method: &str) -> Option<&'a mut Operation> {
+    match method {
+        "GET" => item.get.as_mut(),
+        "POST" => item.post.as_mut(),
+        "PUT" => item.put.as_mut(),
+        "DELETE" => item.delete.as_mut(),
+        _ => None,
+    }
+}
+
+/// 接口权限中间件, 需在 jwt 校验之后执行
+///
+/// 未声明权限的接口直接放行; 调用者的权限值不包含所需权限时返回 `AuthError`
+pub async fn access_guard(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let route = req
+        .match_pattern()
+        .and_then(|pattern| find_route_access(req.method().as_str(), &pattern));
+
+    if let Some(route) = route {
+        if req.headers().get("Authorization").is_none() {
+            return Err(MyError::AuthError.into());
+        }
+        let login = get_jwt_from_req(req.request());
+        let tenant_id = get_tenant_id(req.request());
+        if !has_route_access(&login, tenant_id, route.access).await {
+            log::info!(
+                "用户 {} 无权访问 {} {}, 需要权限 {}",
+                login.name,
+                route.method,
+                route.path,
+                route.access
+            );
+            return Err(MyError::AuthError.into());
+        }
+    }
+
+    next.call(req).await
+}
+
+#[cfg(test)]
+mod test {
+    use utoipa::openapi::{
+        path::{HttpMethod, OperationBuilder},
+        OpenApiBuilder, PathItem, PathsBuilder,
+    };
+
+    use super::{add_route_access_doc, find_route_access, route_access_list};
+
+    #[test]
+    fn test_route_access_unique() {
+        let list = route_access_list();
+        for (idx, route) in list.iter().enumerate() {
+            let dup = list[idx + 1..]
+                .iter()
+                .any(|val| val.method == route.method && val.path == route.path);
+            assert!(!dup, "重复声明 {} {}", route.method, route.path);
+        }
+    }
+
+    #[test]
+    fn test_find_route_access() {
+        let route = find_route_access("DELETE", "/api/role/{id}").unwrap();
+        assert_eq!(route.access, "ROLE_DELETE");
+        assert!(find_route_access("GET", "/api/role/{id}").is_none());
+        assert!(find_route_access("POST", "/api/auth/login").is_none());
+    }
+
+    #[test]
+    fn test_add_route_access_doc() {
+        let paths = PathsBuilder::new()
+            .path(
+                "/api/role/{id}",
+                PathItem::new(HttpMethod::Delete, OperationBuilder::new().build()),
+            )
+            .build();
+        let mut api = OpenApiBuilder::new().paths(paths).build();
+        add_route_access_doc(&mut api);
+
+        let operation = api.paths.paths["/api/role/{id}"].delete.as_ref().unwrap();
+        let ext = operation.extensions.as_ref().unwrap();
+        assert_eq!(ext["x-required-access"], "ROLE_DELETE");
+    }
+}
@@ -1,13 +1,13 @@
+use crate::cache::{cache, id_args, CacheStore};
 use crate::response::MyError;
 use crate::user::RedisLoginData;
-use crate::RB;
+use crate::{RB, REDIS_KEY};
 use actix_web::HttpRequest;
 use derive_more::derive::Display;
 use lazy_regex::regex;
 use rbatis::executor::RBatisTxExecutorGuard;
-use redis::AsyncCommands;
 use rs_service_util::jwt::jwt_token_to_data;
-use rs_service_util::redis_conn;
+use serde::{de::DeserializeOwned, Deserialize};
 
 #[derive(Debug, Display, Clone)]
 pub enum RedisKeys {
@@ -34,8 +34,45 @@ pub enum RedisKeys {
 
     #[display("access_map_ids")]
     AccessMapIds,
+
+    #[display("operation")]
+    Operation,
+
+    #[display("operation_seq")]
+    OperationSeq,
+
+    #[display("login")]
+    Login,
+}
+
+/// 缓存结构版本, 缓存中的结构 (字段、类型) 变化时加一
+///
+/// 版本是 key 的一部分, 升级后读不到旧 key, 按缓存未命中从数据库重建
+pub const CACHE_SCHEMA_VERSION: u32 = 1;
+
+/// 当前版本 key 的命名空间, 例如 `user_service:v1`
+pub fn cache_namespace() -> String {
+    format!("{}:v{}", REDIS_KEY.to_string(), CACHE_SCHEMA_VERSION)
 }
 
+impl RedisKeys {
+    /// 租户隔离后的 key, 例如 `user_service:v1:1:user_ids`
+    pub fn key(&self, tenant_id: i32) -> String {
+        format!("{}:{}:{}", cache_namespace(), tenant_id, self)
+    }
+
+    /// 租户隔离后带 id 的 key, 例如 `user_service:v1:1:user_roles_5`
+    pub fn id_key(&self, tenant_id: i32, id: i32) -> String {
+        format!("{}:{}:{}_{}", cache_namespace(), tenant_id, self, id)
+    }
+}
+
+/// 默认租户, 平台自身的数据都在该租户下
+pub const DEFAULT_TENANT_ID: i32 = 0;
+
+/// 指定租户的请求头
+pub const TENANT_HEADER: &str = "X-Tenant-Id";
+
 /// 检测手机号是否合法
 pub fn check_phone(phone: &str) -> bool {
     let max_len = 11;
@@ -48,6 +85,27 @@ pub fn check_phone(phone: &str) -> bool {
     r.is_match(phone)
 }
 
+/// 对比目标id和现有id, 返回 (需要新增的, 需要删除的)
+///
+/// target      current
+///
+/// [1,2]       [1,2,3,4]    remove 3,4
+///
+/// [1,2 ,5]    [1,2,3,4]    remove 3,4 add 5
+pub fn diff_ids(target: &[i32], current: &[i32]) -> (Vec<i32>, Vec<i32>) {
+    let add_ids: Vec<i32> = target
+        .iter()
+        .filter(|id| !current.contains(id))
+        .copied()
+        .collect();
+    let sub_ids: Vec<i32> = current
+        .iter()
+        .filter(|id| !target.contains(id))
+        .copied()
+        .collect();
+    (add_ids, sub_ids)
+}
+
 pub async fn get_transaction_tx() -> Result<RBatisTxExecutorGuard, MyError> {
     let tx = RB.acquire_begin().await.unwrap();
     let tx: RBatisTxExecutorGuard = tx.defer_async(|ex| async move {
@@ -66,24 +124,51 @@ pub async fn get_transaction_tx() -> Result<RBatisTxExecutorGuard, MyError> {
     Ok(tx)
 }
 
-pub async fn rds_str_to_list<T, U: Fn(String) -> T>(
+/// 按 id 读取信息 hash 并解析, 缓存中没有的 id 跳过
+///
+/// id 为空或有内容无法解析 (缓存结构变化) 时返回 None, 由调用方从数据库重建
+pub async fn rds_str_to_list<T: DeserializeOwned>(
     ids: Vec<i32>,
     key: RedisKeys,
-    cb: U,
-) -> Vec<T> {
-    let mut conn = redis_conn!().await;
-    let mut res: Vec<T> = vec![];
-    for id in ids {
-        let data = conn.hget(key.clone().to_string(), id).await.expect("msg");
-        if let Some(str) = data {
-            let item: T = cb(str);
-            res.push(item);
-        }
+    tenant_id: i32,
+) -> Option<Vec<T>> {
+    if ids.is_empty() {
+        return None;
+    }
+    let mut list: Vec<T> = vec![];
+    for val in hmget_by_ids(key, tenant_id, &ids)
+        .await
+        .into_iter()
+        .flatten()
+    {
+        list.push(serde_json::from_str(&val).ok()?);
     }
-    res
+    Some(list)
+}
+
+/// 用 HMGET 一次读取 hash 中的多个 id, 按 ids 的顺序返回
+pub async fn hmget_by_ids(key: RedisKeys, tenant_id: i32, ids: &[i32]) -> Vec<Option<String>> {
+    cache().hmget(&key.key(tenant_id), &id_args(ids)).await
+}
+
+/// id 是否在 id 集合或信息 hash 中, 按 ids 的顺序返回
+pub async fn cache_ids_exist(
+    set_key: RedisKeys,
+    hash_key: RedisKeys,
+    tenant_id: i32,
+    ids: &[i32],
+) -> Vec<bool> {
+    let args = id_args(ids);
+    let in_ids = cache().smismember(&set_key.key(tenant_id), &args).await;
+    let infos = cache().hmget(&hash_key.key(tenant_id), &args).await;
+    in_ids
+        .into_iter()
+        .zip(infos)
+        .map(|(in_ids, info)| in_ids || info.is_some())
+        .collect()
 }
 
-pub fn get_jwt_from_req(req: HttpRequest) -> RedisLoginData {
+pub fn get_jwt_from_req(req: &HttpRequest) -> RedisLoginData {
     let token = req.headers().get("Authorization").expect("get token error");
     let binding = token.to_owned();
     let jwt_token = binding.to_str().expect("msg").to_string();
@@ -92,12 +177,59 @@ pub fn get_jwt_from_req(req: HttpRequest) -> RedisLoginData {
     jwt_user
 }
 
+/// 解析请求所属的租户
+///
+/// 普通用户只能访问 token 中的租户, 超级管理员可以通过 `X-Tenant-Id` 切换租户;
+/// 未登录的请求(如登录接口)以请求头为准, 缺省为默认租户
+pub fn get_tenant_id(req: &HttpRequest) -> i32 {
+    let header_tenant: Option<i32> = req
+        .headers()
+        .get(TENANT_HEADER)
+        .and_then(|val| val.to_str().ok())
+        .and_then(|val| val.trim().parse().ok());
+
+    if req.headers().get("Authorization").is_none() {
+        return header_tenant.unwrap_or(DEFAULT_TENANT_ID);
+    }
+
+    let jwt_user = get_jwt_from_req(req);
+    if jwt_user.is_super_admin() {
+        return header_tenant.unwrap_or(jwt_user.tenant_id);
+    }
+    jwt_user.tenant_id
+}
+
+#[derive(Deserialize)]
+struct TenantIdRes {
+    tenant_id: i32,
+}
+
+/// 有数据的所有租户
+pub async fn get_tenant_ids() -> Vec<i32> {
+    let ex = RB.acquire().await.expect("msg");
+    let tenants: Vec<TenantIdRes> = ex
+        .query_decode(
+            "select tenant_id from user union select tenant_id from role union select tenant_id from access",
+            vec![],
+        )
+        .await
+        .expect("查询租户失败");
+    tenants.into_iter().map(|val| val.tenant_id).collect()
+}
+
 #[cfg(test)]
 mod test {
 
     use rs_service_util::auth::gen_access_value;
 
-    use crate::util::common::check_phone;
+    use crate::{
+        cache::{cache, init_memory_cache, CacheOp, CacheStore},
+        util::common::{
+            cache_ids_exist, check_phone, diff_ids, hmget_by_ids, rds_str_to_list, RedisKeys,
+            CACHE_SCHEMA_VERSION,
+        },
+        REDIS_KEY,
+    };
 
     #[test]
     fn test_check_phone_length_less() {
@@ -126,6 +258,98 @@ mod test {
         let res = check_phone(phone);
         assert_eq!(res, true);
     }
+    #[test]
+    fn test_diff_ids() {
+        assert_eq!(diff_ids(&[1, 2], &[1, 2, 3, 4]), (vec![], vec![3, 4]));
+        assert_eq!(diff_ids(&[1, 2, 5], &[1, 2, 3, 4]), (vec![5], vec![3, 4]));
+        assert_eq!(diff_ids(&[1, 2], &[]), (vec![1, 2], vec![]));
+    }
+
+    #[test]
+    fn test_redis_key_tenant_scope() {
+        // 前缀来自环境变量 `CACHE_PREFIX`, 不写死
+        let ns = format!("{}:v{}", REDIS_KEY.to_string(), CACHE_SCHEMA_VERSION);
+        assert_eq!(RedisKeys::UserIds.key(3), format!("{ns}:3:user_ids"));
+        assert_eq!(
+            RedisKeys::UserRoles.id_key(3, 12),
+            format!("{ns}:3:user_roles_12")
+        );
+        assert_eq!(RedisKeys::Login.id_key(3, 5), format!("{ns}:3:login_5"));
+    }
+
+    #[actix_rt::test]
+    async fn test_hmget_by_ids() {
+        init_memory_cache();
+        let tenant_id = 451;
+        let hash_key = RedisKeys::RoleInfo.key(tenant_id);
+        cache()
+            .exec(vec![
+                CacheOp::HSet(hash_key.clone(), "1".to_string(), "11".to_string()),
+                CacheOp::HSet(hash_key.clone(), "2".to_string(), "22".to_string()),
+            ])
+            .await;
+
+        // 按 ids 的顺序返回, 缺失的为 None
+        assert_eq!(
+            hmget_by_ids(RedisKeys::RoleInfo, tenant_id, &[2, 9, 1]).await,
+            vec![Some("22".to_string()), None, Some("11".to_string())]
+        );
+        assert!(hmget_by_ids(RedisKeys::RoleInfo, tenant_id, &[])
+            .await
+            .is_empty());
+    }
+
+    #[actix_rt::test]
+    async fn test_cache_ids_exist() {
+        init_memory_cache();
+        let tenant_id = 452;
+        cache()
+            .exec(vec![
+                CacheOp::SAdd(RedisKeys::RoleIds.key(tenant_id), vec!["1".to_string()]),
+                CacheOp::HSet(
+                    RedisKeys::RoleInfo.key(tenant_id),
+                    "2".to_string(),
+                    "{}".to_string(),
+                ),
+            ])
+            .await;
+
+        // 在 id 集合或信息 hash 中任一处即存在
+        let res = cache_ids_exist(
+            RedisKeys::RoleIds,
+            RedisKeys::RoleInfo,
+            tenant_id,
+            &[3, 1, 2],
+        )
+        .await;
+        assert_eq!(res, vec![false, true, true]);
+    }
+
+    #[actix_rt::test]
+    async fn test_rds_str_to_list() {
+        init_memory_cache();
+        let tenant_id = 453;
+        let hash_key = RedisKeys::RoleInfo.key(tenant_id);
+        cache()
+            .exec(vec![
+                CacheOp::HSet(hash_key.clone(), "1".to_string(), "11".to_string()),
+                CacheOp::HSet(hash_key.clone(), "2".to_string(), "22".to_string()),
+                CacheOp::HSet(hash_key.clone(), "3".to_string(), "bad".to_string()),
+            ])
+            .await;
+
+        let list: Option<Vec<i32>> =
+            rds_str_to_list(vec![2, 1], RedisKeys::RoleInfo, tenant_id).await;
+        assert_eq!(list, Some(vec![22, 11]));
+
+        // 内容无法解析时返回 None, 由调用方从数据库重建
+        let list: Option<Vec<i32>> =
+            rds_str_to_list(vec![1, 3], RedisKeys::RoleInfo, tenant_id).await;
+        assert_eq!(list, None);
+        let list: Option<Vec<i32>> = rds_str_to_list(vec![], RedisKeys::RoleInfo, tenant_id).await;
+        assert_eq!(list, None);
+    }
+
     #[test]
     fn test_access_value() {
         // let role_p = [64, 1024];
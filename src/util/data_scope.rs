@@ -0,0 +1,123 @@
+use rbs::to_value;
+use rs_service_util::sql_tool::SqlTool;
+use serde::{Deserialize, Serialize};
+
+use super::structs::DataScope;
+use crate::{
+    response::MyError,
+    user::{
+        auth_service::{get_login_auth, is_adm_auth},
+        user_role_service::{user_role_ids_args, USER_ROLE_IDS_SQL},
+        RedisLoginData,
+    },
+    RB,
+};
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct DataScopeRes {
+    data_scope: Option<i8>,
+}
+
+/// 用户的数据范围, 取所有有效角色中最大的范围; 没有角色时只能看自己的数据
+///
+/// ALL 只对超级管理员生效, 其他用户的角色最大到 TENANT
+pub async fn get_user_data_scope(login: &RedisLoginData) -> DataScope {
+    let ex = RB.acquire().await.expect("get ex error");
+    let res: Option<DataScopeRes> = ex
+        .query_decode(
+            &format!("select max(role.data_scope) as data_scope from role where id in ({USER_ROLE_IDS_SQL}) and role.status=1"),
+            user_role_ids_args(login.id),
+        )
+        .await
+        .expect("查询数据范围失败");
+
+    let scope = res
+        .and_then(|val| val.data_scope)
+        .map(DataScope::from)
+        .unwrap_or(DataScope::SELF);
+    if scope == DataScope::ALL && !login.is_super_admin() {
+        return DataScope::TENANT;
+    }
+    scope
+}
+
+/// 只有持有 ADMIN 权限的调用者可以授予 ALL 范围, 避免租户内的角色管理员越权
+pub async fn check_scope_grant(login: &RedisLoginData, data_scope: i8) -> Result<(), MyError> {
+    if !is_adm_scope(data_scope) {
+        return Ok(());
+    }
+    if !is_adm_auth(get_login_auth(login).await).await {
+        return Err(MyError::DataScopeForbidden);
+    }
+    Ok(())
+}
+
+/// 需要 ADMIN 权限才能授予的数据范围
+fn is_adm_scope(data_scope: i8) -> bool {
+    DataScope::from(data_scope) == DataScope::ALL
+}
+
+/// 列表查询的数据来源
+///
+/// DEPT 范围下为子查询: 归属人是调用者所在部门及其子部门成员的数据,
+/// 其余范围为原表; 子查询只拼接整数id, 不存在注入问题
+pub fn scope_table(
+    table: &str,
+    scope: &DataScope,
+    owner_field: &str,
+    login: &RedisLoginData,
+) -> String {
+    match scope {
+        DataScope::DEPT => format!(
+            "(select * from {table} where {owner_field} in (\
+            select member.user_id from user_org member \
+            inner join org_unit member_org on member_org.id = member.org_id and member_org.status = 1 \
+            inner join user_org mine on mine.user_id = {} \
+            inner join org_unit mine_org on mine_org.id = mine.org_id and mine_org.status = 1 \
+            where member_org.path like concat(mine_org.path, '%') \
+            union select {})) as {table}",
+            login.id, login.id
+        ),
+        _ => table.to_string(),
+    }
+}
+
+/// 按数据范围追加列表查询条件
+///
+/// owner_field: 表示数据归属人的字段, 如 role 表的 `create_by`;
+/// 只有超级管理员的 ALL 不限制租户, 其余情况都按 tenant_id 过滤
+pub fn append_scope_filter(
+    tool: &mut SqlTool,
+    scope: &DataScope,
+    owner_field: &str,
+    login: &RedisLoginData,
+    tenant_id: i32,
+) {
+    match scope {
+        DataScope::SELF => {
+            tool.append_sql_filed(owner_field, to_value!(login.id));
+            tool.append_sql_filed("tenant_id", to_value!(tenant_id));
+        }
+        DataScope::ALL if login.is_super_admin() => {}
+        DataScope::DEPT | DataScope::TENANT | DataScope::ALL => {
+            tool.append_sql_filed("tenant_id", to_value!(tenant_id));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::is_adm_scope;
+    use crate::util::structs::DataScope;
+
+    #[test]
+    fn test_is_adm_scope() {
+        // 创建、复制或修改为 ALL 的角色需要调用者持有 ADMIN 权限
+        assert!(is_adm_scope(DataScope::ALL as i8));
+        assert!(!is_adm_scope(DataScope::TENANT as i8));
+        assert!(!is_adm_scope(DataScope::DEPT as i8));
+        assert!(!is_adm_scope(DataScope::SELF as i8));
+        // 未知的值按 SELF 处理
+        assert!(!is_adm_scope(9));
+    }
+}
@@ -0,0 +1,63 @@
+use actix_web::{
+    body::{BoxBody, MessageBody},
+    dev::{ServiceRequest, ServiceResponse},
+    http::Method,
+    middleware::Next,
+    Error,
+};
+use rs_service_util::jwt::jwt_token_to_data;
+
+use crate::{
+    cache::{cache, CacheStore},
+    response::MyError,
+    user::RedisLoginData,
+    util::common::RedisKeys,
+};
+
+/// 不需要登录的接口, 按前缀匹配
+const WHITE_LIST: &[&str] = &["/api/auth/login", "/doc"];
+
+/// jwt 中间件, 校验 token 可解析且登录缓存存在
+///
+/// 登录缓存按租户和用户 id 存放 (`RedisKeys::Login`), 不再使用 `jwt_mw`;
+/// 除白名单和预检请求外不带 token 的请求直接拒绝
+pub async fn jwt_guard(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<BoxBody>, Error> {
+    if !is_white_list(req.method(), req.path()) {
+        let login: RedisLoginData = req
+            .headers()
+            .get("Authorization")
+            .and_then(|token| token.to_str().ok())
+            .and_then(|val| val.strip_prefix("Bearer "))
+            .and_then(|val| jwt_token_to_data(val.to_owned()).ok())
+            .ok_or(MyError::AuthError)?;
+        let key = RedisKeys::Login.id_key(login.tenant_id, login.id);
+        if !cache().exists(&key).await {
+            return Err(MyError::AuthError.into());
+        }
+    }
+    let res = next.call(req).await?;
+    Ok(res.map_into_boxed_body())
+}
+
+fn is_white_list(method: &Method, path: &str) -> bool {
+    *method == Method::OPTIONS || WHITE_LIST.iter().any(|val| path.starts_with(val))
+}
+
+#[cfg(test)]
+mod test {
+    use actix_web::http::Method;
+
+    use super::is_white_list;
+
+    #[test]
+    fn test_is_white_list() {
+        assert!(is_white_list(&Method::POST, "/api/auth/login"));
+        assert!(is_white_list(&Method::GET, "/doc"));
+        assert!(is_white_list(&Method::OPTIONS, "/api/user/create_user"));
+        assert!(!is_white_list(&Method::POST, "/api/user/create_user"));
+        assert!(!is_white_list(&Method::GET, "/api/auth/me"));
+    }
+}
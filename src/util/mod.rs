@@ -1,3 +1,7 @@
+pub mod access_guard;
 pub mod common;
+pub mod data_scope;
+pub mod jwt_guard;
+pub mod outbox;
 pub mod structs;
-pub mod sync_opt;
\ No newline at end of file
+pub mod sync_opt;
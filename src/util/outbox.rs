@@ -0,0 +1,32 @@
+use rbatis::executor::Executor;
+use rs_service_util::time::get_current_time_fmt;
+
+use super::structs::OutboxEvent;
+use crate::entity::cache_outbox_entity::CacheOutboxEntity;
+
+/// 在业务事务中写入缓存变更事件, 事务提交后由 worker 重建相关缓存
+///
+/// 接口中的即时缓存更新仍然保留, 事件保证进程在提交后、更新缓存前退出时缓存最终一致
+pub async fn add_events(
+    tx: &dyn Executor,
+    event: OutboxEvent,
+    target_ids: &[i32],
+    tenant_id: i32,
+) -> Result<(), rbs::Error> {
+    if target_ids.is_empty() {
+        return Ok(());
+    }
+    let create_time = get_current_time_fmt();
+    let tabs: Vec<CacheOutboxEntity> = target_ids
+        .iter()
+        .map(|id| CacheOutboxEntity {
+            id: None,
+            create_time: create_time.clone(),
+            event_type: event as i8,
+            target_id: *id,
+            tenant_id,
+        })
+        .collect();
+    CacheOutboxEntity::insert_batch(tx, &tabs, tabs.len() as u64).await?;
+    Ok(())
+}
@@ -31,6 +31,110 @@ impl Status {
     }
 }
 
+/// 数据范围, 值越大可见范围越大
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, PartialOrd)]
+#[serde(rename = "Enum")]
+pub enum DataScope {
+    SELF = 1,
+    DEPT = 2,
+    TENANT = 3,
+    ALL = 4,
+}
+
+impl DataScope {
+    pub fn from(val: i8) -> DataScope {
+        match val {
+            2 => DataScope::DEPT,
+            3 => DataScope::TENANT,
+            4 => DataScope::ALL,
+            _ => DataScope::SELF,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename = "Enum")]
+pub enum PolicyEffect {
+    ALLOW = 1,
+    DENY = 0,
+}
+
+impl PolicyEffect {
+    pub fn from(val: i8) -> PolicyEffect {
+        match val {
+            1 => PolicyEffect::ALLOW,
+            _ => PolicyEffect::DENY,
+        }
+    }
+}
+
+/// 角色约束类型
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename = "Enum")]
+pub enum ConstraintType {
+    MUTEX = 1,       // 同一用户最多持有其中一个角色
+    CARDINALITY = 2, // 持有这些角色的用户数不超过 max_count
+}
+
+impl ConstraintType {
+    pub fn from(val: i8) -> Option<ConstraintType> {
+        match val {
+            1 => Some(ConstraintType::MUTEX),
+            2 => Some(ConstraintType::CARDINALITY),
+            _ => None,
+        }
+    }
+}
+
+/// 菜单类型
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename = "Enum")]
+pub enum MenuType {
+    PAGE = 1,
+    BUTTON = 2,
+}
+
+impl MenuType {
+    pub fn from(val: i8) -> Option<MenuType> {
+        match val {
+            1 => Some(MenuType::PAGE),
+            2 => Some(MenuType::BUTTON),
+            _ => None,
+        }
+    }
+}
+
+/// 后台任务状态
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename = "Enum")]
+pub enum OperationStatus {
+    RUNNING = 1,
+    SUCCESS = 2,
+    FAILED = 3, // 部分项执行失败
+}
+
+/// 缓存变更事件类型
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename = "Enum")]
+pub enum OutboxEvent {
+    UserRoles = 1,    // 重建用户角色缓存并刷新登录权限值
+    RoleAccess = 2,   // 重建角色权限缓存并刷新拥有该角色的用户
+    GroupMembers = 3, // 刷新用户组成员
+    OrgMembers = 4,   // 刷新部门及其子部门成员
+}
+
+impl OutboxEvent {
+    pub fn from(val: i8) -> Option<OutboxEvent> {
+        match val {
+            1 => Some(OutboxEvent::UserRoles),
+            2 => Some(OutboxEvent::RoleAccess),
+            3 => Some(OutboxEvent::GroupMembers),
+            4 => Some(OutboxEvent::OrgMembers),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug, Deserialize, Serialize, ToSchema)]
 pub struct DeployInfo {
     pub deployment_name: String,
@@ -1,7 +1,6 @@
-use super::common::RedisKeys;
+use super::common::{cache_namespace, RedisKeys};
 
-use redis::AsyncCommands;
-use rs_service_util::redis_conn;
+use crate::cache::{cache, id_args, CacheOp, CacheStore};
 use serde::Serialize;
 
 pub struct SyncOptData<T> {
@@ -11,10 +10,16 @@ pub struct SyncOptData<T> {
     pub opt_data: T,
 }
 impl<T: Serialize> SyncOptData<T> {
-    pub fn default(set_key: RedisKeys, hmap_key: RedisKeys, id: i32, opt_data: T) -> Self {
+    pub fn default(
+        set_key: RedisKeys,
+        hmap_key: RedisKeys,
+        tenant_id: i32,
+        id: i32,
+        opt_data: T,
+    ) -> Self {
         Self {
-            set_key: set_key.to_string(),
-            hmap_key: hmap_key.to_string(),
+            set_key: set_key.key(tenant_id),
+            hmap_key: hmap_key.key(tenant_id),
             opt_data,
             id,
         }
@@ -22,16 +27,28 @@ impl<T: Serialize> SyncOptData<T> {
 }
 
 pub async fn sync<T: Serialize>(data: SyncOptData<T>) {
-    let mut conn = redis_conn!().await;
-    let _: () = conn.sadd(data.set_key, data.id).await.expect("msg");
-    let _: () = conn
-        .hset(
-            data.hmap_key,
-            data.id,
-            serde_json::to_string(&data.opt_data).expect("msg"),
-        )
-        .await
-        .expect("msg");
+    sync_list(vec![data]).await;
+}
+
+/// 批量写入 id 集合和信息 hash, 在一个 MULTI 中执行
+pub async fn sync_list<T: Serialize>(list: Vec<SyncOptData<T>>) {
+    if list.is_empty() {
+        return;
+    }
+    let mut ops: Vec<CacheOp> = vec![];
+    for data in list.iter() {
+        let json = serde_json::to_string(&data.opt_data).expect("msg");
+        ops.push(CacheOp::SAdd(
+            data.set_key.clone(),
+            vec![data.id.to_string()],
+        ));
+        ops.push(CacheOp::HSet(
+            data.hmap_key.clone(),
+            data.id.to_string(),
+            json,
+        ));
+    }
+    cache().exec(ops).await;
 }
 
 pub struct DelOptData {
@@ -41,17 +58,76 @@ pub struct DelOptData {
 }
 
 impl DelOptData {
-    pub fn default(set_key: RedisKeys, hmap_key: RedisKeys, id: Vec<i32>) -> Self {
+    pub fn default(set_key: RedisKeys, hmap_key: RedisKeys, tenant_id: i32, id: Vec<i32>) -> Self {
         Self {
-            set_key: set_key.to_string(),
-            hmap_key: hmap_key.to_string(),
+            set_key: set_key.key(tenant_id),
+            hmap_key: hmap_key.key(tenant_id),
             id,
         }
     }
 }
 
 pub async fn del(data: DelOptData) {
-    let mut conn = redis_conn!().await;
-    let _: () = conn.srem(data.set_key, &data.id).await.expect("msg");
-    let _: () = conn.hdel(data.hmap_key, &data.id).await.expect("msg");
+    if data.id.is_empty() {
+        return;
+    }
+    let ids = id_args(&data.id);
+    cache()
+        .exec(vec![
+            CacheOp::SRem(data.set_key, ids.clone()),
+            CacheOp::HDel(data.hmap_key, ids),
+        ])
+        .await;
+}
+
+/// 每个 MULTI 中替换的集合数
+const REPLACE_BATCH: usize = 500;
+
+/// 用新成员原子替换整个集合, 成员为空时删除 key
+pub async fn replace_set(key: String, members: Vec<i32>) {
+    replace_sets(vec![(key, members)]).await;
+}
+
+/// 批量替换集合, 每批在一个 MULTI 中执行
+///
+/// 新成员先写入临时 key 再 `RENAME` 覆盖原 key, 读取方不会看到清空后未写入的中间状态
+pub async fn replace_sets(list: Vec<(String, Vec<i32>)>) {
+    for chunk in list.chunks(REPLACE_BATCH) {
+        let mut ops: Vec<CacheOp> = vec![];
+        for (key, members) in chunk {
+            if members.is_empty() {
+                ops.push(CacheOp::Del(key.clone()));
+                continue;
+            }
+            let tmp_key = tmp_key(key);
+            ops.push(CacheOp::Del(tmp_key.clone()));
+            ops.push(CacheOp::SAdd(tmp_key.clone(), id_args(members)));
+            ops.push(CacheOp::Rename(tmp_key, key.clone()));
+        }
+        cache().exec(ops).await;
+    }
+}
+
+/// 临时 key 放在单独的 `{命名空间}:tmp:` 下, 不会被按 `user_roles_` 等前缀扫描到
+fn tmp_key(key: &str) -> String {
+    let ns = cache_namespace();
+    let name = key
+        .strip_prefix(&ns)
+        .and_then(|val| val.strip_prefix(':'))
+        .unwrap_or(key);
+    format!("{ns}:tmp:{name}")
+}
+
+#[cfg(test)]
+mod test {
+    use super::tmp_key;
+    use crate::util::common::{cache_namespace, RedisKeys};
+
+    #[test]
+    fn test_tmp_key() {
+        let ns = cache_namespace();
+        let key = RedisKeys::UserRoles.id_key(3, 12);
+        assert_eq!(tmp_key(&key), format!("{ns}:tmp:3:user_roles_12"));
+        assert!(!tmp_key(&key).starts_with(&RedisKeys::UserRoles.key(3)));
+    }
 }